@@ -0,0 +1,169 @@
+//! Compares palette-compressed chunks with a plain array of block ids.
+//!
+//! Run with `cargo bench --bench chunk_storage`; memory use is printed before the
+//! timings.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use cubix::config::GenerationConfig;
+use cubix::world::block::BlockId;
+use cubix::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos, WorldHeight};
+use cubix::world::generation::TerrainGenerator;
+use cubix::world::generation::noise::SplitMix64;
+use cubix::world::registry::BlockRegistry;
+
+/// The height of the chunks used before they were split into sections, which
+/// keeps the comparison to a single section.
+const HEIGHT: usize = 16;
+const VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * HEIGHT;
+
+/// The layout chunks used before they were palette-compressed.
+struct RawChunk {
+    blocks: Box<[BlockId; VOLUME]>,
+}
+
+impl RawChunk {
+    fn from_chunk(chunk: &Chunk) -> Self {
+        let mut raw = RawChunk {
+            blocks: Box::new([BlockId::AIR; VOLUME]),
+        };
+        for (x, y, z) in positions() {
+            raw.set(x, y, z, chunk.get(x, y as i32, z));
+        }
+        raw
+    }
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        (y * CHUNK_SIZE + z) * CHUNK_SIZE + x
+    }
+
+    fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        self.blocks[RawChunk::index(x, y, z)]
+    }
+
+    fn set(&mut self, x: usize, y: usize, z: usize, id: BlockId) {
+        self.blocks[RawChunk::index(x, y, z)] = id;
+    }
+
+    fn heap_size(&self) -> usize {
+        size_of::<[BlockId; VOLUME]>()
+    }
+}
+
+/// Every local block position, in storage order.
+fn positions() -> impl Iterator<Item = (usize, usize, usize)> {
+    (0..HEIGHT)
+        .flat_map(|y| (0..CHUNK_SIZE).flat_map(move |z| (0..CHUNK_SIZE).map(move |x| (x, y, z))))
+}
+
+/// Chunks ranging from the best case for a palette to the worst.
+fn sample_chunks() -> Vec<(&'static str, Chunk)> {
+    let registry = BlockRegistry::default();
+    let config = GenerationConfig {
+        min_y: 0,
+        max_y: HEIGHT as i32,
+        base_height: 8,
+        height_variation: 4.0,
+        sea_level: 6,
+        ..GenerationConfig::default()
+    };
+    let generator = TerrainGenerator::new(&config, &registry);
+    let height = WorldHeight::new(0, HEIGHT as i32);
+
+    let mut noisy = Chunk::new(height);
+    let mut rng = SplitMix64::new(7);
+    for (x, y, z) in positions() {
+        noisy.set(x, y as i32, z, BlockId(rng.next_below(256) as u16));
+    }
+
+    vec![
+        ("air", Chunk::new(height)),
+        ("terrain", generator.generate_chunk(ChunkPos::new(3, -2))),
+        ("noise", noisy),
+    ]
+}
+
+fn bench_memory(chunks: &[(&str, Chunk)]) {
+    println!("heap bytes per chunk (palette vs raw):");
+    for (name, chunk) in chunks {
+        println!(
+            "  {:<8} {:>6} vs {:>6}",
+            name,
+            chunk.heap_size(),
+            RawChunk::from_chunk(chunk).heap_size()
+        );
+    }
+}
+
+fn bench_get(c: &mut Criterion, chunks: &[(&str, Chunk)]) {
+    let mut group = c.benchmark_group("get_all_blocks");
+    for (name, chunk) in chunks {
+        let raw = RawChunk::from_chunk(chunk);
+        group.bench_with_input(BenchmarkId::new("palette", name), chunk, |b, chunk| {
+            b.iter(|| {
+                for (x, y, z) in positions() {
+                    black_box(chunk.get(x, y as i32, z));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("raw", name), &raw, |b, raw| {
+            b.iter(|| {
+                for (x, y, z) in positions() {
+                    black_box(raw.get(x, y, z));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_set(c: &mut Criterion, chunks: &[(&str, Chunk)]) {
+    // The same scattered edits for every chunk, mixing existing and new block types
+    let mut rng = SplitMix64::new(11);
+    let edits: Vec<_> = (0..1024)
+        .map(|_| {
+            let coord = |rng: &mut SplitMix64, size: usize| rng.next_below(size as i32) as usize;
+            let pos = (
+                coord(&mut rng, CHUNK_SIZE),
+                coord(&mut rng, HEIGHT),
+                coord(&mut rng, CHUNK_SIZE),
+            );
+            (pos, BlockId(rng.next_below(16) as u16))
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("set_scattered_blocks");
+    for (name, chunk) in chunks {
+        group.bench_with_input(BenchmarkId::new("palette", name), chunk, |b, chunk| {
+            b.iter(|| {
+                let mut chunk = chunk.clone();
+                for &((x, y, z), block) in &edits {
+                    chunk.set(x, y as i32, z, block);
+                }
+                chunk
+            })
+        });
+        let raw = RawChunk::from_chunk(chunk);
+        group.bench_with_input(BenchmarkId::new("raw", name), &raw, |b, raw| {
+            b.iter(|| {
+                let mut raw = RawChunk {
+                    blocks: raw.blocks.clone(),
+                };
+                for &((x, y, z), block) in &edits {
+                    raw.set(x, y, z, block);
+                }
+                raw
+            })
+        });
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    let chunks = sample_chunks();
+    bench_memory(&chunks);
+    bench_get(c, &chunks);
+    bench_set(c, &chunks);
+}
+
+criterion_group!(chunk_storage, benches);
+criterion_main!(chunk_storage);
@@ -2,8 +2,9 @@ use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 pub use std::io::Write;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Represents the game configuration, including window, camera, controls, and physics settings.
+/// Represents the game configuration, including window, camera, controls, physics, world, rendering, and generation settings.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     /// Window settings
@@ -14,6 +15,15 @@ pub struct Config {
     pub controls: ControlsConfig,
     /// Physics settings
     pub physics: PhysicsConfig,
+    /// World settings
+    #[serde(default)]
+    pub world: WorldConfig,
+    /// Rendering settings
+    #[serde(default)]
+    pub rendering: RenderingConfig,
+    /// World generation settings
+    #[serde(default)]
+    pub generation: GenerationConfig,
 }
 
 /// Represents the window configuration.
@@ -42,6 +52,13 @@ pub struct CameraConfig {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ControlsConfig {
     pub cursor_locked: bool,
+    /// How far away (in blocks) the player can target, break and place blocks.
+    #[serde(default = "default_reach")]
+    pub reach: f32,
+}
+
+fn default_reach() -> f32 {
+    5.0
 }
 
 /// Represents the physics configuration.
@@ -50,6 +67,241 @@ pub struct PhysicsConfig {
     pub gravity: f32,
     pub jump_force: f32,
     pub player_height: f32,
+    /// The tallest ledge the player walks up without jumping, in blocks.
+    #[serde(default = "default_step_height")]
+    pub step_height: f32,
+}
+
+fn default_step_height() -> f32 {
+    0.6
+}
+
+/// Represents the world configuration.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorldConfig {
+    /// Number of chunks loaded in each direction around the camera.
+    pub render_distance: i32,
+    /// Number of background threads generating and meshing chunks; 0 picks one per spare CPU core.
+    #[serde(default)]
+    pub worker_threads: usize,
+    /// Maximum number of chunk meshes uploaded to the GPU each frame.
+    #[serde(default = "default_mesh_uploads_per_frame")]
+    pub mesh_uploads_per_frame: usize,
+    /// Directory the world is saved in.
+    #[serde(default = "default_save_dir")]
+    pub save_dir: String,
+    /// Seconds between automatic saves; 0 only saves on exit.
+    #[serde(default = "default_autosave_interval")]
+    pub autosave_interval: f32,
+    /// Blocks picked at random in every non-empty section of a loaded chunk each
+    /// tick, to let grass spread and leaves decay; 0 turns random ticks off.
+    #[serde(default = "default_random_tick_speed")]
+    pub random_tick_speed: u32,
+}
+
+fn default_mesh_uploads_per_frame() -> usize {
+    8
+}
+
+fn default_save_dir() -> String {
+    String::from("saves/world")
+}
+
+fn default_autosave_interval() -> f32 {
+    60.0
+}
+
+fn default_random_tick_speed() -> u32 {
+    3
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            render_distance: 4,
+            worker_threads: 0,
+            mesh_uploads_per_frame: default_mesh_uploads_per_frame(),
+            save_dir: default_save_dir(),
+            autosave_interval: default_autosave_interval(),
+            random_tick_speed: default_random_tick_speed(),
+        }
+    }
+}
+
+/// Represents the rendering configuration.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderingConfig {
+    /// Merge coplanar faces of the same block into larger quads when meshing chunks.
+    pub greedy_meshing: bool,
+    /// Start with the debug mode that outlines every block instead of only the
+    /// targeted one; F3 toggles it for the session without changing this.
+    #[serde(default)]
+    pub show_block_edges: bool,
+    /// Direction pointing towards the sun; faces turned away from it are darker.
+    #[serde(default = "default_sun_direction")]
+    pub sun_direction: [f32; 3],
+    /// Brightness of faces the sun doesn't reach, from 0 to 1.
+    #[serde(default = "default_ambient_light")]
+    pub ambient_light: f32,
+}
+
+fn default_sun_direction() -> [f32; 3] {
+    [0.4, 1.0, 0.25]
+}
+
+fn default_ambient_light() -> f32 {
+    0.55
+}
+
+impl RenderingConfig {
+    /// Replaces settings that can't be used with their defaults, reporting each.
+    fn validate(&mut self) {
+        let length_squared: f32 = self.sun_direction.iter().map(|v| v * v).sum();
+        if !length_squared.is_finite() || length_squared < 1e-6 {
+            eprintln!(
+                "Sun direction {:?} has no direction, using the default instead",
+                self.sun_direction
+            );
+            self.sun_direction = default_sun_direction();
+        }
+    }
+}
+
+impl Default for RenderingConfig {
+    fn default() -> Self {
+        RenderingConfig {
+            greedy_meshing: true,
+            show_block_edges: false,
+            sun_direction: default_sun_direction(),
+            ambient_light: default_ambient_light(),
+        }
+    }
+}
+
+/// Represents the world generation configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    /// Seed for all world generation noise.
+    pub seed: u64,
+    /// Lowest height blocks can be placed at; rounded down to a multiple of 16.
+    #[serde(default = "default_min_y")]
+    pub min_y: i32,
+    /// Height just above the highest block; rounded up to a multiple of 16.
+    #[serde(default = "default_max_y")]
+    pub max_y: i32,
+    /// Number of noise octaves summed for the terrain heightmap.
+    pub octaves: u32,
+    /// Horizontal size (in blocks) of the largest terrain features.
+    pub scale: f64,
+    /// Amplitude multiplier applied per octave.
+    pub persistence: f64,
+    /// Frequency multiplier applied per octave.
+    pub lacunarity: f64,
+    /// Average terrain height.
+    pub base_height: i32,
+    /// Maximum distance (in blocks) the terrain rises above or sinks below `base_height`.
+    pub height_variation: f64,
+    /// Height below which empty space is filled with water.
+    pub sea_level: i32,
+    /// Approximate size (in blocks) of a biome.
+    #[serde(default = "default_biome_scale")]
+    pub biome_scale: f64,
+    /// Lowest height caves are carved at; everything below stays solid.
+    #[serde(default = "default_cave_floor")]
+    pub cave_floor: i32,
+    /// Cave noise value above which open caves are carved; `1.0` or more disables them.
+    #[serde(default = "default_cave_threshold")]
+    pub cave_threshold: f64,
+    /// Probability (0-1) that a tunnel starts in any given chunk.
+    #[serde(default = "default_worm_chance")]
+    pub worm_chance: f64,
+    /// Ore veins scattered through the stone, placed in order.
+    #[serde(default = "default_ores")]
+    pub ores: Vec<OreConfig>,
+}
+
+/// Describes how one kind of ore is scattered through the stone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OreConfig {
+    /// Name of the ore block in the block registry.
+    pub block: String,
+    /// Lowest height a vein can start at.
+    pub min_height: i32,
+    /// Highest height a vein can start at.
+    pub max_height: i32,
+    /// Maximum number of blocks in a vein.
+    pub vein_size: u32,
+    /// Average number of veins per chunk; fractions give a chance of one more.
+    pub veins_per_chunk: f64,
+}
+
+fn default_min_y() -> i32 {
+    -64
+}
+
+fn default_max_y() -> i32 {
+    320
+}
+
+fn default_biome_scale() -> f64 {
+    256.0
+}
+
+fn default_cave_floor() -> i32 {
+    -58
+}
+
+fn default_cave_threshold() -> f64 {
+    0.4
+}
+
+fn default_worm_chance() -> f64 {
+    0.15
+}
+
+fn default_ores() -> Vec<OreConfig> {
+    let ore = |block: &str, min_height, max_height, vein_size, veins_per_chunk| OreConfig {
+        block: String::from(block),
+        min_height,
+        max_height,
+        vein_size,
+        veins_per_chunk,
+    };
+
+    vec![
+        ore("coal_ore", 0, 128, 12, 20.0),
+        ore("iron_ore", -48, 64, 8, 10.0),
+        ore("gold_ore", -64, 16, 7, 4.0),
+        ore("diamond_ore", -64, -32, 5, 2.0),
+    ]
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        // A fresh config gets a random seed, which is then saved with it
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0);
+
+        GenerationConfig {
+            seed,
+            min_y: default_min_y(),
+            max_y: default_max_y(),
+            octaves: 4,
+            scale: 192.0,
+            persistence: 0.5,
+            lacunarity: 2.0,
+            base_height: 64,
+            height_variation: 48.0,
+            sea_level: 62,
+            biome_scale: default_biome_scale(),
+            cave_floor: default_cave_floor(),
+            cave_threshold: default_cave_threshold(),
+            worm_chance: default_worm_chance(),
+            ores: default_ores(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -73,12 +325,17 @@ impl Default for Config {
             },
             controls: ControlsConfig {
                 cursor_locked: true,
+                reach: default_reach(),
             },
             physics: PhysicsConfig {
                 gravity: 0.015,
                 jump_force: 0.2,
                 player_height: 1.8,
+                step_height: default_step_height(),
             },
+            world: WorldConfig::default(),
+            rendering: RenderingConfig::default(),
+            generation: GenerationConfig::default(),
         }
     }
 }
@@ -95,8 +352,11 @@ impl Config {
         // If config file exists, load it
         if Path::new(config_path).exists() {
             match fs::read_to_string(config_path) {
-                Ok(content) => match toml::from_str(&content) {
-                    Ok(config) => return config,
+                Ok(content) => match toml::from_str::<Config>(&content) {
+                    Ok(mut config) => {
+                        config.rendering.validate();
+                        return config;
+                    }
                     Err(e) => {
                         eprintln!("Error parsing config file: {}", e);
                         eprintln!("Using default config instead");
@@ -1,9 +1,9 @@
 use crate::config::Config;
 use crate::player::input::PlayerInput;
+use crate::world::world::World;
 use cgmath::Matrix4;
 use cgmath::{Deg, perspective};
-use gl;
-use glfw::{Action, Glfw, GlfwReceiver, Key, Window, WindowEvent};
+use glfw::{Action, Glfw, GlfwReceiver, Key, MouseButton, Window, WindowEvent};
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 
@@ -33,10 +33,14 @@ struct MouseState {
 /// * `config` - A mutable reference to the game configuration.
 /// * `glfw` - A mutable reference to the GLFW instance.
 /// * `player_input` - A mutable reference to the `PlayerInput` instance.
+/// * `world` - A mutable reference to the game world, edited by mouse clicks.
+/// * `alpha` - How far the last frame was drawn between physics steps; clicks
+///   target blocks from the eye it was drawn from.
 ///
 /// # Returns
 ///
 /// An optional new projection matrix if the window size changes.
+#[allow(clippy::too_many_arguments)]
 pub fn process_events(
     window: &mut Window,
     events: &GlfwReceiver<(f64, WindowEvent)>,
@@ -44,6 +48,8 @@ pub fn process_events(
     config: &mut Config,
     glfw: &mut Glfw,
     player_input: &mut PlayerInput,
+    world: &mut World,
+    alpha: f32,
 ) -> Option<Matrix4<f32>> {
     let mut new_projection = None;
 
@@ -80,36 +86,36 @@ pub fn process_events(
 
                             // Switch to fullscreen using a direct approach
                             glfw.with_primary_monitor(|_, m| {
-                                if let Some(monitor) = m {
-                                    if let Some(video_mode) = monitor.get_video_mode() {
-                                        // Use video mode resolution for fullscreen
-                                        window.set_monitor(
-                                            glfw::WindowMode::FullScreen(monitor),
+                                if let Some(monitor) = m
+                                    && let Some(video_mode) = monitor.get_video_mode()
+                                {
+                                    // Use video mode resolution for fullscreen
+                                    window.set_monitor(
+                                        glfw::WindowMode::FullScreen(monitor),
+                                        0,
+                                        0,
+                                        video_mode.width,
+                                        video_mode.height,
+                                        Some(video_mode.refresh_rate),
+                                    );
+
+                                    // Force update viewport size
+                                    unsafe {
+                                        gl::Viewport(
                                             0,
                                             0,
-                                            video_mode.width,
-                                            video_mode.height,
-                                            Some(video_mode.refresh_rate),
+                                            video_mode.width as i32,
+                                            video_mode.height as i32,
                                         );
-
-                                        // Force update viewport size
-                                        unsafe {
-                                            gl::Viewport(
-                                                0,
-                                                0,
-                                                video_mode.width as i32,
-                                                video_mode.height as i32,
-                                            );
-                                        }
-
-                                        // Update projection for new resolution
-                                        new_projection = Some(perspective(
-                                            Deg(config.camera.fov),
-                                            video_mode.width as f32 / video_mode.height as f32,
-                                            config.camera.near_plane,
-                                            config.camera.far_plane,
-                                        ));
                                     }
+
+                                    // Update projection for new resolution
+                                    new_projection = Some(perspective(
+                                        Deg(config.camera.fov),
+                                        video_mode.width as f32 / video_mode.height as f32,
+                                        config.camera.near_plane,
+                                        config.camera.far_plane,
+                                    ));
                                 }
                             });
                         } else {
@@ -145,6 +151,10 @@ pub fn process_events(
                         // Save config changes
                         config.save();
                     }
+                    (Key::F3, Action::Press) => {
+                        // Toggle the debug wireframe around every block
+                        player_input.show_block_edges = !player_input.show_block_edges;
+                    }
                     _ => {
                         // Pass other keys to player input system
                         player_input.key_callback(key, action);
@@ -173,19 +183,28 @@ pub fn process_events(
 
                 // Constrain maximum movement per frame
                 const MAX_MOVEMENT: f32 = 100.0;
-                x_offset = x_offset.max(-MAX_MOVEMENT).min(MAX_MOVEMENT);
-                y_offset = y_offset.max(-MAX_MOVEMENT).min(MAX_MOVEMENT);
+                x_offset = x_offset.clamp(-MAX_MOVEMENT, MAX_MOVEMENT);
+                y_offset = y_offset.clamp(-MAX_MOVEMENT, MAX_MOVEMENT);
 
                 mouse_state.last_x = x_pos;
                 mouse_state.last_y = y_pos;
 
                 camera.process_mouse_movement(x_offset, y_offset, true, config.camera.sensitivity);
             }
-            WindowEvent::MouseButton(_button, _action, _) => {
-                if !config.controls.cursor_locked {
-                    continue;
+            WindowEvent::MouseButton(button, Action::Press, _) if config.controls.cursor_locked => {
+                match button {
+                    // Left click breaks the targeted block
+                    MouseButton::Button1 => {
+                        let eye = camera.interpolated_position(alpha);
+                        player_input.break_block(camera, eye, world, config);
+                    }
+                    // Right click places the selected block
+                    MouseButton::Button2 => {
+                        let eye = camera.interpolated_position(alpha);
+                        player_input.place_block(camera, eye, world, config);
+                    }
+                    _ => {}
                 }
-                // Implementation will come later
             }
             _ => {}
         }
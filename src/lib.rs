@@ -0,0 +1,9 @@
+//! Cubix, a Minecraft clone. The game itself lives in `main.rs`; the modules
+//! are exposed as a library so benchmarks can use them.
+
+pub mod config;
+pub mod events;
+pub mod player;
+pub mod rendering;
+pub mod timestep;
+pub mod world;
@@ -1,22 +1,19 @@
 extern crate gl;
 extern crate glfw;
 
-use cgmath::Matrix;
+use cgmath::{InnerSpace, Matrix, Vector3};
 use gl::types::*;
 use glfw::Context;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-mod config;
-mod events;
-mod player;
-mod rendering;
-mod world;
-
-use crate::config::Config;
-use crate::events::process_events;
-use crate::player::input::PlayerInput;
-use crate::player::movement::handle_movement_input; // Updated import
-use crate::world::init::App;
+use cubix::config::Config;
+use cubix::events::process_events;
+use cubix::player::input::PlayerInput;
+use cubix::player::movement::{PHYSICS_STEPS_PER_SECOND, handle_movement_input};
+use cubix::timestep::FixedTimestep;
+use cubix::world::cube_render::draw_block_outline;
+use cubix::world::init::App;
+use cubix::world::world::TICKS_PER_SECOND;
 
 /// The main entry point of the Cubix application.
 fn main() {
@@ -25,12 +22,25 @@ fn main() {
 
     let mut app = App::new(&config);
 
-    // Initialize player input system
+    // Initialize player input system, resuming fly mode from the save
     let mut player_input = PlayerInput::new(&config);
+    if let Some(player) = app.saved_player {
+        player_input.fly_mode = player.flying;
+    }
 
     // For calculating delta time
     let mut last_frame = Instant::now();
 
+    // Player physics and world ticks advance at fixed rates, whatever the frame rate
+    let mut physics = FixedTimestep::new(PHYSICS_STEPS_PER_SECOND);
+    let mut world_ticks = FixedTimestep::new(TICKS_PER_SECOND);
+
+    // When the world was last saved, for autosaving
+    let mut last_save = Instant::now();
+
+    // The biome shown in the window title
+    let mut current_biome = None;
+
     unsafe {
         gl::Enable(gl::DEPTH_TEST);
 
@@ -40,8 +50,10 @@ fn main() {
             let current_frame = Instant::now();
             let delta_time = current_frame.duration_since(last_frame).as_secs_f32();
             last_frame = current_frame;
+            app.game_time += delta_time as f64;
 
-            // Process events and update projection if needed
+            // Process events and update projection if needed. No physics step has
+            // run since the last frame was drawn, so clicks aim from its eye
             if let Some(new_projection) = process_events(
                 &mut app.window,
                 &app.events,
@@ -49,22 +61,50 @@ fn main() {
                 &mut config,
                 &mut app.glfw,
                 &mut player_input,
+                &mut app.world,
+                physics.alpha(),
             ) {
                 app.projection = new_projection;
             }
 
-            // Handle player movement with delta time
-            handle_movement_input(
-                &app.window,
-                &mut app.camera,
-                &mut player_input,
-                delta_time,
-                &config,
-                &app.world, // Pass the world
+            // Generate chunks around the player and drop the ones left behind
+            app.world.update_loaded_chunks(app.camera.position);
+            app.world.rebuild_dirty_meshes();
+
+            // Tick the world and step the player's movement for the time that passed
+            for _ in 0..world_ticks.advance(delta_time) {
+                app.world.tick();
+            }
+            for _ in 0..physics.advance(delta_time) {
+                handle_movement_input(&mut app.camera, &mut player_input, &config, &app.world);
+            }
+
+            // Draw the player between its last two physics steps, so movement
+            // looks smooth at frame rates above the physics rate
+            let alpha = physics.alpha();
+            let eye = app.camera.interpolated_position(alpha);
+
+            // Show the biome the player is standing in next to the window title
+            let biome = app.world.biome_at(
+                app.camera.position.x.floor() as i32,
+                app.camera.position.z.floor() as i32,
             );
+            if current_biome != Some(biome) {
+                app.window
+                    .set_title(&format!("{} - {}", config.window.title, biome.name()));
+                current_biome = Some(biome);
+            }
+
+            // Save periodically so a crash loses little progress
+            if config.world.autosave_interval > 0.0
+                && last_save.elapsed().as_secs_f32() >= config.world.autosave_interval
+            {
+                app.save(&player_input);
+                last_save = Instant::now();
+            }
 
             // Update the view matrix with new camera orientation
-            app.update_view_matrix();
+            app.update_view_matrix(alpha);
 
             gl::ClearColor(0.2, 0.3, 0.3, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
@@ -72,8 +112,7 @@ fn main() {
             app.shader.use_program();
 
             // Set up projection matrix
-            let projection_location =
-                gl::GetUniformLocation(app.shader.id, b"projection\0".as_ptr() as *const GLchar);
+            let projection_location = gl::GetUniformLocation(app.shader.id, c"projection".as_ptr());
             gl::UniformMatrix4fv(
                 projection_location,
                 1,
@@ -82,19 +121,37 @@ fn main() {
             );
 
             // Set up view matrix
-            let view_location =
-                gl::GetUniformLocation(app.shader.id, b"view\0".as_ptr() as *const GLchar);
+            let view_location = gl::GetUniformLocation(app.shader.id, c"view".as_ptr());
             gl::UniformMatrix4fv(view_location, 1, gl::FALSE as GLboolean, app.view.as_ptr());
 
-            app.world.draw(&app.shader, app.glfw.get_time() as f32);
+            // Set up sun shading
+            let sun = Vector3::from(config.rendering.sun_direction).normalize();
+            let sun_location = gl::GetUniformLocation(app.shader.id, c"sunDirection".as_ptr());
+            gl::Uniform3f(sun_location, sun.x, sun.y, sun.z);
+            let ambient_location = gl::GetUniformLocation(app.shader.id, c"ambientLight".as_ptr());
+            gl::Uniform1f(ambient_location, config.rendering.ambient_light);
 
-            app.window.swap_buffers();
-            app.glfw.poll_events();
+            app.atlas.bind(&app.shader);
 
-            // Cap framerate (optional)
-            if config.window.vsync {
-                std::thread::sleep(Duration::from_millis(16));
+            app.world.draw(
+                &app.shader,
+                eye,
+                app.glfw.get_time() as f32,
+                player_input.show_block_edges,
+            );
+
+            // Outline the block the player is looking at, from where the frame is drawn
+            if let Some(hit) = app
+                .world
+                .raycast(eye, app.camera.front, config.controls.reach)
+            {
+                draw_block_outline(&app.outline, hit.block, &app.shader);
             }
+
+            app.window.swap_buffers();
+            app.glfw.poll_events();
         }
     }
+
+    app.save(&player_input);
 }
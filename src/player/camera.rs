@@ -3,6 +3,8 @@ use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
 /// Represents the player's camera, including position and orientation.
 pub struct Camera {
     pub position: Point3<f32>,
+    /// Where the camera was before the last physics step.
+    pub previous_position: Point3<f32>,
     pub front: Vector3<f32>,
     pub up: Vector3<f32>,
     pub yaw: f32,   // Horizontal rotation (in degrees)
@@ -22,6 +24,7 @@ impl Camera {
     pub fn new(position: Point3<f32>) -> Self {
         Camera {
             position,
+            previous_position: position,
             front: Vector3::new(0.0, 0.0, -1.0),
             up: Vector3::new(0.0, 1.0, 0.0),
             yaw: -90.0, // Start facing negative z
@@ -29,13 +32,30 @@ impl Camera {
         }
     }
 
+    /// Returns where the camera is drawn from, part of the way from its previous
+    /// position to its current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - How far the frame lies between the last physics step and the
+    ///   next, from 0 to 1.
+    pub fn interpolated_position(&self, alpha: f32) -> Point3<f32> {
+        self.previous_position + (self.position - self.previous_position) * alpha
+    }
+
     /// Returns the view matrix for the camera.
     ///
+    /// # Arguments
+    ///
+    /// * `alpha` - How far the frame lies between the last physics step and the
+    ///   next, from 0 to 1.
+    ///
     /// # Returns
     ///
-    /// The view matrix.
-    pub fn get_view_matrix(&self) -> Matrix4<f32> {
-        Matrix4::look_at_rh(self.position, self.position + self.front, self.up)
+    /// The view matrix, looking from the interpolated position.
+    pub fn get_view_matrix(&self, alpha: f32) -> Matrix4<f32> {
+        let eye = self.interpolated_position(alpha);
+        Matrix4::look_at_rh(eye, eye + self.front, self.up)
     }
 
     /// Processes mouse movement to update the camera's orientation.
@@ -58,12 +78,7 @@ impl Camera {
 
         // Constrain pitch to avoid flipping
         if constrain_pitch {
-            if self.pitch > 89.0 {
-                self.pitch = 89.0;
-            }
-            if self.pitch < -89.0 {
-                self.pitch = -89.0;
-            }
+            self.pitch = self.pitch.clamp(-89.0, 89.0);
         }
 
         // Update front vector
@@ -1,7 +1,18 @@
 use crate::config::Config;
+use crate::player::camera::Camera;
+use crate::player::movement::PLAYER_HALF_WIDTH;
+use crate::world::block::BlockId;
+use crate::world::collision::Aabb;
+use crate::world::world::World;
+use cgmath::Point3;
 use glfw::{Action, Key};
 use std::collections::HashMap;
 
+/// The names of the blocks selected by the number keys `1` to `9` and `0`.
+const HOTBAR: [&str; 10] = [
+    "dirt", "grass", "stone", "snow", "water", "lamp", "glass", "lava", "sand", "gravel",
+];
+
 /// Manages player input, including movement and key states.
 pub struct PlayerInput {
     key_states: HashMap<Key, bool>,
@@ -11,8 +22,13 @@ pub struct PlayerInput {
     pub jump_force: f32,
     pub velocity: cgmath::Vector3<f32>,
     pub on_ground: bool,
+    /// Whether the player is swimming in a liquid.
+    pub in_liquid: bool,
     pub last_jump_time: f32,
     pub jump_cooldown: f32,
+    pub selected_slot: usize,
+    /// Whether every block is outlined, toggled with F3; starts as configured.
+    pub show_block_edges: bool,
 }
 
 impl PlayerInput {
@@ -34,8 +50,11 @@ impl PlayerInput {
             jump_force: config.physics.jump_force,
             velocity: cgmath::Vector3::new(0.0, 0.0, 0.0),
             on_ground: false,
+            in_liquid: false,
             last_jump_time: 0.0,
             jump_cooldown: 0.2, // Prevent jump spam
+            selected_slot: 0,
+            show_block_edges: config.rendering.show_block_edges,
         }
     }
 
@@ -57,6 +76,24 @@ impl PlayerInput {
                         self.velocity.y = 0.0; // Reset vertical velocity when entering fly mode
                     }
                 }
+
+                // Select the block to place with the number keys
+                let slot = match key {
+                    Key::Num1 => Some(0),
+                    Key::Num2 => Some(1),
+                    Key::Num3 => Some(2),
+                    Key::Num4 => Some(3),
+                    Key::Num5 => Some(4),
+                    Key::Num6 => Some(5),
+                    Key::Num7 => Some(6),
+                    Key::Num8 => Some(7),
+                    Key::Num9 => Some(8),
+                    Key::Num0 => Some(9),
+                    _ => None,
+                };
+                if let Some(slot) = slot {
+                    self.selected_slot = slot;
+                }
             }
             Action::Release => {
                 self.key_states.insert(key, false);
@@ -78,21 +115,85 @@ impl PlayerInput {
         *self.key_states.get(&key).unwrap_or(&false)
     }
 
-    /// Placeholder method for block placement.
+    /// Places the selected block against the face of the block the camera is looking at.
+    ///
+    /// Placement is refused if the new block would overlap the player.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The player's camera.
+    /// * `eye` - Where the ray starts: the eye the last frame was drawn from, so
+    ///   the block placed against is the one outlined.
+    /// * `world` - The world to place the block in.
+    /// * `config` - A reference to the game configuration.
     ///
     /// # Returns
     ///
-    /// `false` as block placement is not yet implemented.
-    pub fn _place_block(&self) -> bool {
-        false
+    /// `true` if a block was placed, `false` otherwise.
+    pub fn place_block(
+        &self,
+        camera: &Camera,
+        eye: Point3<f32>,
+        world: &mut World,
+        config: &Config,
+    ) -> bool {
+        let Some(hit) = world.raycast(eye, camera.front, config.controls.reach) else {
+            return false;
+        };
+
+        let Some(block) = world.registry().id(HOTBAR[self.selected_slot]) else {
+            return false;
+        };
+
+        // Only replace blocks the player could walk through, such as air and water
+        let [x, y, z] = hit.adjacent();
+        if world.registry().is_solid(world.get_block(x, y, z)) {
+            return false;
+        }
+
+        let player_height = config.physics.player_height;
+        let feet_position = Point3::new(
+            camera.position.x,
+            camera.position.y - player_height,
+            camera.position.z,
+        );
+        let player = Aabb::from_feet(feet_position, PLAYER_HALF_WIDTH, player_height);
+        if player.intersects(&Aabb::block([x, y, z])) {
+            return false;
+        }
+
+        world.set_block(x, y, z, block)
     }
 
-    /// Placeholder method for block breaking.
+    /// Removes the block the camera is looking at, unless it's unbreakable.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` - The player's camera.
+    /// * `eye` - Where the ray starts: the eye the last frame was drawn from, so
+    ///   the block removed is the one outlined.
+    /// * `world` - The world to remove the block from.
+    /// * `config` - A reference to the game configuration.
     ///
     /// # Returns
     ///
-    /// `false` as block breaking is not yet implemented.
-    pub fn _break_block(&self) -> bool {
-        false
+    /// `true` if a block was removed, `false` otherwise.
+    pub fn break_block(
+        &self,
+        camera: &Camera,
+        eye: Point3<f32>,
+        world: &mut World,
+        config: &Config,
+    ) -> bool {
+        let Some(hit) = world.raycast(eye, camera.front, config.controls.reach) else {
+            return false;
+        };
+
+        let [x, y, z] = hit.block;
+        if world.registry().get(world.get_block(x, y, z)).hardness < 0.0 {
+            return false;
+        }
+
+        world.set_block(x, y, z, BlockId::AIR)
     }
 }
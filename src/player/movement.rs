@@ -1,30 +1,49 @@
 use crate::config::Config;
 use crate::player::camera::Camera;
 use crate::player::input::PlayerInput;
+use crate::world::collision::Aabb;
 use crate::world::world::World;
 use cgmath::InnerSpace;
 use cgmath::Point3;
-use glfw::{Key, Window};
+use glfw::Key;
 
-/// Processes player input and updates the camera position.
+/// How many times per second the player's movement is simulated. `velocity`,
+/// `gravity` and `jump_force` are in blocks per step.
+pub const PHYSICS_STEPS_PER_SECOND: u32 = 60;
+
+/// Half the width and depth of the player's collision box.
+pub const PLAYER_HALF_WIDTH: f32 = 0.3;
+
+/// How much slower the player moves through liquid than through air.
+const SWIM_SPEED_FACTOR: f32 = 0.5;
+
+/// How much weaker gravity is in liquid.
+const SWIM_GRAVITY_FACTOR: f32 = 0.2;
+
+/// The vertical speed of swimming upwards, in blocks per step like `velocity`.
+const SWIM_UP_VELOCITY: f32 = 0.06;
+
+/// The fastest the player sinks through liquid, in blocks per step.
+const SWIM_SINK_VELOCITY: f32 = 0.05;
+
+/// Processes player input and moves the camera by one physics step.
 ///
 /// # Arguments
 ///
-/// * `_window` - A reference to the GLFW window.
+/// * `player_input` - A mutable reference to the `PlayerInput` instance.
 /// * `camera` - A mutable reference to the player's camera.
-/// * `delta_time` - The time elapsed since the last frame.
 /// * `config` - A reference to the game configuration.
 /// * `world` - A reference to the game world.
 pub fn process_input(
     player_input: &mut PlayerInput,
-    _window: &Window,
     camera: &mut Camera,
-    delta_time: f32,
     config: &Config,
     world: &World,
 ) {
+    let step_length = 1.0 / PHYSICS_STEPS_PER_SECOND as f32;
+
     // Update timers
-    player_input.last_jump_time += delta_time;
+    player_input.last_jump_time += step_length;
 
     // Set movement speed
     let base_speed = if player_input.is_key_pressed(Key::LeftControl) {
@@ -35,43 +54,29 @@ pub fn process_input(
         5.0 // Normal walking speed
     };
 
-    // Calculate frame-dependent speed
-    let speed = base_speed * delta_time;
-    player_input.movement_speed = base_speed;
-
-    // Player collision properties
-    let player_radius = 0.3; // Reasonable radius for player
     let player_height = config.physics.player_height;
 
+    // Swim while the middle of the player's body is in a liquid
+    let waist = Point3::new(
+        camera.position.x,
+        camera.position.y - player_height / 2.0,
+        camera.position.z,
+    );
+    player_input.in_liquid = !player_input.fly_mode && world.is_liquid_at(waist);
+    let base_speed = if player_input.in_liquid {
+        base_speed * SWIM_SPEED_FACTOR
+    } else {
+        base_speed
+    };
+
+    // Speeds are per second, so scale them down to one step
+    let speed = base_speed * step_length;
+    player_input.movement_speed = base_speed;
+
     // Store current position before movement
     let current_pos = camera.position;
     let mut target_pos = current_pos;
 
-    // Ground detection for jumping
-    if !player_input.fly_mode {
-        // Position is now the camera position (eyes), so we check at feet level
-        let feet_position = Point3::new(
-            camera.position.x,
-            camera.position.y - config.physics.player_height,
-            camera.position.z,
-        );
-
-        // Check if there's a block below feet
-        let ground_check_pos = Point3::new(
-            feet_position.x,
-            feet_position.y - 0.05, // Check slightly below feet
-            feet_position.z,
-        );
-
-        let was_on_ground = player_input.on_ground;
-        player_input.on_ground = world.check_collision(&ground_check_pos, player_radius, 0.1);
-
-        // If we just landed, reset vertical velocity
-        if !was_on_ground && player_input.on_ground {
-            player_input.velocity.y = 0.0;
-        }
-    }
-
     // Left/right movement (strafe)
     if player_input.is_key_pressed(Key::A) || player_input.is_key_pressed(Key::D) {
         let right = camera.front.cross(camera.up).normalize();
@@ -113,6 +118,15 @@ pub fn process_input(
         if player_input.is_key_pressed(Key::LeftShift) {
             target_pos.y -= speed;
         }
+    } else if player_input.in_liquid {
+        // Swimming - space rises, otherwise the player slowly sinks
+        if player_input.is_key_pressed(Key::Space) {
+            player_input.velocity.y = SWIM_UP_VELOCITY;
+        } else {
+            player_input.velocity.y -= player_input.gravity * SWIM_GRAVITY_FACTOR;
+            player_input.velocity.y = player_input.velocity.y.max(-SWIM_SINK_VELOCITY);
+        }
+        target_pos.y += player_input.velocity.y;
     } else {
         // Walking mode with physics and jumping
         if player_input.is_key_pressed(Key::Space)
@@ -125,7 +139,7 @@ pub fn process_input(
         }
 
         // Apply gravity
-        player_input.velocity.y -= player_input.gravity * delta_time * 60.0;
+        player_input.velocity.y -= player_input.gravity;
 
         // Terminal velocity cap
         if player_input.velocity.y < -0.8 {
@@ -136,51 +150,115 @@ pub fn process_input(
         target_pos.y += player_input.velocity.y;
     }
 
-    // Resolve collisions with world
-    camera.position =
-        world.resolve_collision(current_pos, target_pos, player_radius, player_height);
-
-    // Ground detection for jumping
-    if !player_input.fly_mode {
-        // Check if there's a block below us
-        let ground_check_pos = Point3::new(
-            camera.position.x,
-            camera.position.y - 0.05, // Check slightly below feet
-            camera.position.z,
-        );
-
-        let was_on_ground = player_input.on_ground;
-        player_input.on_ground = world.check_collision(&ground_check_pos, player_radius, 0.1);
-
-        // If we just landed, reset vertical velocity
-        if !was_on_ground && player_input.on_ground {
-            player_input.velocity.y = 0.0;
-        }
+    // Sweep the player's box towards the target, stepping up low ledges on foot
+    let feet = Point3::new(current_pos.x, current_pos.y - player_height, current_pos.z);
+    let movement = target_pos - current_pos;
+    let step_height = if player_input.fly_mode {
+        0.0
+    } else {
+        config.physics.step_height
+    };
+    let moved = world.move_box(
+        Aabb::from_feet(feet, PLAYER_HALF_WIDTH, player_height),
+        movement,
+        step_height,
+    );
+    camera.position = current_pos + moved.offset;
+
+    // Landing or bumping into a ceiling stops vertical motion
+    if moved.collided.y {
+        player_input.velocity.y = 0.0;
     }
+    player_input.on_ground = !player_input.fly_mode && moved.landed(movement);
 }
 
-/// Handles player movement input in the main event loop.
+/// Runs one physics step of player movement from the main loop.
+///
+/// The camera's previous position is remembered first, so frames drawn before
+/// the next step can interpolate between the two.
 ///
 /// # Arguments
 ///
-/// * `window` - A reference to the GLFW window.
 /// * `camera` - A mutable reference to the player's camera.
 /// * `player_input` - A mutable reference to the `PlayerInput` instance.
-/// * `delta_time` - The time elapsed since the last frame.
 /// * `config` - A reference to the game configuration.
 /// * `world` - A reference to the game world.
 pub fn handle_movement_input(
-    window: &Window,
     camera: &mut Camera,
     player_input: &mut PlayerInput,
-    delta_time: f32,
     config: &Config,
     world: &World,
 ) {
+    camera.previous_position = camera.position;
+
     // Skip input processing if cursor isn't locked (in menus)
     if !config.controls.cursor_locked {
         return;
     }
 
-    process_input(player_input, window, camera, delta_time, config, world);
+    process_input(player_input, camera, config, world);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestep::FixedTimestep;
+    use crate::world::world::TestWorld;
+    use glfw::Action;
+
+    /// Returns a loaded world with a stone floor high above the terrain, and the
+    /// height of the floor's top.
+    fn world_with_floor() -> (TestWorld, f32) {
+        let mut world = TestWorld::new(1);
+        world.update_loaded_chunks(Point3::new(8.0, 0.0, 8.0));
+        world.finish_loading();
+
+        let stone = world.registry().expect_id("stone");
+        let floor = world.height().max_y() - 20;
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(x, floor, z, stone);
+            }
+        }
+        (world, floor as f32 + 1.0)
+    }
+
+    /// Runs a player jumping and walking off the floor for a little over two
+    /// seconds at a frame rate, and returns its position after every physics step.
+    fn trajectory(world: &World, floor: f32, fps: u32) -> Vec<Point3<f32>> {
+        let config = Config::default();
+        let start = Point3::new(8.0, floor + config.physics.player_height, 4.0);
+        let mut camera = Camera::new(start);
+        let mut player_input = PlayerInput::new(&config);
+        player_input.key_callback(Key::Space, Action::Press);
+        player_input.key_callback(Key::W, Action::Press);
+
+        let mut physics = FixedTimestep::new(PHYSICS_STEPS_PER_SECOND);
+        let frames = fps * 2;
+        let mut positions = Vec::new();
+        for _ in 0..frames {
+            for _ in 0..physics.advance(2.01 / frames as f32) {
+                handle_movement_input(&mut camera, &mut player_input, &config, world);
+                positions.push(camera.position);
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn trajectories_do_not_depend_on_the_frame_rate() {
+        let (world, floor) = world_with_floor();
+        let expected = trajectory(&world, floor, 60);
+        assert_eq!(expected.len(), 2 * PHYSICS_STEPS_PER_SECOND as usize);
+
+        // The player jumped, then walked off the edge of the floor
+        let peak = expected.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+        assert!(peak > expected[0].y + 0.5);
+        assert!(expected.last().unwrap().y < expected[0].y - 1.0);
+
+        for fps in [20, 30, 75, 144, 240] {
+            let positions = trajectory(&world, floor, fps);
+            assert_eq!(positions, expected, "at {} frames per second", fps);
+        }
+    }
 }
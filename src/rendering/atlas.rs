@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// The width and height of every atlas tile, in pixels.
+pub const TILE_SIZE: usize = 16;
+
+/// The plain white tile, always at index 0, sampled by untextured faces so they
+/// show their vertex color unchanged.
+pub const BLANK_TILE: u32 = 0;
+
+/// The number of mip levels, from full size down to one pixel per tile.
+pub const MIP_LEVELS: usize = TILE_SIZE.ilog2() as usize + 1;
+
+/// Square RGBA tiles packed into one grid, so every block face can be drawn
+/// from a single texture.
+///
+/// Tiles are numbered row by row from the atlas's bottom-left corner. Pixel rows
+/// are stored bottom row first, as OpenGL expects.
+pub struct TextureAtlas {
+    /// The number of tiles along each side of the atlas.
+    columns: usize,
+    /// Tile indices by name.
+    tiles: HashMap<String, u32>,
+    /// The RGBA pixels of each mip level, from full size down to one pixel per tile.
+    levels: Vec<Vec<u8>>,
+}
+
+impl TextureAtlas {
+    /// Builds an atlas from every PNG file in a directory, naming each tile after
+    /// its file name without the extension.
+    ///
+    /// Files that can't be read or aren't `TILE_SIZE` pixels square are reported
+    /// and left out, so a broken texture never stops the game.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory holding the tiles.
+    ///
+    /// # Returns
+    ///
+    /// The atlas, holding at least the blank tile.
+    pub fn load(dir: &str) -> Self {
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+                .collect(),
+            Err(e) => {
+                eprintln!("Error reading textures {}: {}", dir, e);
+                Vec::new()
+            }
+        };
+        // Sorted so tile indices don't depend on directory order
+        paths.sort();
+
+        let mut tiles = Vec::new();
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            match decode_tile(&path) {
+                Ok(pixels) => tiles.push((name.to_string(), pixels)),
+                Err(e) => eprintln!("Error loading texture {}: {}", path.display(), e),
+            }
+        }
+
+        TextureAtlas::from_tiles(tiles)
+    }
+
+    /// Packs tiles into an atlas after the blank tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `tiles` - Each tile's name and its `TILE_SIZE`² RGBA pixels, top row first.
+    ///
+    /// # Returns
+    ///
+    /// The atlas, with tiles numbered in the given order starting at 1.
+    pub fn from_tiles(tiles: Vec<(String, Vec<u8>)>) -> Self {
+        let blank = vec![255; TILE_SIZE * TILE_SIZE * 4];
+        let count = tiles.len() + 1;
+        let columns = (1..).find(|c| c * c >= count).unwrap();
+        let size = columns * TILE_SIZE;
+
+        let mut pixels = vec![0; size * size * 4];
+        let mut names = HashMap::new();
+        let all = std::iter::once((String::new(), blank)).chain(tiles);
+        for (index, (name, tile)) in all.enumerate() {
+            let (column, row) = (index % columns, index / columns);
+            for y in 0..TILE_SIZE {
+                // Flip the tile so its top row ends up at the top of the face
+                let source = &tile[(TILE_SIZE - 1 - y) * TILE_SIZE * 4..][..TILE_SIZE * 4];
+                let start = ((row * TILE_SIZE + y) * size + column * TILE_SIZE) * 4;
+                pixels[start..][..TILE_SIZE * 4].copy_from_slice(source);
+            }
+            if index > 0 {
+                names.insert(name, index as u32);
+            }
+        }
+
+        // Tile borders stay aligned to every other pixel until a tile shrinks to
+        // a single pixel, so halving never blends neighbouring tiles together
+        let mut levels = vec![pixels];
+        for level in 1..MIP_LEVELS {
+            levels.push(downsample(&levels[level - 1], size >> (level - 1)));
+        }
+
+        TextureAtlas {
+            columns,
+            tiles: names,
+            levels,
+        }
+    }
+
+    /// Returns the index of the tile with the given name.
+    pub fn tile(&self, name: &str) -> Option<u32> {
+        self.tiles.get(name).copied()
+    }
+
+    /// Returns the number of tiles along each side of the atlas.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the width and height of the atlas at full size, in pixels.
+    pub fn size(&self) -> usize {
+        self.columns * TILE_SIZE
+    }
+
+    /// Returns the RGBA pixels of each mip level, from full size down to one
+    /// pixel per tile, each half the size of the one before.
+    pub fn levels(&self) -> &[Vec<u8>] {
+        &self.levels
+    }
+}
+
+/// Reads a PNG tile as `TILE_SIZE`² RGBA pixels, top row first.
+fn decode_tile(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+
+    if info.width as usize != TILE_SIZE || info.height as usize != TILE_SIZE {
+        return Err(format!(
+            "tiles must be {0}x{0} pixels, not {1}x{2}",
+            TILE_SIZE, info.width, info.height
+        ));
+    }
+
+    let pixels = &buffer[..info.buffer_size()];
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(String::from("palette wasn't expanded")),
+    };
+    Ok(rgba)
+}
+
+/// Halves a square RGBA image, averaging each 2×2 block of pixels.
+fn downsample(pixels: &[u8], size: usize) -> Vec<u8> {
+    let half = size / 2;
+    let mut result = vec![0; half * half * 4];
+    for y in 0..half {
+        for x in 0..half {
+            for channel in 0..4 {
+                let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .iter()
+                    .map(|(dx, dy)| pixels[((y * 2 + dy) * size + x * 2 + dx) * 4 + channel] as u32)
+                    .sum();
+                result[(y * half + x) * 4 + channel] = ((sum + 2) / 4) as u8;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::storage::temp_save_dir;
+
+    fn solid(color: [u8; 4]) -> Vec<u8> {
+        color.repeat(TILE_SIZE * TILE_SIZE)
+    }
+
+    fn pixel(atlas: &TextureAtlas, level: usize, x: usize, y: usize) -> [u8; 4] {
+        let size = atlas.size() >> level;
+        let start = (y * size + x) * 4;
+        atlas.levels()[level][start..start + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn tiles_follow_the_blank_tile() {
+        let atlas = TextureAtlas::from_tiles(vec![
+            (String::from("red"), solid([255, 0, 0, 255])),
+            (String::from("green"), solid([0, 255, 0, 255])),
+        ]);
+
+        assert_eq!(atlas.columns(), 2);
+        assert_eq!(atlas.tile("red"), Some(1));
+        assert_eq!(atlas.tile("green"), Some(2));
+        assert_eq!(atlas.tile("blue"), None);
+        assert_eq!(pixel(&atlas, 0, 0, 0), [255; 4]);
+        assert_eq!(pixel(&atlas, 0, TILE_SIZE, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&atlas, 0, 0, TILE_SIZE), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn tiles_are_stored_bottom_row_first() {
+        let mut tile = solid([0, 0, 0, 255]);
+        tile[..4].copy_from_slice(&[255, 0, 0, 255]);
+        let atlas = TextureAtlas::from_tiles(vec![(String::from("dot"), tile)]);
+
+        // The top-left pixel of the image lands at the top of its tile
+        assert_eq!(pixel(&atlas, 0, TILE_SIZE, TILE_SIZE - 1), [255, 0, 0, 255]);
+        assert_eq!(pixel(&atlas, 0, TILE_SIZE, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn mip_levels_never_blend_tiles() {
+        let atlas = TextureAtlas::from_tiles(vec![
+            (String::from("red"), solid([255, 0, 0, 255])),
+            (String::from("green"), solid([0, 255, 0, 255])),
+            (String::from("blue"), solid([0, 0, 255, 255])),
+        ]);
+
+        assert_eq!(atlas.levels().len(), MIP_LEVELS);
+        let last = MIP_LEVELS - 1;
+        assert_eq!(atlas.levels()[last].len(), atlas.columns().pow(2) * 4);
+        assert_eq!(pixel(&atlas, last, 0, 0), [255; 4]);
+        assert_eq!(pixel(&atlas, last, 1, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&atlas, last, 0, 1), [0, 255, 0, 255]);
+        assert_eq!(pixel(&atlas, last, 1, 1), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn loads_png_tiles_and_skips_bad_ones() {
+        let dir = temp_save_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, size: u32| {
+            let file = File::create(dir.join(name)).unwrap();
+            let mut encoder = png::Encoder::new(file, size, size);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[10, 20, 30].repeat((size * size) as usize))
+                .unwrap();
+        };
+        write("stone.png", TILE_SIZE as u32);
+        write("huge.png", TILE_SIZE as u32 * 2);
+        fs::write(dir.join("notes.txt"), "not a texture").unwrap();
+
+        let atlas = TextureAtlas::load(dir.to_str().unwrap());
+        assert_eq!(atlas.tile("stone"), Some(1));
+        assert_eq!(atlas.tile("huge"), None);
+        assert_eq!(atlas.tile("notes"), None);
+        assert_eq!(pixel(&atlas, 0, TILE_SIZE, 0), [10, 20, 30, 255]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
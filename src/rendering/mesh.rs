@@ -1,28 +1,108 @@
 extern crate gl;
 use gl::types::*;
-use std::ptr;
+
+/// A kind of per-vertex data, each bound to its own fixed shader location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexAttribute {
+    /// `vec3` position.
+    Position,
+    /// `vec3` outward normal of the surface.
+    Normal,
+    /// `vec4` RGBA color.
+    Color,
+    /// `vec2` texture coordinates.
+    Uv,
+    /// `vec3` sky light, block light and ambient occlusion, each in `0..=1`.
+    Light,
+    /// `float` index of the texture atlas tile to sample.
+    Tile,
+}
+
+impl VertexAttribute {
+    /// Returns the shader location the attribute is bound to.
+    pub const fn location(self) -> GLuint {
+        match self {
+            VertexAttribute::Position => 0,
+            VertexAttribute::Normal => 1,
+            VertexAttribute::Color => 2,
+            VertexAttribute::Uv => 3,
+            VertexAttribute::Light => 4,
+            VertexAttribute::Tile => 5,
+        }
+    }
+
+    /// Returns the number of floats in the attribute.
+    pub const fn size(self) -> usize {
+        match self {
+            VertexAttribute::Position | VertexAttribute::Normal | VertexAttribute::Light => 3,
+            VertexAttribute::Color => 4,
+            VertexAttribute::Uv => 2,
+            VertexAttribute::Tile => 1,
+        }
+    }
+}
+
+/// The attributes interleaved in each vertex of a mesh, in order.
+///
+/// Attributes missing from a layout are left disabled, so shaders read their
+/// default value for them.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexLayout {
+    attributes: &'static [VertexAttribute],
+}
+
+impl VertexLayout {
+    /// Creates a layout of the given attributes, which must not repeat.
+    pub const fn new(attributes: &'static [VertexAttribute]) -> Self {
+        VertexLayout { attributes }
+    }
+
+    /// Returns the number of floats per vertex.
+    pub const fn vertex_size(&self) -> usize {
+        let mut size = 0;
+        let mut index = 0;
+        while index < self.attributes.len() {
+            size += self.attributes[index].size();
+            index += 1;
+        }
+        size
+    }
+
+    /// Returns the position of an attribute's first float within a vertex, if
+    /// the layout has it.
+    pub fn offset(&self, attribute: VertexAttribute) -> Option<usize> {
+        let index = self.attributes.iter().position(|&a| a == attribute)?;
+        Some(self.attributes[..index].iter().map(|a| a.size()).sum())
+    }
+}
 
 /// Represents a 3D mesh with vertex data stored in a Vertex Array Object (VAO).
-#[derive(Clone)]
+///
+/// The GL buffers are released when the mesh is dropped, so meshes must not
+/// outlive the OpenGL context they were created in.
 pub struct Mesh {
     vao: GLuint,
+    vbo: GLuint,
     vertex_count: i32,
 }
 
 impl Mesh {
-    /// Creates a new `Mesh` from a list of vertices.
+    /// Creates a new `Mesh` from a list of interleaved vertices.
     ///
     /// # Arguments
     ///
-    /// * `vertices` - A slice of vertex positions.
+    /// * `vertices` - A slice of interleaved vertex attributes.
+    /// * `layout` - The attributes making up each vertex.
     ///
     /// # Returns
     ///
     /// A new `Mesh` instance.
-    pub fn new(vertices: &[f32]) -> Self {
+    pub fn new(vertices: &[f32], layout: &VertexLayout) -> Self {
         let mut vao: GLuint = 0;
         let mut vbo: GLuint = 0;
-        let vertex_count = (vertices.len() / 3) as i32;
+        let floats_per_vertex = layout.vertex_size();
+        let vertex_count = (vertices.len() / floats_per_vertex) as i32;
+        let stride = (floats_per_vertex * size_of::<GLfloat>()) as GLint;
 
         unsafe {
             // Create Vertex Array Object
@@ -34,35 +114,99 @@ impl Mesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * size_of::<GLfloat>()) as GLsizeiptr,
+                size_of_val(vertices) as GLsizeiptr,
                 vertices.as_ptr() as *const _,
                 gl::STATIC_DRAW,
             );
 
             // Specify the layout of the vertex data
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE as GLboolean,
-                (3 * size_of::<GLfloat>()) as GLint,
-                ptr::null(),
-            );
+            for &attribute in layout.attributes {
+                let offset = layout.offset(attribute).unwrap();
+                gl::EnableVertexAttribArray(attribute.location());
+                gl::VertexAttribPointer(
+                    attribute.location(),
+                    attribute.size() as GLint,
+                    gl::FLOAT,
+                    gl::FALSE as GLboolean,
+                    stride,
+                    (offset * size_of::<GLfloat>()) as *const _,
+                );
+            }
 
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
         }
 
-        Mesh { vao, vertex_count }
+        Mesh {
+            vao,
+            vbo,
+            vertex_count,
+        }
+    }
+
+    /// Replaces the mesh's vertices with as many new ones, such as the same faces
+    /// in a different order.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - A slice of interleaved vertex attributes, in the mesh's layout.
+    pub fn update(&mut self, vertices: &[f32]) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                size_of_val(vertices) as GLsizeiptr,
+                vertices.as_ptr() as *const _,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
     }
 
     /// Draws the mesh using OpenGL.
     pub fn draw(&self) {
+        self.draw_primitives(gl::TRIANGLES);
+    }
+
+    /// Draws the mesh as a list of line segments (one per pair of vertices).
+    pub fn draw_lines(&self) {
+        self.draw_primitives(gl::LINES);
+    }
+
+    fn draw_primitives(&self, mode: GLenum) {
         unsafe {
             gl::BindVertexArray(self.vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, self.vertex_count);
+            gl::DrawArrays(mode, 0, self.vertex_count);
             gl::BindVertexArray(0);
         }
     }
 }
+
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_interleave_attributes_in_order() {
+        const LAYOUT: VertexLayout = VertexLayout::new(&[
+            VertexAttribute::Position,
+            VertexAttribute::Color,
+            VertexAttribute::Light,
+        ]);
+
+        assert_eq!(LAYOUT.vertex_size(), 10);
+        assert_eq!(LAYOUT.offset(VertexAttribute::Position), Some(0));
+        assert_eq!(LAYOUT.offset(VertexAttribute::Color), Some(3));
+        assert_eq!(LAYOUT.offset(VertexAttribute::Light), Some(7));
+        assert_eq!(LAYOUT.offset(VertexAttribute::Normal), None);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod atlas;
 pub mod mesh;
 pub mod shader;
+pub mod texture;
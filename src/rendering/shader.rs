@@ -38,7 +38,7 @@ impl Shader {
 
     fn read_shader_source(path: &str) -> String {
         let path = Path::new(path);
-        let mut file = match File::open(&path) {
+        let mut file = match File::open(path) {
             Ok(file) => file,
             Err(why) => panic!("couldn't open {}: {}", path.display(), why),
         };
@@ -73,8 +73,7 @@ impl Shader {
             if status != (gl::TRUE as GLint) {
                 let mut len = 0;
                 gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf = Vec::with_capacity(len as usize);
-                buf.set_len((len as usize) - 1);
+                let mut buf = vec![0u8; (len as usize) - 1];
                 gl::GetShaderInfoLog(
                     shader,
                     len,
@@ -113,8 +112,7 @@ impl Shader {
             if status != (gl::TRUE as GLint) {
                 let mut len: GLint = 0;
                 gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf = Vec::with_capacity(len as usize);
-                buf.set_len((len as usize) - 1);
+                let mut buf = vec![0u8; (len as usize) - 1];
                 gl::GetProgramInfoLog(
                     program,
                     len,
@@ -0,0 +1,107 @@
+extern crate gl;
+
+use gl::types::*;
+
+use crate::rendering::atlas::TextureAtlas;
+use crate::rendering::shader::Shader;
+
+/// A texture atlas uploaded to the GPU, sampled by block faces.
+///
+/// The texture is released when dropped, so it must not outlive the OpenGL
+/// context it was created in.
+pub struct AtlasTexture {
+    id: GLuint,
+    columns: usize,
+}
+
+impl AtlasTexture {
+    /// Uploads an atlas and its mip levels.
+    ///
+    /// Texels are sampled nearest-neighbour for crisp pixels up close, blending
+    /// between mip levels in the distance. The atlas's own mip levels are used
+    /// rather than generated ones, since those would blend neighbouring tiles.
+    ///
+    /// # Arguments
+    ///
+    /// * `atlas` - The atlas to upload.
+    ///
+    /// # Returns
+    ///
+    /// A new `AtlasTexture` instance.
+    pub fn new(atlas: &TextureAtlas) -> Self {
+        let mut id: GLuint = 0;
+
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            for (level, pixels) in atlas.levels().iter().enumerate() {
+                let size = (atlas.size() >> level) as GLsizei;
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    level as GLint,
+                    gl::RGBA8 as GLint,
+                    size,
+                    size,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const _,
+                );
+            }
+
+            let max_level = atlas.levels().len() as GLint - 1;
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, max_level);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                gl::NEAREST_MIPMAP_LINEAR as GLint,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        AtlasTexture {
+            id,
+            columns: atlas.columns(),
+        }
+    }
+
+    /// Binds the atlas to texture unit 0 and points the shader's `atlas` and
+    /// `atlasColumns` uniforms at it.
+    ///
+    /// # Arguments
+    ///
+    /// * `shader` - The shader that will sample the atlas.
+    pub fn bind(&self, shader: &Shader) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+
+            let atlas_location = gl::GetUniformLocation(shader.id, c"atlas".as_ptr());
+            gl::Uniform1i(atlas_location, 0);
+            let columns_location = gl::GetUniformLocation(shader.id, c"atlasColumns".as_ptr());
+            gl::Uniform1f(columns_location, self.columns as f32);
+        }
+    }
+}
+
+impl Drop for AtlasTexture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
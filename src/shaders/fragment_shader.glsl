@@ -1,8 +1,23 @@
 #version 330 core
+in vec4 vertexColor;
+in vec2 uv;
+flat in float tile;
 out vec4 FragColor;
 
-uniform vec4 color;
+// Multiplied with the vertex color; black for the edge pass
+uniform vec4 tint;
+
+// Square grid of block textures, with `atlasColumns` tiles along each side
+uniform sampler2D atlas;
+uniform float atlasColumns;
 
 void main() {
-    FragColor = color;
-}
\ No newline at end of file
+    // Texture coordinates count blocks, so merged faces repeat the tile once per
+    // block. The gradients come from the unwrapped coordinates, so the jump at
+    // each block border doesn't pick the smallest mip level
+    vec2 origin = vec2(mod(tile, atlasColumns), floor(tile / atlasColumns));
+    vec2 atlasUv = (origin + fract(uv)) / atlasColumns;
+    vec4 texel = textureGrad(atlas, atlasUv, dFdx(uv) / atlasColumns, dFdy(uv) / atlasColumns);
+
+    FragColor = texel * vertexColor * tint;
+}
@@ -1,10 +1,42 @@
 #version 330 core
 layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec4 aColor;
+layout (location = 3) in vec2 aUv;
+layout (location = 4) in vec3 aLight;
+layout (location = 5) in float aTile;
+
+out vec4 vertexColor;
+out vec2 uv;
+flat out float tile;
 
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 
+// Unit vector pointing towards the sun
+uniform vec3 sunDirection;
+// Brightness of faces turned away from the sun
+uniform float ambientLight;
+
+// The brightness of the dimmest light level, so unlit caves aren't pitch black
+const float AMBIENT = 0.05;
+// The brightness of a fully occluded corner
+const float OCCLUDED = 0.45;
+
 void main() {
+    // Each light level is 80% as bright as the one above it; the brighter of sky
+    // and block light wins
+    float level = max(aLight.x, aLight.y);
+    float brightness = max(pow(0.8, (1.0 - level) * 15.0), AMBIENT);
+    brightness *= mix(OCCLUDED, 1.0, aLight.z);
+
+    // Faces turned towards the sun are lit fully, the rest get the ambient term
+    float sun = max(dot(aNormal, sunDirection), 0.0);
+    brightness *= ambientLight + (1.0 - ambientLight) * sun;
+
+    vertexColor = vec4(aColor.rgb * brightness, aColor.a);
+    uv = aUv;
+    tile = aTile;
     gl_Position = projection * view * model * vec4(aPos, 1.0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,92 @@
+//! Fixed-rate stepping of the simulation, independent of the frame rate.
+
+/// The most time a frame catches up on, in seconds; after a longer stall the
+/// simulation skips ahead rather than running a burst of steps.
+const MAX_CATCH_UP: f64 = 1.0;
+
+/// Splits the time between frames into steps of a fixed length, so the
+/// simulation advances the same way whatever the frame rate.
+///
+/// Time left over after the last whole step is carried into the next frame, and
+/// tells the renderer how far to interpolate towards the next step.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    /// The length of a step, in seconds.
+    step: f64,
+    /// Time not yet simulated, in seconds; always less than a step between frames.
+    accumulated: f64,
+}
+
+impl FixedTimestep {
+    /// Creates a timestep with nothing accumulated yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps_per_second` - How many steps make up a second.
+    ///
+    /// # Returns
+    ///
+    /// A new `FixedTimestep` instance.
+    pub fn new(steps_per_second: u32) -> Self {
+        FixedTimestep {
+            step: 1.0 / steps_per_second.max(1) as f64,
+            accumulated: 0.0,
+        }
+    }
+
+    /// Adds the time a frame took and returns how many steps to run for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta_time` - The time elapsed since the last frame, in seconds.
+    ///
+    /// # Returns
+    ///
+    /// The number of whole steps that fit in the time not yet simulated.
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulated = (self.accumulated + delta_time.max(0.0) as f64).min(MAX_CATCH_UP);
+        let steps = (self.accumulated / self.step).floor();
+        self.accumulated -= steps * self.step;
+        steps as u32
+    }
+
+    /// Returns how far the current frame lies between the last step and the
+    /// next, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulated / self.step).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_add_up_whatever_the_frame_rate() {
+        for fps in [24, 30, 60, 75, 144, 240] {
+            let mut timestep = FixedTimestep::new(60);
+            // A little over two and a half seconds, so the last frame ends between steps
+            let frames = fps * 5 / 2;
+            let delta_time = 2.51 / frames as f32;
+            let steps: u32 = (0..frames).map(|_| timestep.advance(delta_time)).sum();
+            assert_eq!(steps, 150, "at {} frames per second", fps);
+            assert!((timestep.alpha() - 0.6).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn leftover_time_is_carried_and_interpolated() {
+        let mut timestep = FixedTimestep::new(20);
+        assert_eq!(timestep.advance(0.125), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.025), 1);
+        assert!(timestep.alpha() < 1e-5);
+    }
+
+    #[test]
+    fn long_stalls_are_skipped() {
+        let mut timestep = FixedTimestep::new(20);
+        assert_eq!(timestep.advance(10.0), 20);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+}
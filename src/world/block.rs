@@ -1,89 +1,113 @@
-/// Represents a block in the world.
-#[derive(Clone, Copy, Debug)]
-pub struct Block {
-    pub id: u32,
+/// Identifies a block type registered in the `BlockRegistry`.
+///
+/// Ids are handed out by the registry in definition order; `BlockId::AIR` (`0`)
+/// is always reserved for empty space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u16);
+
+impl BlockId {
+    /// Empty space.
+    pub const AIR: BlockId = BlockId(0);
+
+    /// Returns `true` if this is air.
+    pub fn is_air(self) -> bool {
+        self == BlockId::AIR
+    }
 }
 
-impl Block {
-    /// Creates a new block with the given id.
+/// One of the six axis-aligned faces of a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    NegX,
+    PosX,
+    NegY,
+    PosY,
+    NegZ,
+    PosZ,
+}
+
+impl Face {
+    /// All six faces, in a fixed order.
+    pub const ALL: [Face; 6] = [
+        Face::NegX,
+        Face::PosX,
+        Face::NegY,
+        Face::PosY,
+        Face::NegZ,
+        Face::PosZ,
+    ];
+
+    /// Returns the face whose normal points along `axis` (0 = x, 1 = y, 2 = z).
     ///
     /// # Arguments
     ///
-    /// * `id` - A unique identifier for the block.
-    ///
-    /// # Returns
-    ///
-    /// A new `Block` instance.
-    pub fn new(id: u32) -> Self {
-        Block { id }
+    /// * `axis` - The axis index.
+    /// * `positive` - Whether the normal points towards the positive end of the axis.
+    pub fn from_axis(axis: usize, positive: bool) -> Face {
+        match (axis, positive) {
+            (0, false) => Face::NegX,
+            (0, true) => Face::PosX,
+            (1, false) => Face::NegY,
+            (1, true) => Face::PosY,
+            (2, false) => Face::NegZ,
+            _ => Face::PosZ,
+        }
     }
 
-    /// Returns the color of the block based on its id.
-    ///
-    /// # Returns
-    ///
-    /// An array of four `f32` values representing the RGBA color of the block.
-    pub fn get_color(&self) -> [f32; 4] {
-        match self.id {
-            0 => [0.0, 0.0, 0.0, 0.0], // Air/empty (transparent)
-            1 => [0.6, 0.3, 0.0, 1.0], // Dirt/soil (brown)
-            2 => [0.0, 0.7, 0.0, 1.0], // Grass (green)
-            3 => [0.5, 0.5, 0.5, 1.0], // Stone (gray)
-            4 => [0.9, 0.9, 0.9, 1.0], // Snow (white)
-            5 => [0.0, 0.0, 0.8, 1.0], // Water (blue)
-            _ => [1.0, 0.0, 1.0, 1.0], // Unknown (magenta)
+    /// Returns the outward unit normal of the face.
+    pub fn normal(self) -> [i32; 3] {
+        match self {
+            Face::NegX => [-1, 0, 0],
+            Face::PosX => [1, 0, 0],
+            Face::NegY => [0, -1, 0],
+            Face::PosY => [0, 1, 0],
+            Face::NegZ => [0, 0, -1],
+            Face::PosZ => [0, 0, 1],
         }
     }
 
-    /// Returns the vertices for a cube.
-    ///
-    /// # Returns
+    /// Returns the corners of the face on the unit cube `[0, 1]³`.
     ///
-    /// An array of vertices representing a cube.
-    pub fn get_cube_vertices() -> [f32; 108] {
-        [
-            // Back face
-            -0.5, -0.5, -0.5, // Bottom-left
-            0.5, -0.5, -0.5, // Bottom-right
-            0.5, 0.5, -0.5, // Top-right
-            0.5, 0.5, -0.5, // Top-right
-            -0.5, 0.5, -0.5, // Top-left
-            -0.5, -0.5, -0.5, // Bottom-left
-            // Front face
-            -0.5, -0.5, 0.5, // Bottom-left
-            0.5, -0.5, 0.5, // Bottom-right
-            0.5, 0.5, 0.5, // Top-right
-            0.5, 0.5, 0.5, // Top-right
-            -0.5, 0.5, 0.5, // Top-left
-            -0.5, -0.5, 0.5, // Bottom-left
-            // Left face
-            -0.5, 0.5, 0.5, // Top-right
-            -0.5, 0.5, -0.5, // Top-left
-            -0.5, -0.5, -0.5, // Bottom-left
-            -0.5, -0.5, -0.5, // Bottom-left
-            -0.5, -0.5, 0.5, // Bottom-right
-            -0.5, 0.5, 0.5, // Top-right
-            // Right face
-            0.5, 0.5, 0.5, // Top-left
-            0.5, 0.5, -0.5, // Top-right
-            0.5, -0.5, -0.5, // Bottom-right
-            0.5, -0.5, -0.5, // Bottom-right
-            0.5, -0.5, 0.5, // Bottom-left
-            0.5, 0.5, 0.5, // Top-left
-            // Bottom face
-            -0.5, -0.5, -0.5, // Top-right
-            0.5, -0.5, -0.5, // Top-left
-            0.5, -0.5, 0.5, // Bottom-left
-            0.5, -0.5, 0.5, // Bottom-left
-            -0.5, -0.5, 0.5, // Bottom-right
-            -0.5, -0.5, -0.5, // Top-right
-            // Top face
-            -0.5, 0.5, -0.5, // Top-left
-            0.5, 0.5, -0.5, // Top-right
-            0.5, 0.5, 0.5, // Bottom-right
-            0.5, 0.5, 0.5, // Bottom-right
-            -0.5, 0.5, 0.5, // Bottom-left
-            -0.5, 0.5, -0.5, // Top-left
-        ]
+    /// Corners are ordered bottom-left, bottom-right, top-right, top-left as seen
+    /// from outside the block, so they wind counter-clockwise.
+    pub fn corners(self) -> [[f32; 3]; 4] {
+        match self {
+            Face::NegX => [
+                [0.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, 1.0, 1.0],
+                [0.0, 1.0, 0.0],
+            ],
+            Face::PosX => [
+                [1.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [1.0, 1.0, 1.0],
+            ],
+            Face::NegY => [
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 0.0, 1.0],
+                [0.0, 0.0, 1.0],
+            ],
+            Face::PosY => [
+                [0.0, 1.0, 1.0],
+                [1.0, 1.0, 1.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            Face::NegZ => [
+                [1.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+            ],
+            Face::PosZ => [
+                [0.0, 0.0, 1.0],
+                [1.0, 0.0, 1.0],
+                [1.0, 1.0, 1.0],
+                [0.0, 1.0, 1.0],
+            ],
+        }
     }
 }
@@ -0,0 +1,327 @@
+use crate::world::block::BlockId;
+use crate::world::light::{Light, LightSection};
+use crate::world::section::{SECTION_SIZE, Section};
+
+/// The width and depth of a chunk in blocks.
+pub const CHUNK_SIZE: usize = 16;
+
+/// The position of a chunk in chunk coordinates (one unit per `CHUNK_SIZE` blocks).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    /// Creates a new `ChunkPos` from chunk coordinates.
+    pub fn new(x: i32, z: i32) -> Self {
+        ChunkPos { x, z }
+    }
+
+    /// Returns the position of the chunk containing the given world block coordinates.
+    ///
+    /// Uses euclidean division so that negative coordinates map to the correct chunk
+    /// (e.g. block `-1` lives in chunk `-1`, not chunk `0`).
+    pub fn from_block(x: i32, z: i32) -> Self {
+        ChunkPos {
+            x: x.div_euclid(CHUNK_SIZE as i32),
+            z: z.div_euclid(CHUNK_SIZE as i32),
+        }
+    }
+
+    /// Returns the world block coordinates of the chunk's minimum corner.
+    pub fn origin(&self) -> (i32, i32) {
+        (self.x * CHUNK_SIZE as i32, self.z * CHUNK_SIZE as i32)
+    }
+
+    /// Returns the four chunks sharing a side with this one.
+    pub fn neighbours(&self) -> [ChunkPos; 4] {
+        [
+            ChunkPos::new(self.x - 1, self.z),
+            ChunkPos::new(self.x + 1, self.z),
+            ChunkPos::new(self.x, self.z - 1),
+            ChunkPos::new(self.x, self.z + 1),
+        ]
+    }
+
+    /// Returns the eight chunks around this one: the four sharing a side, in the
+    /// order of `neighbours`, then the four sharing only a corner.
+    pub fn surrounding(&self) -> [ChunkPos; 8] {
+        [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ]
+        .map(|(x, z)| ChunkPos::new(self.x + x, self.z + z))
+    }
+
+    /// Returns the chessboard distance between two chunk positions.
+    pub fn distance(&self, other: ChunkPos) -> i32 {
+        (self.x - other.x).abs().max((self.z - other.z).abs())
+    }
+}
+
+/// Converts a world block coordinate into a coordinate local to its chunk.
+pub fn local_coord(v: i32) -> usize {
+    v.rem_euclid(CHUNK_SIZE as i32) as usize
+}
+
+/// The vertical extent of the world: the range of `y` coordinates blocks can occupy.
+///
+/// Both ends lie on section boundaries, so every chunk is a whole number of
+/// sections tall.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldHeight {
+    min_y: i32,
+    max_y: i32,
+}
+
+impl WorldHeight {
+    /// Creates a world height spanning `min_y..max_y`, widened to section boundaries
+    /// and to at least one section.
+    pub fn new(min_y: i32, max_y: i32) -> Self {
+        let size = SECTION_SIZE as i32;
+        let min_y = min_y.div_euclid(size) * size;
+        let max_y = (max_y.div_euclid(size) + (max_y.rem_euclid(size) != 0) as i32) * size;
+        WorldHeight {
+            min_y,
+            max_y: max_y.max(min_y + size),
+        }
+    }
+
+    /// Returns the lowest `y` a block can have.
+    pub fn min_y(&self) -> i32 {
+        self.min_y
+    }
+
+    /// Returns the `y` just above the highest block; it is outside the world.
+    pub fn max_y(&self) -> i32 {
+        self.max_y
+    }
+
+    /// Returns the range of `y` coordinates inside the world.
+    pub fn range(&self) -> std::ops::Range<i32> {
+        self.min_y..self.max_y
+    }
+
+    /// Returns `true` if `y` is inside the world.
+    pub fn contains(&self, y: i32) -> bool {
+        self.range().contains(&y)
+    }
+
+    /// Returns the number of sections stacked in each chunk.
+    pub fn sections(&self) -> usize {
+        ((self.max_y - self.min_y) / SECTION_SIZE as i32) as usize
+    }
+
+    /// Returns the `y` of the lowest block of the given section.
+    pub fn section_base(&self, section: usize) -> i32 {
+        self.min_y + (section * SECTION_SIZE) as i32
+    }
+
+    /// Returns the section containing `y` and the height of `y` within it.
+    fn locate(&self, y: i32) -> (usize, usize) {
+        let offset = (y - self.min_y) as usize;
+        (offset / SECTION_SIZE, offset % SECTION_SIZE)
+    }
+}
+
+/// A column of blocks spanning the world's height, stored as palette-compressed
+/// sections stacked from the bottom up.
+///
+/// Coordinates are local to the chunk horizontally and world coordinates vertically.
+/// Each chunk also keeps a heightmap of the highest non-air block in every column,
+/// and the light of every block, which is computed by `light` rather than saved.
+#[derive(Clone)]
+pub struct Chunk {
+    height: WorldHeight,
+    sections: Vec<Section>,
+    /// The light of each section, in the same order as `sections`.
+    light: Vec<LightSection>,
+    /// The `y` of the highest non-air block of each column, or `min_y - 1` if the
+    /// column is empty, indexed by `z * CHUNK_SIZE + x`.
+    heightmap: [i32; CHUNK_SIZE * CHUNK_SIZE],
+}
+
+impl Chunk {
+    /// Creates a new chunk filled with air.
+    pub fn new(height: WorldHeight) -> Self {
+        Chunk {
+            height,
+            sections: vec![Section::default(); height.sections()],
+            light: vec![LightSection::default(); height.sections()],
+            heightmap: [height.min_y() - 1; CHUNK_SIZE * CHUNK_SIZE],
+        }
+    }
+
+    /// Returns the vertical extent of the chunk.
+    pub fn height(&self) -> WorldHeight {
+        self.height
+    }
+
+    /// Returns the block at the given coordinates; blocks above or below the world are air.
+    pub fn get(&self, x: usize, y: i32, z: usize) -> BlockId {
+        if !self.height.contains(y) {
+            return BlockId::AIR;
+        }
+        let (section, y) = self.height.locate(y);
+        self.sections[section].get(Section::index(x, y, z))
+    }
+
+    /// Sets the block at the given coordinates; positions above or below the world
+    /// are ignored.
+    pub fn set(&mut self, x: usize, y: i32, z: usize, id: BlockId) {
+        if !self.height.contains(y) {
+            return;
+        }
+        let (section, local_y) = self.height.locate(y);
+        self.sections[section].set(Section::index(x, local_y, z), id);
+
+        let top = self.heightmap[z * CHUNK_SIZE + x];
+        if !id.is_air() && y > top {
+            self.heightmap[z * CHUNK_SIZE + x] = y;
+        } else if id.is_air() && y == top {
+            self.heightmap[z * CHUNK_SIZE + x] = self.scan_column(x, z, y - 1);
+        }
+    }
+
+    /// Returns the light at the given coordinates; above the world is open sky and
+    /// below it is dark.
+    pub fn light(&self, x: usize, y: i32, z: usize) -> Light {
+        if y >= self.height.max_y() {
+            return Light::SKY;
+        }
+        if y < self.height.min_y() {
+            return Light::DARK;
+        }
+        let (section, y) = self.height.locate(y);
+        self.light[section].get(Section::index(x, y, z))
+    }
+
+    /// Sets the light at the given coordinates; positions above or below the world
+    /// are ignored.
+    pub fn set_light(&mut self, x: usize, y: i32, z: usize, light: Light) {
+        if !self.height.contains(y) {
+            return;
+        }
+        let (section, y) = self.height.locate(y);
+        self.light[section].set(Section::index(x, y, z), light);
+    }
+
+    /// Sets the light of every block in the section containing `y`.
+    pub fn fill_section_light(&mut self, y: i32, light: Light) {
+        if self.height.contains(y) {
+            self.light[self.height.locate(y).0] = LightSection::Uniform(light);
+        }
+    }
+
+    /// Stores sections that are lit evenly as a single value.
+    pub fn compact_light(&mut self) {
+        for light in &mut self.light {
+            light.compact();
+        }
+    }
+
+    /// Returns the `y` of the highest non-air block in the given column, or one
+    /// below the world if the column is empty.
+    pub fn top_block(&self, x: usize, z: usize) -> i32 {
+        self.heightmap[z * CHUNK_SIZE + x]
+    }
+
+    /// Returns the `y` of the lowest block of every section that contains
+    /// anything but air, from the bottom up.
+    pub fn non_empty_sections(&self) -> impl Iterator<Item = i32> + '_ {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| !section.is_empty())
+            .map(|(index, _)| self.height.section_base(index))
+    }
+
+    /// Returns `true` if every block in the section containing `y` is air.
+    ///
+    /// Positions outside the world count as empty.
+    pub fn is_section_empty(&self, y: i32) -> bool {
+        !self.height.contains(y) || self.sections[self.height.locate(y).0].is_empty()
+    }
+
+    /// Replaces every block with the result of `f`, e.g. to renumber block ids.
+    pub fn map_blocks<F: Fn(BlockId) -> BlockId>(&mut self, f: F) {
+        for section in &mut self.sections {
+            section.map_blocks(&f);
+        }
+        self.rebuild_heightmap();
+    }
+
+    /// Shrinks the sections' palettes to the blocks they still contain, e.g. after
+    /// generation has replaced most of them.
+    pub fn compact(&mut self) {
+        for section in &mut self.sections {
+            section.compact();
+        }
+    }
+
+    /// Returns the number of bytes the chunk's blocks use on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.sections.iter().map(Section::heap_size).sum::<usize>()
+            + self.sections.capacity() * size_of::<Section>()
+    }
+
+    /// Serializes the sections from the bottom up, as written by `Section::write`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for section in &self.sections {
+            section.write(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Deserializes a chunk written by `to_bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The serialized sections.
+    /// * `height` - The height of the world the chunk was saved in.
+    ///
+    /// # Returns
+    ///
+    /// The chunk, or `None` if `bytes` is malformed or has data left over.
+    pub fn from_bytes(mut bytes: &[u8], height: WorldHeight) -> Option<Self> {
+        let mut chunk = Chunk::new(height);
+        for section in &mut chunk.sections {
+            *section = Section::read(&mut bytes)?;
+        }
+        chunk.rebuild_heightmap();
+        bytes.is_empty().then_some(chunk)
+    }
+
+    /// Returns the `y` of the highest non-air block in a column at or below `from`.
+    fn scan_column(&self, x: usize, z: usize, from: i32) -> i32 {
+        let mut y = from;
+        while y >= self.height.min_y() {
+            if self.is_section_empty(y) {
+                // Jump to the top of the section below
+                y = self.height.section_base(self.height.locate(y).0) - 1;
+            } else if self.get(x, y, z).is_air() {
+                y -= 1;
+            } else {
+                return y;
+            }
+        }
+        self.height.min_y() - 1
+    }
+
+    fn rebuild_heightmap(&mut self) {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                self.heightmap[z * CHUNK_SIZE + x] =
+                    self.scan_column(x, z, self.height.max_y() - 1);
+            }
+        }
+    }
+}
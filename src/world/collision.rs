@@ -0,0 +1,344 @@
+use cgmath::{Point3, Vector3};
+
+/// How close boxes may come before they count as touching. Positions are `f32`,
+/// so boxes that were stopped against a block end up a rounding error away from
+/// it rather than exactly on it.
+const CONTACT_EPSILON: f32 = 1e-3;
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    /// Creates a box from its lowest and highest corners.
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Returns the box filled by the block at world block coordinates.
+    pub fn block([x, y, z]: [i32; 3]) -> Self {
+        let min = Point3::new(x as f32, y as f32, z as f32);
+        Aabb::new(min, min + Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    /// Returns the box of something standing at `feet`, the centre of its bottom face.
+    ///
+    /// # Arguments
+    ///
+    /// * `feet` - The centre of the box's bottom face.
+    /// * `half_width` - Half the width and depth of the box.
+    /// * `height` - The height of the box.
+    pub fn from_feet(feet: Point3<f32>, half_width: f32, height: f32) -> Self {
+        Aabb::new(
+            Point3::new(feet.x - half_width, feet.y, feet.z - half_width),
+            Point3::new(feet.x + half_width, feet.y + height, feet.z + half_width),
+        )
+    }
+
+    /// Returns the box moved by an offset.
+    pub fn translated(&self, offset: Vector3<f32>) -> Self {
+        Aabb::new(self.min + offset, self.max + offset)
+    }
+
+    /// Returns the smallest box containing both boxes.
+    fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Point3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            Point3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        )
+    }
+
+    /// Returns whether the boxes overlap by more than touching.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.overlaps_on(other, axis))
+    }
+
+    /// Returns whether the boxes overlap along one axis by more than touching.
+    fn overlaps_on(&self, other: &Aabb, axis: usize) -> bool {
+        self.min[axis] < other.max[axis] - CONTACT_EPSILON
+            && self.max[axis] > other.min[axis] + CONTACT_EPSILON
+    }
+
+    /// Returns how far the box can move along an axis, up to `distance`, before
+    /// running into `obstacle`.
+    fn clip(&self, obstacle: &Aabb, axis: usize, distance: f32) -> f32 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        if !self.overlaps_on(obstacle, u) || !self.overlaps_on(obstacle, v) {
+            return distance;
+        }
+        if distance > 0.0 && self.max[axis] <= obstacle.min[axis] + CONTACT_EPSILON {
+            distance.min((obstacle.min[axis] - self.max[axis]).max(0.0))
+        } else if distance < 0.0 && self.min[axis] >= obstacle.max[axis] - CONTACT_EPSILON {
+            distance.max((obstacle.max[axis] - self.min[axis]).min(0.0))
+        } else {
+            distance
+        }
+    }
+}
+
+/// Which axes a move was stopped along.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Collisions {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl Collisions {
+    /// Returns whether the move was stopped sideways.
+    pub fn horizontal(&self) -> bool {
+        self.x || self.z
+    }
+}
+
+/// The outcome of sweeping a box through the world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweptMove {
+    /// How far the box actually moved.
+    pub offset: Vector3<f32>,
+    /// The axes it was stopped along before moving as far as it wanted.
+    pub collided: Collisions,
+}
+
+impl SweptMove {
+    /// Returns whether the box came to rest on something below it.
+    pub fn landed(&self, movement: Vector3<f32>) -> bool {
+        self.collided.y && movement.y < 0.0
+    }
+}
+
+/// Moves a box through solid blocks as far as it can, one axis at a time:
+/// vertically first, then along `x`, then along `z`. Each axis is cut short at
+/// the first block in the way, however far the box moves, so it never passes
+/// through blocks.
+///
+/// A box that lands and is stopped sideways tries again raised by up to
+/// `step_height`, then lowered back onto whatever it climbed, and keeps that
+/// move if it gets further. This walks it up ledges no taller than `step_height`.
+///
+/// # Arguments
+///
+/// * `aabb` - The box to move.
+/// * `movement` - How far it wants to move.
+/// * `step_height` - The tallest ledge it climbs without jumping.
+/// * `solid` - Returns whether the block at world block coordinates stops boxes.
+///
+/// # Returns
+///
+/// How far the box moved and along which axes it was stopped.
+pub fn sweep<F>(aabb: Aabb, movement: Vector3<f32>, step_height: f32, solid: F) -> SweptMove
+where
+    F: Fn([i32; 3]) -> bool,
+{
+    // Every block the box could touch between where it starts and where it
+    // wants to end up, raised by the step height or not
+    let step = Vector3::new(0.0, step_height.max(0.0), 0.0);
+    let reach = aabb
+        .union(&aabb.translated(step))
+        .union(&aabb.translated(movement))
+        .union(&aabb.translated(movement + step));
+    let mut obstacles = Vec::new();
+    let floor = |v: f32| v.floor() as i32;
+    for x in floor(reach.min.x)..=floor(reach.max.x) {
+        for y in floor(reach.min.y)..=floor(reach.max.y) {
+            for z in floor(reach.min.z)..=floor(reach.max.z) {
+                if solid([x, y, z]) {
+                    obstacles.push(Aabb::block([x, y, z]));
+                }
+            }
+        }
+    }
+
+    let direct = slide(aabb, movement, [1, 0, 2], &obstacles);
+    if step_height <= 0.0 || !direct.landed(movement) || !direct.collided.horizontal() {
+        return direct;
+    }
+
+    // Climb, move sideways from up there, then settle back down onto the ledge
+    let climb = slide(aabb, Vector3::new(0.0, step_height, 0.0), [1], &obstacles);
+    let raised = aabb.translated(climb.offset);
+    let across = slide(
+        raised,
+        Vector3::new(movement.x, 0.0, movement.z),
+        [0, 2],
+        &obstacles,
+    );
+    let drop = climb.offset.y - movement.y;
+    let settle = slide(
+        raised.translated(across.offset),
+        Vector3::new(0.0, -drop, 0.0),
+        [1],
+        &obstacles,
+    );
+
+    let horizontal = |offset: Vector3<f32>| offset.x * offset.x + offset.z * offset.z;
+    let stepped_offset = climb.offset + across.offset + settle.offset;
+    if horizontal(stepped_offset) <= horizontal(direct.offset) {
+        return direct;
+    }
+    SweptMove {
+        offset: stepped_offset,
+        collided: Collisions {
+            y: settle.collided.y,
+            ..across.collided
+        },
+    }
+}
+
+/// Moves a box along each of the given axes in turn, stopping each axis at the
+/// first obstacle in the way.
+fn slide<const N: usize>(
+    aabb: Aabb,
+    movement: Vector3<f32>,
+    axes: [usize; N],
+    obstacles: &[Aabb],
+) -> SweptMove {
+    let mut moved = aabb;
+    let mut offset = Vector3::new(0.0, 0.0, 0.0);
+    let mut collided = Collisions::default();
+    for axis in axes {
+        let wanted = movement[axis];
+        if wanted == 0.0 {
+            continue;
+        }
+        let distance = obstacles.iter().fold(wanted, |distance, obstacle| {
+            moved.clip(obstacle, axis, distance)
+        });
+        let mut step = Vector3::new(0.0, 0.0, 0.0);
+        step[axis] = distance;
+        moved = moved.translated(step);
+        offset[axis] = distance;
+        if distance != wanted {
+            match axis {
+                0 => collided.x = true,
+                1 => collided.y = true,
+                _ => collided.z = true,
+            }
+        }
+    }
+    SweptMove { offset, collided }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// A player-sized box standing at `feet`.
+    fn player(feet: [f32; 3]) -> Aabb {
+        Aabb::from_feet(Point3::new(feet[0], feet[1], feet[2]), 0.3, 1.8)
+    }
+
+    /// A floor of blocks at `y = -1`, plus the given blocks.
+    fn world(blocks: &[[i32; 3]]) -> impl Fn([i32; 3]) -> bool {
+        let blocks: HashSet<[i32; 3]> = blocks.iter().copied().collect();
+        move |pos: [i32; 3]| pos[1] == -1 || blocks.contains(&pos)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn free_moves_go_the_whole_way() {
+        let movement = Vector3::new(0.5, 0.4, -0.25);
+        let moved = sweep(player([0.5, 0.0, 0.5]), movement, 0.0, world(&[]));
+        assert_eq!(moved.offset, movement);
+        assert_eq!(moved.collided, Collisions::default());
+    }
+
+    #[test]
+    fn falls_stop_on_the_floor_without_tunnelling() {
+        let moved = sweep(
+            player([0.5, 30.0, 0.5]),
+            Vector3::new(0.0, -50.0, 0.0),
+            0.0,
+            world(&[]),
+        );
+        assert_close(moved.offset.y, -30.0);
+        assert!(moved.landed(Vector3::new(0.0, -50.0, 0.0)));
+    }
+
+    #[test]
+    fn fast_moves_stop_at_the_first_wall() {
+        let moved = sweep(
+            player([0.5, 0.0, 0.5]),
+            Vector3::new(15.0, 0.0, 0.0),
+            0.0,
+            world(&[[3, 0, 0], [8, 0, 0]]),
+        );
+        // The box's side stops against the near face of the first wall
+        assert_close(moved.offset.x, 3.0 - 0.8);
+        assert!(moved.collided.x && !moved.collided.y && !moved.collided.z);
+    }
+
+    #[test]
+    fn blocked_axes_slide_along_walls() {
+        let moved = sweep(
+            player([0.5, 0.0, 0.5]),
+            Vector3::new(1.0, 0.0, 0.5),
+            0.0,
+            world(&[[1, 0, 0], [1, 1, 0], [1, 0, 1], [1, 1, 1]]),
+        );
+        assert_close(moved.offset.x, 0.2);
+        assert_close(moved.offset.z, 0.5);
+        assert!(moved.collided.x && !moved.collided.z);
+    }
+
+    #[test]
+    fn ledges_are_climbed_up_to_the_step_height() {
+        let movement = Vector3::new(0.5, -0.1, 0.0);
+        let start = player([0.6, 0.0, 0.5]);
+
+        let blocked = sweep(start, movement, 0.6, world(&[[1, 0, 0]]));
+        assert_close(blocked.offset.x, 0.1);
+        assert_close(blocked.offset.y, 0.0);
+        assert!(blocked.collided.x);
+
+        let stepped = sweep(start, movement, 1.0, world(&[[1, 0, 0]]));
+        assert_close(stepped.offset.x, 0.5);
+        assert_close(stepped.offset.y, 1.0);
+        assert!(stepped.landed(movement));
+
+        // Nothing is climbed with a ceiling in the way
+        let capped = sweep(
+            start,
+            movement,
+            1.0,
+            world(&[[1, 0, 0], [0, 2, 0], [1, 2, 0]]),
+        );
+        assert_close(capped.offset.x, 0.1);
+    }
+
+    #[test]
+    fn boxes_touching_a_wall_can_still_move_along_it() {
+        // The player rests exactly against the wall after being stopped by it
+        let wall = world(&[[1, 0, 0], [1, 1, 0]]);
+        let first = sweep(
+            player([0.5, 0.0, 0.5]),
+            Vector3::new(1.0, -0.1, 0.0),
+            0.0,
+            &wall,
+        );
+        let box_after = player([0.5, 0.0, 0.5]).translated(first.offset);
+        let along = sweep(box_after, Vector3::new(0.0, -0.1, 2.0), 0.0, &wall);
+        assert_close(along.offset.z, 2.0);
+        assert!(!along.collided.x && !along.collided.z);
+    }
+}
@@ -1,82 +1,242 @@
 extern crate gl;
 
-use cgmath::{Matrix, Matrix4, Vector3};
+use cgmath::{EuclideanSpace, Matrix, Matrix4, Point3, Vector3};
 use gl::types::*;
 
-use crate::rendering::mesh::Mesh;
+use crate::rendering::mesh::{Mesh, VertexAttribute, VertexLayout};
 use crate::rendering::shader::Shader;
-use crate::world::block::Block;
+use crate::world::chunk::{CHUNK_SIZE, ChunkPos};
+use crate::world::mesher::{CHUNK_VERTEX_LAYOUT, sort_back_to_front};
 
-/// Draws a chunk of blocks using the given mesh and shader.
+/// Draws a chunk's baked mesh using the given shader.
 ///
 /// # Arguments
 ///
-/// * `chunk_data` - A 3D array representing the chunk data.
-/// * `mesh` - The mesh to use for rendering.
+/// * `mesh` - The baked mesh of the chunk.
+/// * `pos` - The position of the chunk in the world.
 /// * `shader` - The shader to use for rendering.
 /// * `time` - The current time (used for animations).
-pub fn draw_chunk(chunk_data: &[[[u32; 16]; 16]; 16], mesh: &Mesh, shader: &Shader, _time: f32) {
+/// * `show_edges` - Whether to draw the debug wireframe around every block.
+pub fn draw_chunk(mesh: &Mesh, pos: ChunkPos, shader: &Shader, _time: f32, show_edges: bool) {
+    set_chunk_model(pos, shader);
+
     // First pass: Draw solid blocks
-    draw_solid_blocks(chunk_data, mesh, shader);
+    draw_solid_blocks(mesh, shader);
 
     // Second pass: Draw edges
-    draw_block_edges(chunk_data, mesh, shader);
+    if show_edges {
+        draw_block_edges(mesh, shader);
+    }
 }
 
-/// Draw the solid blocks
+/// A chunk's translucent faces, with a copy of their vertices kept so they can be
+/// re-sorted as the camera moves.
+pub struct TranslucentMesh {
+    mesh: Mesh,
+    vertices: Vec<f32>,
+    /// The chunk-local block the camera was in when the faces were last sorted.
+    sorted_from: Option<[i32; 3]>,
+}
+
+impl TranslucentMesh {
+    /// Uploads a chunk's translucent faces; they are sorted before being drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The chunk's translucent vertices, in `CHUNK_VERTEX_LAYOUT`.
+    pub fn new(vertices: Vec<f32>) -> Self {
+        TranslucentMesh {
+            mesh: Mesh::new(&vertices, &CHUNK_VERTEX_LAYOUT),
+            vertices,
+            sorted_from: None,
+        }
+    }
+
+    /// Sorts the faces back to front as seen from `eye`, in chunk-local
+    /// coordinates. Faces are only re-sorted when the camera enters another block.
+    fn sort_for(&mut self, eye: [f32; 3]) {
+        let block = eye.map(|v| v.floor() as i32);
+        if self.sorted_from == Some(block) {
+            return;
+        }
+        sort_back_to_front(&mut self.vertices, eye);
+        self.mesh.update(&self.vertices);
+        self.sorted_from = Some(block);
+    }
+}
+
+/// Draws the translucent faces of chunks blended over everything drawn so far.
+///
+/// Chunks are drawn farthest first and their faces sorted back to front, so
+/// nearer faces blend over farther ones. Depth writes are off, so translucent
+/// faces never hide each other.
 ///
 /// # Arguments
 ///
-/// * `chunk_data` - A 3D array representing the chunk data.
-/// * `mesh` - The mesh to use for rendering.
+/// * `meshes` - The translucent faces of each chunk.
+/// * `eye` - The camera position.
 /// * `shader` - The shader to use for rendering.
-fn draw_solid_blocks(chunk_data: &[[[u32; 16]; 16]; 16], mesh: &Mesh, shader: &Shader) {
+pub fn draw_translucent_chunks(
+    meshes: Vec<(ChunkPos, &mut TranslucentMesh)>,
+    eye: Point3<f32>,
+    shader: &Shader,
+) {
+    let local_eye = |pos: ChunkPos| {
+        let (origin_x, origin_z) = pos.origin();
+        [eye.x - origin_x as f32, eye.y, eye.z - origin_z as f32]
+    };
+    let centre_distance = |pos: ChunkPos| {
+        let [x, _, z] = local_eye(pos);
+        let half = CHUNK_SIZE as f32 / 2.0;
+        (x - half).powi(2) + (z - half).powi(2)
+    };
+    let mut meshes = meshes;
+    meshes.sort_by(|a, b| centre_distance(b.0).total_cmp(&centre_distance(a.0)));
+
     unsafe {
-        for x in 0..16 {
-            for y in 0..16 {
-                for z in 0..16 {
-                    let block_id = chunk_data[x][y][z];
-                    if block_id != 0 {
-                        // Model matrix - use consistent cube size
-                        let model_location =
-                            gl::GetUniformLocation(shader.id, b"model\0".as_ptr() as *const GLchar);
-
-                        // Position cubes with exact 1.0 unit size for perfect cubes
-                        // Use pure translation without scaling to maintain cube proportions
-                        let model: Matrix4<f32> =
-                            Matrix4::from_translation(Vector3::new(x as f32, y as f32, z as f32));
-
-                        gl::UniformMatrix4fv(
-                            model_location,
-                            1,
-                            gl::FALSE as GLboolean,
-                            model.as_ptr(),
-                        );
-
-                        // Apply color based on block ID
-                        let block = Block::new(block_id);
-                        let color = block.get_color();
-                        let color_location =
-                            gl::GetUniformLocation(shader.id, b"color\0".as_ptr() as *const GLchar);
-                        gl::Uniform4f(color_location, color[0], color[1], color[2], color[3]);
-
-                        // Draw the cube
-                        mesh.draw();
-                    }
-                }
-            }
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::DepthMask(gl::FALSE);
+
+        let tint_location = gl::GetUniformLocation(shader.id, c"tint".as_ptr());
+        gl::Uniform4f(tint_location, 1.0, 1.0, 1.0, 1.0);
+    }
+
+    for (pos, mesh) in meshes {
+        mesh.sort_for(local_eye(pos));
+        set_chunk_model(pos, shader);
+        mesh.mesh.draw();
+    }
+
+    unsafe {
+        gl::DepthMask(gl::TRUE);
+        gl::Disable(gl::BLEND);
+    }
+}
+
+/// The attributes of a block outline vertex.
+pub const OUTLINE_VERTEX_LAYOUT: VertexLayout =
+    VertexLayout::new(&[VertexAttribute::Position, VertexAttribute::Color]);
+
+/// Returns the line vertices of a unit block outline, in `OUTLINE_VERTEX_LAYOUT`.
+///
+/// The outline is inflated slightly so it isn't hidden by the block's own faces.
+pub fn outline_vertices() -> Vec<f32> {
+    const INFLATE: f32 = 0.002;
+    let (low, high) = (-INFLATE, 1.0 + INFLATE);
+    let corner = |x: bool, y: bool, z: bool| {
+        let pick = |v: bool| if v { high } else { low };
+        [pick(x), pick(y), pick(z)]
+    };
+
+    let mut vertices = Vec::new();
+    let mut push_edge = |a: [f32; 3], b: [f32; 3]| {
+        for position in [a, b] {
+            vertices.extend_from_slice(&position);
+            vertices.extend_from_slice(&[0.0, 0.0, 0.0, 1.0]); // Black
+        }
+    };
+
+    for a in [false, true] {
+        for b in [false, true] {
+            // One edge along each axis for every combination of the other two
+            push_edge(corner(false, a, b), corner(true, a, b));
+            push_edge(corner(a, false, b), corner(a, true, b));
+            push_edge(corner(a, b, false), corner(a, b, true));
         }
     }
+
+    vertices
+}
+
+/// Draws an outline around a single block.
+///
+/// # Arguments
+///
+/// * `outline` - The mesh built from `outline_vertices`.
+/// * `block` - The world coordinates of the block to outline.
+/// * `shader` - The shader to use for rendering.
+pub fn draw_block_outline(outline: &Mesh, block: [i32; 3], shader: &Shader) {
+    let model = Matrix4::from_translation(Vector3::new(
+        block[0] as f32,
+        block[1] as f32,
+        block[2] as f32,
+    ));
+
+    unsafe {
+        let model_location = gl::GetUniformLocation(shader.id, c"model".as_ptr());
+        gl::UniformMatrix4fv(model_location, 1, gl::FALSE as GLboolean, model.as_ptr());
+
+        let tint_location = gl::GetUniformLocation(shader.id, c"tint".as_ptr());
+        gl::Uniform4f(tint_location, 1.0, 1.0, 1.0, 1.0);
+
+        gl::LineWidth(2.0);
+    }
+
+    outline.draw_lines();
+}
+
+/// Draws a block that isn't part of a chunk, such as a falling block or an item.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh built from `block_vertices`.
+/// * `corner` - The world position of the block's lowest corner.
+/// * `size` - The width of the drawn block, in blocks.
+/// * `shader` - The shader to use for rendering.
+pub fn draw_entity(mesh: &Mesh, corner: Point3<f32>, size: f32, shader: &Shader) {
+    let model = Matrix4::from_translation(corner.to_vec()) * Matrix4::from_scale(size);
+
+    unsafe {
+        let model_location = gl::GetUniformLocation(shader.id, c"model".as_ptr());
+        gl::UniformMatrix4fv(model_location, 1, gl::FALSE as GLboolean, model.as_ptr());
+
+        let tint_location = gl::GetUniformLocation(shader.id, c"tint".as_ptr());
+        gl::Uniform4f(tint_location, 1.0, 1.0, 1.0, 1.0);
+    }
+
+    mesh.draw();
+}
+
+/// Uploads the model matrix translating chunk-local vertices into the world.
+///
+/// # Arguments
+///
+/// * `pos` - The position of the chunk in the world.
+/// * `shader` - The shader to use for rendering.
+fn set_chunk_model(pos: ChunkPos, shader: &Shader) {
+    let (origin_x, origin_z) = pos.origin();
+    let model = Matrix4::from_translation(Vector3::new(origin_x as f32, 0.0, origin_z as f32));
+
+    unsafe {
+        let model_location = gl::GetUniformLocation(shader.id, c"model".as_ptr());
+        gl::UniformMatrix4fv(model_location, 1, gl::FALSE as GLboolean, model.as_ptr());
+    }
+}
+
+/// Draw the solid blocks
+///
+/// # Arguments
+///
+/// * `mesh` - The baked mesh of the chunk.
+/// * `shader` - The shader to use for rendering.
+fn draw_solid_blocks(mesh: &Mesh, shader: &Shader) {
+    unsafe {
+        // Use the per-vertex block colors as-is
+        let tint_location = gl::GetUniformLocation(shader.id, c"tint".as_ptr());
+        gl::Uniform4f(tint_location, 1.0, 1.0, 1.0, 1.0);
+    }
+
+    mesh.draw();
 }
 
 /// Draw the edges of blocks
 ///
 /// # Arguments
 ///
-/// * `chunk_data` - A 3D array representing the chunk data.
-/// * `mesh` - The mesh to use for rendering.
+/// * `mesh` - The baked mesh of the chunk.
 /// * `shader` - The shader to use for rendering.
-fn draw_block_edges(chunk_data: &[[[u32; 16]; 16]; 16], mesh: &Mesh, shader: &Shader) {
+fn draw_block_edges(mesh: &Mesh, shader: &Shader) {
     unsafe {
         // Save the current polygon mode
         let mut polygon_mode = [0];
@@ -93,36 +253,11 @@ fn draw_block_edges(chunk_data: &[[[u32; 16]; 16]; 16], mesh: &Mesh, shader: &Sh
         gl::PolygonOffset(-1.0, -1.0);
 
         // Set edge color (black)
-        let color_location =
-            gl::GetUniformLocation(shader.id, b"color\0".as_ptr() as *const GLchar);
-        gl::Uniform4f(color_location, 0.0, 0.0, 0.0, 1.0);
-
-        for x in 0..16 {
-            for y in 0..16 {
-                for z in 0..16 {
-                    let block_id = chunk_data[x][y][z];
-                    if block_id != 0 {
-                        // Model matrix
-                        let model_location =
-                            gl::GetUniformLocation(shader.id, b"model\0".as_ptr() as *const GLchar);
-
-                        // Create basic model matrix for position - use same size as solid cubes
-                        let model =
-                            Matrix4::from_translation(Vector3::new(x as f32, y as f32, z as f32));
-
-                        gl::UniformMatrix4fv(
-                            model_location,
-                            1,
-                            gl::FALSE as GLboolean,
-                            model.as_ptr(),
-                        );
-
-                        // Draw the wireframe
-                        mesh.draw();
-                    }
-                }
-            }
-        }
+        let tint_location = gl::GetUniformLocation(shader.id, c"tint".as_ptr());
+        gl::Uniform4f(tint_location, 0.0, 0.0, 0.0, 1.0);
+
+        // Draw the wireframe
+        mesh.draw();
 
         // Disable polygon offset
         gl::Disable(gl::POLYGON_OFFSET_LINE);
@@ -0,0 +1,253 @@
+use cgmath::Point3;
+
+use crate::world::block::BlockId;
+use crate::world::registry::BlockRegistry;
+use crate::world::world::TICKS_PER_SECOND;
+
+/// How much faster falling blocks and items fall each tick, in blocks per tick.
+const GRAVITY: f32 = 0.04;
+
+/// The fastest anything falls, in blocks per tick.
+const TERMINAL_VELOCITY: f32 = 2.0;
+
+/// How many ticks a dropped item lies around before it disappears.
+const ITEM_LIFETIME: u32 = 5 * 60 * TICKS_PER_SECOND;
+
+/// The width, depth and height of a dropped item, in blocks.
+pub const ITEM_SIZE: f32 = 0.25;
+
+/// Returns whether falling blocks and items fall through a block.
+pub fn falls_through(registry: &BlockRegistry, block: BlockId) -> bool {
+    block.is_air() || registry.liquid(block).is_some()
+}
+
+/// Pulls something down by one tick of gravity, stopping it on top of the first
+/// block below that it can't fall through.
+///
+/// Only the column containing `position` is checked, however fast it falls.
+///
+/// # Returns
+///
+/// The block it rests on and the block it rests in, or `None` while it's falling.
+fn fall<F>(
+    registry: &BlockRegistry,
+    position: &mut Point3<f32>,
+    velocity: &mut f32,
+    block: F,
+) -> Option<(BlockId, [i32; 3])>
+where
+    F: Fn([i32; 3]) -> BlockId,
+{
+    let (x, z) = (position.x.floor() as i32, position.z.floor() as i32);
+    *velocity = (*velocity + GRAVITY).min(TERMINAL_VELOCITY);
+    let target = position.y - *velocity;
+
+    for y in (target.floor() as i32..position.y.floor() as i32).rev() {
+        let below = block([x, y, z]);
+        if !falls_through(registry, below) {
+            position.y = (y + 1) as f32;
+            *velocity = 0.0;
+            return Some((below, [x, y + 1, z]));
+        }
+    }
+    position.y = target;
+    None
+}
+
+/// How a falling block came to rest.
+#[derive(Debug, PartialEq)]
+pub enum Landing {
+    /// It landed on solid ground and becomes a block again in this cell.
+    Block([i32; 3]),
+    /// It landed on something else and broke into an item.
+    Item(DroppedItem),
+}
+
+/// A block that lost its support, falling until it lands.
+#[derive(Debug)]
+pub struct FallingBlock {
+    pub block: BlockId,
+    /// The block's lowest corner, always aligned with a block column.
+    pub position: Point3<f32>,
+    velocity: f32,
+}
+
+impl FallingBlock {
+    /// Starts a block falling from its cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The falling block.
+    /// * `pos` - The world coordinates of the cell it falls from.
+    pub fn new(block: BlockId, pos: [i32; 3]) -> Self {
+        FallingBlock {
+            block,
+            position: Point3::new(pos[0] as f32, pos[1] as f32, pos[2] as f32),
+            velocity: 0.0,
+        }
+    }
+
+    /// Moves the block down by one tick.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - The block registry describing what it can land on.
+    /// * `block` - Returns the block at world coordinates.
+    ///
+    /// # Returns
+    ///
+    /// How the block came to rest, or `None` while it's still falling.
+    pub fn tick<F>(&mut self, registry: &BlockRegistry, block: F) -> Option<Landing>
+    where
+        F: Fn([i32; 3]) -> BlockId,
+    {
+        let (support, cell) = fall(registry, &mut self.position, &mut self.velocity, block)?;
+        if registry.is_solid(support) {
+            Some(Landing::Block(cell))
+        } else {
+            Some(Landing::Item(DroppedItem::new(self.block, cell)))
+        }
+    }
+}
+
+/// A block lying around as an item after it couldn't be placed. Items fall like
+/// falling blocks, and disappear after a few minutes.
+#[derive(Debug, PartialEq)]
+pub struct DroppedItem {
+    pub block: BlockId,
+    /// The centre of the item's bottom face.
+    pub position: Point3<f32>,
+    velocity: f32,
+    /// The number of ticks since the item was dropped.
+    age: u32,
+}
+
+impl DroppedItem {
+    /// Drops an item in the middle of a cell's floor.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The block the item is of.
+    /// * `pos` - The world coordinates of the cell it lies in.
+    pub fn new(block: BlockId, pos: [i32; 3]) -> Self {
+        DroppedItem {
+            block,
+            position: Point3::new(pos[0] as f32 + 0.5, pos[1] as f32, pos[2] as f32 + 0.5),
+            velocity: 0.0,
+            age: 0,
+        }
+    }
+
+    /// Ages the item by one tick, letting it fall if nothing holds it up.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - The block registry describing what it can rest on.
+    /// * `block` - Returns the block at world coordinates.
+    ///
+    /// # Returns
+    ///
+    /// `false` once the item has disappeared.
+    pub fn tick<F>(&mut self, registry: &BlockRegistry, block: F) -> bool
+    where
+        F: Fn([i32; 3]) -> BlockId,
+    {
+        fall(registry, &mut self.position, &mut self.velocity, block);
+        self.age += 1;
+        self.age < ITEM_LIFETIME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Ticks a falling block until it lands, within a time limit.
+    fn land<F>(registry: &BlockRegistry, falling: &mut FallingBlock, block: F) -> Landing
+    where
+        F: Fn([i32; 3]) -> BlockId,
+    {
+        for _ in 0..200 {
+            if let Some(landing) = falling.tick(registry, &block) {
+                return landing;
+            }
+        }
+        panic!("the block never landed");
+    }
+
+    #[test]
+    fn falling_blocks_land_on_solid_ground_through_liquid() {
+        let registry = BlockRegistry::default();
+        let (sand, stone, water) = (
+            registry.expect_id("sand"),
+            registry.expect_id("stone"),
+            registry.expect_id("water"),
+        );
+        let world = |[_, y, _]: [i32; 3]| match y {
+            ..0 => stone,
+            0..3 => water,
+            _ => BlockId::AIR,
+        };
+
+        let mut falling = FallingBlock::new(sand, [4, 40, -2]);
+        assert_eq!(
+            land(&registry, &mut falling, world),
+            Landing::Block([4, 0, -2])
+        );
+        assert_eq!(falling.position.y, 0.0);
+    }
+
+    #[test]
+    fn fast_blocks_do_not_skip_thin_floors() {
+        let registry = BlockRegistry::default();
+        let (sand, stone) = (registry.expect_id("sand"), registry.expect_id("stone"));
+        let world = |[_, y, _]: [i32; 3]| if y == 10 { stone } else { BlockId::AIR };
+
+        let mut falling = FallingBlock::new(sand, [0, 300, 0]);
+        assert_eq!(
+            land(&registry, &mut falling, world),
+            Landing::Block([0, 11, 0])
+        );
+    }
+
+    #[test]
+    fn blocks_landing_on_non_full_blocks_drop_as_items() {
+        let registry = BlockRegistry::from_toml(
+            r#"
+            [[block]]
+            name = "sand"
+            color = [1.0, 1.0, 1.0, 1.0]
+            falls = true
+
+            [[block]]
+            name = "flower"
+            color = [1.0, 0.0, 0.0, 1.0]
+            solid = false
+            transparent = true
+            "#,
+        )
+        .unwrap();
+        let (sand, flower) = (registry.expect_id("sand"), registry.expect_id("flower"));
+        let mut blocks = HashMap::new();
+        blocks.insert([0, 0, 0], flower);
+        let world = |pos: [i32; 3]| blocks.get(&pos).copied().unwrap_or(BlockId::AIR);
+
+        let mut falling = FallingBlock::new(sand, [0, 5, 0]);
+        let Landing::Item(mut item) = land(&registry, &mut falling, world) else {
+            panic!("the block landed whole on a flower");
+        };
+        assert_eq!(item.block, sand);
+        assert_eq!(item.position, Point3::new(0.5, 1.0, 0.5));
+
+        // The item rests until it disappears, and falls once the flower is gone
+        assert!(item.tick(&registry, world));
+        assert_eq!(item.position.y, 1.0);
+        blocks.clear();
+        let world = |pos: [i32; 3]| blocks.get(&pos).copied().unwrap_or(BlockId::AIR);
+        assert!(item.tick(&registry, world));
+        assert!(item.position.y < 1.0);
+        while item.tick(&registry, world) {}
+        assert_eq!(item.age, ITEM_LIFETIME);
+    }
+}
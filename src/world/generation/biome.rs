@@ -0,0 +1,229 @@
+use crate::world::generation::noise::FractalNoise;
+
+/// The kinds of terrain the world generator can produce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Desert,
+    Mountains,
+    SnowyTundra,
+    Ocean,
+    Forest,
+}
+
+/// Controls how high and how rough a biome's terrain is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeightProfile {
+    /// Fraction of the world's height variation added to its base height.
+    pub offset: f64,
+    /// Multiplier applied to the world's height variation.
+    pub variation: f64,
+}
+
+impl Biome {
+    /// All biomes, in a fixed order.
+    pub const ALL: [Biome; 6] = [
+        Biome::Plains,
+        Biome::Desert,
+        Biome::Mountains,
+        Biome::SnowyTundra,
+        Biome::Ocean,
+        Biome::Forest,
+    ];
+
+    /// Returns the display name of the biome.
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Plains => "Plains",
+            Biome::Desert => "Desert",
+            Biome::Mountains => "Mountains",
+            Biome::SnowyTundra => "Snowy Tundra",
+            Biome::Ocean => "Ocean",
+            Biome::Forest => "Forest",
+        }
+    }
+
+    /// Returns the `(temperature, humidity)` the biome is centred on.
+    ///
+    /// Each column belongs to the biome whose climate is closest to its own.
+    pub fn climate(self) -> (f64, f64) {
+        match self {
+            Biome::Plains => (0.15, -0.1),
+            Biome::Desert => (0.7, -0.6),
+            Biome::Mountains => (-0.3, -0.45),
+            Biome::SnowyTundra => (-0.7, 0.1),
+            Biome::Ocean => (0.0, 0.7),
+            Biome::Forest => (0.35, 0.35),
+        }
+    }
+
+    /// Returns the shape of the biome's terrain.
+    pub fn height_profile(self) -> HeightProfile {
+        let (offset, variation) = match self {
+            Biome::Plains => (0.0, 0.4),
+            Biome::Desert => (0.0, 0.3),
+            Biome::Mountains => (0.6, 1.4),
+            Biome::SnowyTundra => (0.1, 0.5),
+            Biome::Ocean => (-0.8, 0.4),
+            Biome::Forest => (0.1, 0.6),
+        };
+        HeightProfile { offset, variation }
+    }
+
+    /// Returns the names of the block covering the surface and the blocks just below it.
+    pub fn surface_blocks(self) -> (&'static str, &'static str) {
+        match self {
+            Biome::Plains | Biome::Forest => ("grass", "dirt"),
+            Biome::Desert | Biome::Ocean => ("sand", "sand"),
+            Biome::Mountains => ("stone", "stone"),
+            Biome::SnowyTundra => ("snow", "dirt"),
+        }
+    }
+
+    /// Returns the chance (0-1) that each attempt to grow a tree in the biome succeeds.
+    pub fn tree_chance(self) -> f64 {
+        match self {
+            Biome::Forest => 0.6,
+            Biome::SnowyTundra => 0.15,
+            Biome::Plains => 0.08,
+            Biome::Mountains => 0.05,
+            Biome::Desert | Biome::Ocean => 0.0,
+        }
+    }
+
+    /// Returns the chance (0-1) that a chunk in the biome gets a boulder.
+    pub fn boulder_chance(self) -> f64 {
+        match self {
+            Biome::Mountains => 0.5,
+            Biome::Plains | Biome::SnowyTundra => 0.1,
+            Biome::Forest | Biome::Desert => 0.05,
+            Biome::Ocean => 0.0,
+        }
+    }
+}
+
+/// How much further away (in climate space) than the closest biome another biome
+/// can be and still contribute to a column's height.
+const BLEND_WIDTH: f64 = 0.3;
+
+/// Selects biomes from seeded temperature and humidity noise.
+pub struct BiomeMap {
+    temperature: FractalNoise,
+    humidity: FractalNoise,
+}
+
+impl BiomeMap {
+    /// Creates a biome map.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The world seed; the climate maps derive their own seeds from it.
+    /// * `scale` - The approximate size (in blocks) of a biome.
+    pub fn new(seed: u64, scale: f64) -> Self {
+        BiomeMap {
+            temperature: FractalNoise::new(seed ^ 0x7E3F_1A2B_0000_0001, 3, scale, 0.5, 2.0),
+            humidity: FractalNoise::new(seed ^ 0x4D59_C6E1_0000_0002, 3, scale, 0.5, 2.0),
+        }
+    }
+
+    /// Returns the `(temperature, humidity)` of a column, each roughly in `[-1, 1]`.
+    pub fn climate_at(&self, x: i32, z: i32) -> (f64, f64) {
+        // Fractal noise rarely strays far from zero, so stretch it to reach every biome
+        let stretch = |v: f64| (v * 2.0).clamp(-1.0, 1.0);
+        (
+            stretch(self.temperature.sample2(x as f64, z as f64)),
+            stretch(self.humidity.sample2(x as f64, z as f64)),
+        )
+    }
+
+    /// Returns the biome of the given column.
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        let climate = self.climate_at(x, z);
+        Biome::ALL
+            .into_iter()
+            .min_by(|a, b| {
+                climate_distance(climate, a.climate())
+                    .total_cmp(&climate_distance(climate, b.climate()))
+            })
+            .unwrap()
+    }
+
+    /// Returns the height profile of a column, blended between biomes whose climates
+    /// are close to it so terrain doesn't step at biome borders.
+    pub fn height_profile_at(&self, x: i32, z: i32) -> HeightProfile {
+        let climate = self.climate_at(x, z);
+        let distances = Biome::ALL.map(|biome| climate_distance(climate, biome.climate()));
+        let closest = distances.iter().copied().fold(f64::INFINITY, f64::min);
+
+        // The closest biome always has full weight, so the total is never zero
+        let mut total_weight = 0.0;
+        let mut offset = 0.0;
+        let mut variation = 0.0;
+        for (biome, distance) in Biome::ALL.into_iter().zip(distances) {
+            let weight = (1.0 - (distance - closest) / BLEND_WIDTH).max(0.0).powi(2);
+            let profile = biome.height_profile();
+
+            total_weight += weight;
+            offset += profile.offset * weight;
+            variation += profile.variation * weight;
+        }
+
+        HeightProfile {
+            offset: offset / total_weight,
+            variation: variation / total_weight,
+        }
+    }
+}
+
+fn climate_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn biome_is_the_closest_climate() {
+        let map = BiomeMap::new(3, 256.0);
+        for i in 0..200 {
+            let (x, z) = (i * 37 - 3000, i * -53 + 1000);
+            let climate = map.climate_at(x, z);
+            let biome = map.biome_at(x, z);
+            for other in Biome::ALL {
+                assert!(
+                    climate_distance(climate, biome.climate())
+                        <= climate_distance(climate, other.climate())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_biome_appears() {
+        let map = BiomeMap::new(11, 256.0);
+        let mut found = Vec::new();
+        for x in (-8192..8192).step_by(64) {
+            for z in (-8192..8192).step_by(64) {
+                let biome = map.biome_at(x, z);
+                if !found.contains(&biome) {
+                    found.push(biome);
+                }
+            }
+        }
+        assert_eq!(found.len(), Biome::ALL.len(), "only found {:?}", found);
+    }
+
+    #[test]
+    fn blended_profile_is_continuous() {
+        let map = BiomeMap::new(5, 256.0);
+        for z in (-2000..2000).step_by(250) {
+            for x in -2000..2000 {
+                let a = map.height_profile_at(x, z);
+                let b = map.height_profile_at(x + 1, z);
+                assert!((a.offset - b.offset).abs() < 0.1);
+                assert!((a.variation - b.variation).abs() < 0.1);
+            }
+        }
+    }
+}
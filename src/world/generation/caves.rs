@@ -0,0 +1,294 @@
+use crate::config::GenerationConfig;
+use crate::world::block::BlockId;
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos};
+use crate::world::generation::noise::{FractalNoise, SplitMix64};
+use std::f64::consts::{PI, TAU};
+
+/// Number of steps a worm tunnel takes; each step advances one block.
+const WORM_LENGTH: usize = 80;
+
+/// How many chunks away a worm can start and still reach the chunk being carved.
+const WORM_REACH_CHUNKS: i32 = (WORM_LENGTH / CHUNK_SIZE) as i32 + 1;
+
+/// Salt distinguishing worm seeds from other per-chunk features.
+const WORM_SALT: u64 = 0xC0FFEE;
+
+/// A sphere of air carved by a worm tunnel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CaveSphere {
+    pub center: [f64; 3],
+    pub radius: f64,
+}
+
+/// Carves caves into generated terrain.
+///
+/// Two kinds of caves are carved:
+/// * "cheese" caves: open pockets where 3D noise exceeds a threshold;
+/// * "worm" tunnels: long winding tunnels that start in a chunk and wander into
+///   its neighbours. Each tunnel is simulated from a seed derived from its
+///   starting chunk, so every chunk it crosses carves it identically.
+pub struct CaveCarver {
+    seed: u64,
+    cheese_noise: FractalNoise,
+    cheese_threshold: f64,
+    worm_chance: f64,
+    floor: i32,
+    sea_level: i32,
+}
+
+impl CaveCarver {
+    /// Creates a new `CaveCarver` from the world generation settings.
+    pub fn new(config: &GenerationConfig) -> Self {
+        CaveCarver {
+            seed: config.seed,
+            cheese_noise: FractalNoise::new(config.seed ^ 0x0CA7_E5EE_D000_0003, 2, 24.0, 0.5, 2.0),
+            cheese_threshold: config.cave_threshold,
+            worm_chance: config.worm_chance,
+            floor: config.cave_floor,
+            sea_level: config.sea_level,
+        }
+    }
+
+    /// Returns whether the cheese cave noise opens a pocket at the given block.
+    pub fn is_cheese_cave(&self, x: i32, y: i32, z: i32) -> bool {
+        // Squash the noise vertically so caves are wider than they are tall
+        let noise = self
+            .cheese_noise
+            .sample3(x as f64, y as f64 * 2.0, z as f64);
+        noise > self.cheese_threshold
+    }
+
+    /// Returns the spheres carved by the worm tunnels starting in the given chunk.
+    pub fn worm_spheres(&self, source: ChunkPos) -> Vec<CaveSphere> {
+        let mut rng = SplitMix64::for_chunk(self.seed, WORM_SALT, source.x, source.z);
+        if rng.next_f64() >= self.worm_chance {
+            return Vec::new();
+        }
+
+        let (origin_x, origin_z) = source.origin();
+        let mut x = origin_x as f64 + rng.next_f64() * CHUNK_SIZE as f64;
+        let mut z = origin_z as f64 + rng.next_f64() * CHUNK_SIZE as f64;
+        // Start somewhere between the floor and sea level
+        let depth = (self.sea_level - self.floor).max(1) as f64;
+        let mut y = self.floor as f64 + 1.0 + rng.next_f64() * depth;
+        let mut yaw = rng.next_f64() * TAU;
+        let mut pitch = (rng.next_f64() - 0.5) * 0.5;
+        let radius = 1.0 + rng.next_f64();
+
+        let mut spheres = Vec::with_capacity(WORM_LENGTH);
+        for step in 0..WORM_LENGTH {
+            x += yaw.cos() * pitch.cos();
+            y += pitch.sin();
+            z += yaw.sin() * pitch.cos();
+
+            // Wander, with the pitch pulled back towards level so tunnels don't dive
+            yaw += (rng.next_f64() - 0.5) * 0.5;
+            pitch = pitch * 0.7 + (rng.next_f64() - 0.5) * 0.4;
+
+            // Taper the tunnel towards both ends
+            let taper = 0.6 + 0.4 * (step as f64 / WORM_LENGTH as f64 * PI).sin();
+            spheres.push(CaveSphere {
+                center: [x, y, z],
+                radius: radius * taper,
+            });
+        }
+
+        spheres
+    }
+
+    /// Carves caves into a freshly generated chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk to carve.
+    /// * `pos` - The position of the chunk.
+    /// * `heights` - The terrain height of each column, indexed `[x][z]`.
+    /// * `water` - The water block, which is never carved away.
+    pub fn carve(
+        &self,
+        chunk: &mut Chunk,
+        pos: ChunkPos,
+        heights: &[[i32; CHUNK_SIZE]; CHUNK_SIZE],
+        water: BlockId,
+    ) {
+        let (origin_x, origin_z) = pos.origin();
+
+        // Returns whether the block at local coordinates may be turned into air
+        let can_carve = |chunk: &Chunk, x: usize, y: i32, z: usize| {
+            let height = heights[x][z];
+            // Keep a crust under the sea so caves don't drain it
+            let ceiling = if height < self.sea_level {
+                height - 2
+            } else {
+                height
+            };
+            let block = chunk.get(x, y, z);
+            y >= self.floor && y <= ceiling && !block.is_air() && block != water
+        };
+
+        let bottom = self.floor.max(chunk.height().min_y());
+        for (x, column) in heights.iter().enumerate() {
+            for (z, &height) in column.iter().enumerate() {
+                for y in bottom..=height {
+                    if can_carve(chunk, x, y, z)
+                        && self.is_cheese_cave(origin_x + x as i32, y, origin_z + z as i32)
+                    {
+                        chunk.set(x, y, z, BlockId::AIR);
+                    }
+                }
+            }
+        }
+
+        for source_x in pos.x - WORM_REACH_CHUNKS..=pos.x + WORM_REACH_CHUNKS {
+            for source_z in pos.z - WORM_REACH_CHUNKS..=pos.z + WORM_REACH_CHUNKS {
+                for sphere in self.worm_spheres(ChunkPos::new(source_x, source_z)) {
+                    self.carve_sphere(chunk, pos, sphere, &can_carve);
+                }
+            }
+        }
+    }
+
+    /// Carves the part of a sphere that lies inside the chunk.
+    fn carve_sphere<F>(&self, chunk: &mut Chunk, pos: ChunkPos, sphere: CaveSphere, can_carve: &F)
+    where
+        F: Fn(&Chunk, usize, i32, usize) -> bool,
+    {
+        let (origin_x, origin_z) = pos.origin();
+        let [cx, cy, cz] = sphere.center;
+        let r = sphere.radius;
+
+        // Clip the sphere's bounding box to the chunk
+        let clip = |center: f64, min: i32, max: i32| {
+            ((center - r).floor() as i32).max(min)..=((center + r).ceil() as i32).min(max - 1)
+        };
+        let size = CHUNK_SIZE as i32;
+        let height = chunk.height();
+
+        for x in clip(cx, origin_x, origin_x + size) {
+            for z in clip(cz, origin_z, origin_z + size) {
+                for y in clip(cy, height.min_y(), height.max_y()) {
+                    let dx = x as f64 + 0.5 - cx;
+                    let dy = y as f64 + 0.5 - cy;
+                    let dz = z as f64 + 0.5 - cz;
+                    if dx * dx + dy * dy + dz * dz > r * r {
+                        continue;
+                    }
+
+                    let (x, z) = ((x - origin_x) as usize, (z - origin_z) as usize);
+                    if can_carve(chunk, x, y, z) {
+                        chunk.set(x, y, z, BlockId::AIR);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::{WorldHeight, local_coord};
+
+    const STONE: BlockId = BlockId(3);
+    const WATER: BlockId = BlockId(5);
+    const FLOOR: i32 = -14;
+
+    fn height() -> WorldHeight {
+        WorldHeight::new(-16, 16)
+    }
+
+    fn carver(threshold: f64, worm_chance: f64) -> CaveCarver {
+        CaveCarver::new(&GenerationConfig {
+            seed: 77,
+            cave_threshold: threshold,
+            worm_chance,
+            cave_floor: FLOOR,
+            sea_level: 0,
+            ..GenerationConfig::default()
+        })
+    }
+
+    /// Carves a chunk of solid stone with the surface at the top of the chunk.
+    fn carve_solid(carver: &CaveCarver, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(height());
+        for x in 0..CHUNK_SIZE {
+            for y in height().range() {
+                for z in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, STONE);
+                }
+            }
+        }
+        let heights = [[height().max_y() - 1; CHUNK_SIZE]; CHUNK_SIZE];
+        carver.carve(&mut chunk, pos, &heights, WATER);
+        chunk
+    }
+
+    #[test]
+    fn caves_never_reach_below_the_floor() {
+        let carver = carver(0.1, 1.0);
+        for i in 0..8 {
+            let chunk = carve_solid(&carver, ChunkPos::new(i, -i));
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    for y in height().min_y()..FLOOR {
+                        assert_eq!(chunk.get(x, y, z), STONE);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cheese_caves_follow_the_noise() {
+        let carver = carver(0.2, 0.0);
+        let pos = ChunkPos::new(3, -2);
+        let chunk = carve_solid(&carver, pos);
+        let (origin_x, origin_z) = pos.origin();
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in FLOOR..height().max_y() {
+                    let open = carver.is_cheese_cave(origin_x + x as i32, y, origin_z + z as i32);
+                    assert_eq!(chunk.get(x, y, z).is_air(), open);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn worms_are_carved_in_every_chunk_they_cross() {
+        // Cheese caves off, so only worms open any space
+        let carver = carver(2.0, 1.0);
+
+        let mut checked = 0;
+        for source_x in 0..10 {
+            let source = ChunkPos::new(source_x, 0);
+            for sphere in carver.worm_spheres(source) {
+                let [x, y, z] = sphere.center.map(|v| v.floor() as i32);
+                let target = ChunkPos::from_block(x, z);
+                if target == source || !(FLOOR..height().max_y()).contains(&y) {
+                    continue;
+                }
+
+                // The tunnel left its starting chunk; the chunk it entered must be
+                // carved at the sphere's centre even though it's generated separately
+                let chunk = carve_solid(&carver, target);
+                assert!(chunk.get(local_coord(x), y, local_coord(z)).is_air());
+                checked += 1;
+            }
+        }
+        assert!(checked > 0, "no worm left its starting chunk");
+    }
+
+    #[test]
+    fn worms_are_deterministic() {
+        let a = carver(2.0, 1.0);
+        let b = carver(2.0, 1.0);
+        for x in -5..5 {
+            assert_eq!(
+                a.worm_spheres(ChunkPos::new(x, 2 * x)),
+                b.worm_spheres(ChunkPos::new(x, 2 * x))
+            );
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use crate::world::block::BlockId;
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos, local_coord};
+use crate::world::generation::biome::Biome;
+use crate::world::generation::noise::SplitMix64;
+use crate::world::registry::BlockRegistry;
+use std::collections::HashMap;
+
+/// Salt distinguishing decoration seeds from other per-chunk features.
+const DECORATION_SALT: u64 = 0xDEC0_0001;
+
+/// Number of places in each chunk where a tree may grow.
+const TREE_ATTEMPTS: usize = 8;
+
+/// Chance (0-1) that a chunk on dry land gets a prefab structure.
+const PREFAB_CHANCE: f64 = 0.03;
+
+/// How many chunks away from its origin chunk a feature can reach.
+pub const FEATURE_REACH: i32 = 1;
+
+/// A small structure placed on the surface, described layer by layer from the bottom up.
+///
+/// Each layer is a list of rows along z, each row a string of blocks along x:
+/// `S` is stone, `L` is log, `W` is water and `.` leaves the terrain untouched.
+struct Prefab {
+    layers: &'static [&'static [&'static str]],
+}
+
+/// The prefab structures the decorator chooses from.
+const PREFABS: [Prefab; 2] = [
+    // A ruined stone hut
+    Prefab {
+        layers: &[
+            &[
+                "SSSSSSS", "S.....S", "S.....S", "S......", "S.....S", "S.....S", "SSS.SSS",
+            ],
+            &[
+                "SSSS..S", "S.....S", "S......", ".......", "S.....S", "S......", "SS...SS",
+            ],
+            &[
+                "S.....S", ".......", "S......", ".......", ".......", ".......", "S.....S",
+            ],
+        ],
+    },
+    // A well
+    Prefab {
+        layers: &[
+            &["SSS", "SWS", "SSS"],
+            &["L.L", "...", "L.L"],
+            &["SSS", "SSS", "SSS"],
+        ],
+    },
+];
+
+/// A single block a feature places into the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    /// The world block coordinates of the block.
+    pub pos: [i32; 3],
+    pub block: BlockId,
+    /// A block other than air the placement may overwrite.
+    pub replaces: BlockId,
+}
+
+impl Placement {
+    /// Returns the chunk the placement lands in.
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos::from_block(self.pos[0], self.pos[2])
+    }
+
+    /// Writes the placement into its chunk if the spot is air or holds the block
+    /// the placement replaces.
+    ///
+    /// Features never cut into terrain, and overlapping features resolve the same
+    /// way whichever is placed first.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the block was written.
+    pub fn apply(&self, chunk: &mut Chunk) -> bool {
+        let [x, y, z] = self.pos;
+        let (x, z) = (local_coord(x), local_coord(z));
+        let existing = chunk.get(x, y, z);
+        let fits = existing.is_air() || existing == self.replaces;
+        if fits {
+            chunk.set(x, y, z, self.block);
+        }
+        fits
+    }
+}
+
+/// Placements waiting for their chunk to be generated.
+///
+/// Features can spill into chunks that don't exist yet; their blocks are held
+/// here and applied once the chunk is generated.
+#[derive(Default)]
+pub struct PendingPlacements {
+    by_chunk: HashMap<ChunkPos, Vec<Placement>>,
+}
+
+impl PendingPlacements {
+    /// Queues a placement until its chunk is generated.
+    pub fn push(&mut self, placement: Placement) {
+        self.by_chunk
+            .entry(placement.chunk())
+            .or_default()
+            .push(placement);
+    }
+
+    /// Returns every waiting placement.
+    pub fn iter(&self) -> impl Iterator<Item = &Placement> {
+        self.by_chunk.values().flatten()
+    }
+
+    /// Removes and returns every placement waiting for the given chunk.
+    pub fn take(&mut self, pos: ChunkPos) -> Vec<Placement> {
+        self.by_chunk.remove(&pos).unwrap_or_default()
+    }
+}
+
+/// The top of a column features may be placed on.
+#[derive(Clone, Copy, Debug)]
+pub struct Surface {
+    /// The height of the topmost terrain block.
+    pub height: i32,
+    pub biome: Biome,
+    /// The topmost terrain block.
+    pub block: BlockId,
+}
+
+/// Places trees, boulders and prefab structures on generated terrain.
+///
+/// The features of a chunk are a pure function of the seed and the chunk position,
+/// so they can be recomputed whenever a neighbouring chunk needs its share of them.
+pub struct Decorator {
+    seed: u64,
+    log: BlockId,
+    leaves: BlockId,
+    stone: BlockId,
+    water: BlockId,
+    /// Blocks trees grow on.
+    soil: [BlockId; 3],
+}
+
+impl Decorator {
+    /// Creates a new `Decorator`.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The world seed.
+    /// * `registry` - The block registry to look up feature blocks in.
+    pub fn new(seed: u64, registry: &BlockRegistry) -> Self {
+        Decorator {
+            seed,
+            log: registry.expect_id("log"),
+            leaves: registry.expect_id("leaves"),
+            stone: registry.expect_id("stone"),
+            water: registry.expect_id("water"),
+            soil: ["grass", "dirt", "snow"].map(|name| registry.expect_id(name)),
+        }
+    }
+
+    /// Returns the blocks of every feature originating in the given chunk.
+    ///
+    /// Placements may land in neighbouring chunks, up to `FEATURE_REACH` chunks away.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The chunk the features start in.
+    /// * `surface` - Returns the surface of a column, or `None` if nothing can be
+    ///   placed on it (for example because it's under water).
+    pub fn decorate<F>(&self, pos: ChunkPos, surface: F) -> Vec<Placement>
+    where
+        F: Fn(i32, i32) -> Option<Surface>,
+    {
+        let mut rng = SplitMix64::for_chunk(self.seed, DECORATION_SALT, pos.x, pos.z);
+        let (origin_x, origin_z) = pos.origin();
+        let random_column = |rng: &mut SplitMix64| {
+            (
+                origin_x + rng.next_below(CHUNK_SIZE as i32),
+                origin_z + rng.next_below(CHUNK_SIZE as i32),
+            )
+        };
+        let mut placements = Vec::new();
+
+        // Prefabs go first so trees and boulders don't take their space
+        let (x, z) = random_column(&mut rng);
+        let prefab = &PREFABS[rng.next_below(PREFABS.len() as i32) as usize];
+        if rng.next_f64() < PREFAB_CHANCE
+            && let Some(ground) = surface(x, z)
+            && ground.biome != Biome::Ocean
+        {
+            self.place_prefab(&mut placements, prefab, [x, ground.height + 1, z]);
+        }
+
+        let (x, z) = random_column(&mut rng);
+        let boulder_radius = 1.0 + rng.next_f64();
+        if let Some(ground) = surface(x, z)
+            && rng.next_f64() < ground.biome.boulder_chance()
+        {
+            self.place_boulder(&mut placements, [x, ground.height, z], boulder_radius);
+        }
+
+        for _ in 0..TREE_ATTEMPTS {
+            let (x, z) = random_column(&mut rng);
+            let trunk_height = 4 + rng.next_below(2);
+            if let Some(ground) = surface(x, z)
+                && self.soil.contains(&ground.block)
+                && rng.next_f64() < ground.biome.tree_chance()
+            {
+                self.place_tree(&mut placements, [x, ground.height + 1, z], trunk_height);
+            }
+        }
+
+        placements
+    }
+
+    /// Creates a placement of a feature block.
+    ///
+    /// Leaves give way to every other feature block, so overlapping trees and
+    /// structures come out the same whichever chunk is decorated first.
+    fn placement(&self, pos: [i32; 3], block: BlockId) -> Placement {
+        let replaces = if block == self.leaves {
+            BlockId::AIR
+        } else {
+            self.leaves
+        };
+        Placement {
+            pos,
+            block,
+            replaces,
+        }
+    }
+
+    /// Adds a tree whose trunk starts at `base`.
+    fn place_tree(&self, placements: &mut Vec<Placement>, base: [i32; 3], trunk_height: i32) {
+        let [x, y, z] = base;
+        let top = y + trunk_height - 1;
+
+        for trunk_y in y..=top {
+            placements.push(self.placement([x, trunk_y, z], self.log));
+        }
+
+        // Two wide layers around the top of the trunk, then two narrow ones above it
+        for leaf_y in top - 1..=top + 2 {
+            let radius: i32 = if leaf_y <= top { 2 } else { 1 };
+            for dx in -radius..=radius {
+                for dz in -radius..=radius {
+                    let is_corner = dx.abs() == radius && dz.abs() == radius;
+                    let is_trunk = dx == 0 && dz == 0 && leaf_y <= top;
+                    if is_corner || is_trunk {
+                        continue;
+                    }
+                    placements.push(self.placement([x + dx, leaf_y, z + dz], self.leaves));
+                }
+            }
+        }
+    }
+
+    /// Adds a rough ball of stone centred on `center`.
+    fn place_boulder(&self, placements: &mut Vec<Placement>, center: [i32; 3], radius: f64) {
+        let reach = radius.ceil() as i32;
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    let distance = ((dx * dx + dy * dy + dz * dz) as f64).sqrt();
+                    if distance <= radius {
+                        let pos = [center[0] + dx, center[1] + dy, center[2] + dz];
+                        placements.push(self.placement(pos, self.stone));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds a prefab structure whose bottom layer starts at `corner`.
+    fn place_prefab(&self, placements: &mut Vec<Placement>, prefab: &Prefab, corner: [i32; 3]) {
+        for (dy, layer) in prefab.layers.iter().enumerate() {
+            for (dz, row) in layer.iter().enumerate() {
+                for (dx, symbol) in row.chars().enumerate() {
+                    let block = match symbol {
+                        'S' => self.stone,
+                        'L' => self.log,
+                        'W' => self.water,
+                        _ => continue,
+                    };
+                    let pos = [
+                        corner[0] + dx as i32,
+                        corner[1] + dy as i32,
+                        corner[2] + dz as i32,
+                    ];
+                    placements.push(self.placement(pos, block));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::WorldHeight;
+
+    /// Flat grass at height 4 everywhere, in the given biome.
+    fn flat(registry: &BlockRegistry, biome: Biome) -> impl Fn(i32, i32) -> Option<Surface> {
+        let grass = registry.expect_id("grass");
+        move |_, _| {
+            Some(Surface {
+                height: 4,
+                biome,
+                block: grass,
+            })
+        }
+    }
+
+    #[test]
+    fn decoration_is_deterministic() {
+        let registry = BlockRegistry::default();
+        let decorator = Decorator::new(12, &registry);
+        for x in -3..3 {
+            let pos = ChunkPos::new(x, 1 - x);
+            assert_eq!(
+                decorator.decorate(pos, flat(&registry, Biome::Forest)),
+                decorator.decorate(pos, flat(&registry, Biome::Forest))
+            );
+        }
+    }
+
+    #[test]
+    fn forests_grow_trees_that_cross_chunk_borders() {
+        let registry = BlockRegistry::default();
+        let decorator = Decorator::new(3, &registry);
+        let log = registry.expect_id("log");
+
+        let mut spilled = false;
+        for x in 0..16 {
+            let pos = ChunkPos::new(x, 0);
+            let placements = decorator.decorate(pos, flat(&registry, Biome::Forest));
+            assert!(placements.iter().any(|p| p.block == log));
+
+            for placement in &placements {
+                // Features never reach further than the neighbouring chunks
+                assert!(placement.chunk().distance(pos) <= FEATURE_REACH);
+                spilled |= placement.chunk() != pos;
+            }
+        }
+        assert!(spilled);
+    }
+
+    #[test]
+    fn nothing_grows_where_the_surface_is_unusable() {
+        let registry = BlockRegistry::default();
+        let decorator = Decorator::new(3, &registry);
+        for x in 0..16 {
+            let placements = decorator.decorate(ChunkPos::new(x, x), |_, _| None);
+            assert!(placements.is_empty());
+        }
+    }
+
+    #[test]
+    fn pending_placements_are_grouped_by_chunk() {
+        let mut pending = PendingPlacements::default();
+        let placement = |x, z| Placement {
+            pos: [x, 5, z],
+            block: BlockId(1),
+            replaces: BlockId::AIR,
+        };
+        pending.push(placement(-1, 3));
+        pending.push(placement(17, 3));
+        pending.push(placement(-16, 15));
+
+        assert_eq!(
+            pending.take(ChunkPos::new(-1, 0)),
+            vec![placement(-1, 3), placement(-16, 15)]
+        );
+        assert!(pending.take(ChunkPos::new(-1, 0)).is_empty());
+        assert_eq!(pending.take(ChunkPos::new(1, 0)), vec![placement(17, 3)]);
+    }
+
+    #[test]
+    fn placements_only_fill_air_or_what_they_replace() {
+        let registry = BlockRegistry::default();
+        let decorator = Decorator::new(1, &registry);
+        let (dirt, leaves) = (registry.expect_id("dirt"), registry.expect_id("leaves"));
+
+        let mut chunk = Chunk::new(WorldHeight::new(0, 16));
+        chunk.set(2, 3, 4, dirt);
+        chunk.set(2, 5, 4, leaves);
+        for y in 3..=5 {
+            decorator
+                .placement([18, y, 4], decorator.log)
+                .apply(&mut chunk);
+        }
+        assert_eq!(chunk.get(2, 3, 4), dirt);
+        assert_eq!(chunk.get(2, 4, 4), decorator.log);
+        assert_eq!(chunk.get(2, 5, 4), decorator.log);
+
+        // Leaves never overwrite other feature blocks
+        decorator.placement([18, 5, 4], leaves).apply(&mut chunk);
+        assert_eq!(chunk.get(2, 5, 4), decorator.log);
+    }
+}
@@ -0,0 +1,338 @@
+pub mod biome;
+pub mod caves;
+pub mod decoration;
+pub mod noise;
+pub mod ores;
+
+use crate::config::GenerationConfig;
+use crate::world::block::BlockId;
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos, WorldHeight};
+use crate::world::generation::biome::{Biome, BiomeMap};
+use crate::world::generation::caves::CaveCarver;
+use crate::world::generation::decoration::{Decorator, Placement, Surface};
+use crate::world::generation::noise::FractalNoise;
+use crate::world::generation::ores::OrePlacer;
+use crate::world::registry::BlockRegistry;
+
+/// Generates terrain from a seeded fractal noise heightmap shaped by biomes, then
+/// scatters ores through it and carves caves into it.
+///
+/// Heights are a pure function of the seed and world coordinates, so chunks line
+/// up across borders and regenerate identically.
+pub struct TerrainGenerator {
+    height_noise: FractalNoise,
+    biomes: BiomeMap,
+    ores: OrePlacer,
+    caves: CaveCarver,
+    decorator: Decorator,
+    height: WorldHeight,
+    base_height: i32,
+    height_variation: f64,
+    sea_level: i32,
+    /// Surface and filler blocks of each biome, indexed like `Biome::ALL`.
+    biome_blocks: [(BlockId, BlockId); Biome::ALL.len()],
+    stone: BlockId,
+    snow: BlockId,
+    sand: BlockId,
+    water: BlockId,
+}
+
+impl TerrainGenerator {
+    /// Creates a new `TerrainGenerator`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The seed and noise parameters.
+    /// * `registry` - The block registry to look up terrain blocks in.
+    ///
+    /// # Returns
+    ///
+    /// A new `TerrainGenerator` instance.
+    pub fn new(config: &GenerationConfig, registry: &BlockRegistry) -> Self {
+        TerrainGenerator {
+            height_noise: FractalNoise::new(
+                config.seed,
+                config.octaves,
+                config.scale,
+                config.persistence,
+                config.lacunarity,
+            ),
+            biomes: BiomeMap::new(config.seed, config.biome_scale),
+            ores: OrePlacer::new(config.seed, &config.ores, registry),
+            caves: CaveCarver::new(config),
+            decorator: Decorator::new(config.seed, registry),
+            height: WorldHeight::new(config.min_y, config.max_y),
+            base_height: config.base_height,
+            height_variation: config.height_variation,
+            sea_level: config.sea_level,
+            biome_blocks: Biome::ALL.map(|biome| {
+                let (surface, filler) = biome.surface_blocks();
+                (registry.expect_id(surface), registry.expect_id(filler))
+            }),
+            stone: registry.expect_id("stone"),
+            snow: registry.expect_id("snow"),
+            sand: registry.expect_id("sand"),
+            water: registry.expect_id("water"),
+        }
+    }
+
+    /// Returns the vertical extent of the chunks the generator produces.
+    pub fn height(&self) -> WorldHeight {
+        self.height
+    }
+
+    /// Returns the biome of the given column.
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        self.biomes.biome_at(x, z)
+    }
+
+    /// Returns the height of the topmost terrain block in the given column.
+    pub fn height_at(&self, x: i32, z: i32) -> i32 {
+        let profile = self.biomes.height_profile_at(x, z);
+        let noise = self.height_noise.sample2(x as f64, z as f64);
+        let height = self.base_height as f64
+            + (profile.offset + noise * profile.variation) * self.height_variation;
+        let height = height.round() as i32;
+
+        // Keep at least one block of ground and one block of sky inside the world
+        height.clamp(self.height.min_y() + 1, self.height.max_y() - 2)
+    }
+
+    /// Generates the chunk at the given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the chunk to generate.
+    ///
+    /// # Returns
+    ///
+    /// The generated chunk.
+    pub fn generate_chunk(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(self.height);
+        let (origin_x, origin_z) = pos.origin();
+        let mut heights = [[0; CHUNK_SIZE]; CHUNK_SIZE];
+
+        for (x, column_heights) in heights.iter_mut().enumerate() {
+            for (z, column_height) in column_heights.iter_mut().enumerate() {
+                let (world_x, world_z) = (origin_x + x as i32, origin_z + z as i32);
+                let height = self.height_at(world_x, world_z);
+                *column_height = height;
+                let biome = self.biome_at(world_x, world_z);
+                let (_, filler) = self.biome_blocks[biome as usize];
+
+                // Generate terrain layers, up to the surface or the sea
+                for y in self.height.min_y()..=height.max(self.sea_level - 1) {
+                    let block = if y < height - 2 {
+                        self.stone
+                    } else if y < height {
+                        filler
+                    } else if y == height {
+                        self.surface_block(height, biome)
+                    } else if y < self.sea_level {
+                        // Flood everything below sea level
+                        self.water
+                    } else {
+                        continue;
+                    };
+
+                    chunk.set(x, y, z, block);
+                }
+            }
+        }
+
+        self.ores.place(&mut chunk, pos, &heights);
+        self.caves.carve(&mut chunk, pos, &heights, self.water);
+
+        // Carving and ores leave unused entries in the section palettes
+        chunk.compact();
+        chunk
+    }
+
+    /// Returns the blocks of the trees, boulders and structures originating in the
+    /// given chunk, including those that spill into its neighbours.
+    ///
+    /// Decorations are kept separate from `generate_chunk` because they can cross
+    /// chunk borders; the world applies them as the chunks they land in are loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The chunk the decorations start in.
+    ///
+    /// # Returns
+    ///
+    /// The blocks to place, in world coordinates.
+    pub fn decorations(&self, pos: ChunkPos) -> Vec<Placement> {
+        let mut placements = self.decorator.decorate(pos, |x, z| {
+            let height = self.height_at(x, z);
+            // Nothing grows under water or over the mouth of a cave
+            if height < self.sea_level || self.caves.is_cheese_cave(x, height, z) {
+                return None;
+            }
+
+            let biome = self.biome_at(x, z);
+            Some(Surface {
+                height,
+                biome,
+                block: self.surface_block(height, biome),
+            })
+        });
+
+        // Anything above or below the world is dropped
+        placements.retain(|placement| self.height.contains(placement.pos[1]));
+        placements
+    }
+
+    /// Returns the block covering a column of the given height and biome.
+    fn surface_block(&self, height: i32, biome: Biome) -> BlockId {
+        // Columns this high up get a snow cap instead of grass
+        let snow_line = self.base_height + (self.height_variation * 0.5).ceil() as i32;
+
+        if height < self.sea_level {
+            // Sea and lake beds
+            self.sand
+        } else if height >= snow_line && biome != Biome::Desert {
+            self.snow
+        } else {
+            self.biome_blocks[biome as usize].0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(seed: u64) -> TerrainGenerator {
+        let config = GenerationConfig {
+            seed,
+            ..GenerationConfig::default()
+        };
+        TerrainGenerator::new(&config, &BlockRegistry::default())
+    }
+
+    /// A generator that doesn't carve caves, so every column is solid up to its height.
+    fn generator_without_caves(seed: u64) -> TerrainGenerator {
+        let config = GenerationConfig {
+            seed,
+            cave_threshold: 1.0,
+            worm_chance: 0.0,
+            ..GenerationConfig::default()
+        };
+        TerrainGenerator::new(&config, &BlockRegistry::default())
+    }
+
+    fn same_blocks(a: &Chunk, b: &Chunk) -> bool {
+        (0..CHUNK_SIZE).all(|x| {
+            a.height()
+                .range()
+                .all(|y| (0..CHUNK_SIZE).all(|z| a.get(x, y, z) == b.get(x, y, z)))
+        })
+    }
+
+    #[test]
+    fn same_seed_generates_identical_chunks() {
+        for pos in [ChunkPos::new(0, 0), ChunkPos::new(-3, 7)] {
+            let a = generator(1234).generate_chunk(pos);
+            let b = generator(1234).generate_chunk(pos);
+            assert!(same_blocks(&a, &b));
+        }
+    }
+
+    #[test]
+    fn different_seeds_generate_different_terrain() {
+        let a = generator(1).generate_chunk(ChunkPos::new(0, 0));
+        let b = generator(2).generate_chunk(ChunkPos::new(0, 0));
+        assert!(!same_blocks(&a, &b));
+    }
+
+    #[test]
+    fn heightmap_is_continuous_across_chunk_borders() {
+        let generator = generator(99);
+
+        // Neighbouring columns never jump by more than a few blocks, whether
+        // or not they straddle a chunk border (x = -1 / 0 and x = 15 / 16)
+        for z in -32..32 {
+            for x in -20..20 {
+                let step = (generator.height_at(x, z) - generator.height_at(x + 1, z)).abs();
+                assert!(
+                    step <= 2,
+                    "height jumps by {} at x = {}, z = {}",
+                    step,
+                    x,
+                    z
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_matches_heightmap() {
+        let generator = generator_without_caves(5);
+        let registry = BlockRegistry::default();
+        let pos = ChunkPos::new(-1, 2);
+        let chunk = generator.generate_chunk(pos);
+        let (origin_x, origin_z) = pos.origin();
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let height = generator.height_at(origin_x + x as i32, origin_z + z as i32);
+                let surface = chunk.get(x, height, z);
+                assert!(registry.is_solid(surface));
+                assert_eq!(chunk.top_block(x, z), height.max(generator.sea_level - 1));
+
+                let above = chunk.get(x, height + 1, z);
+                assert!(!registry.is_solid(above));
+            }
+        }
+    }
+
+    #[test]
+    fn surface_blocks_follow_the_biome() {
+        let generator = generator_without_caves(21);
+        let registry = BlockRegistry::default();
+        let snow_line = generator.base_height + (generator.height_variation * 0.5).ceil() as i32;
+
+        for cx in -4..4 {
+            for cz in -4..4 {
+                let pos = ChunkPos::new(cx * 8, cz * 8);
+                let chunk = generator.generate_chunk(pos);
+                let (origin_x, origin_z) = pos.origin();
+
+                for x in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        let (world_x, world_z) = (origin_x + x as i32, origin_z + z as i32);
+                        let height = generator.height_at(world_x, world_z);
+                        if height < generator.sea_level || height >= snow_line {
+                            continue;
+                        }
+
+                        let (surface, _) = generator.biome_at(world_x, world_z).surface_blocks();
+                        let block = chunk.get(x, height, z);
+                        assert_eq!(registry.get(block).name, surface);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn caves_keep_the_sea_contained() {
+        let generator = generator(8);
+
+        // Every block directly below water must still be something other than air
+        for cx in -6..6 {
+            for cz in -6..6 {
+                let chunk = generator.generate_chunk(ChunkPos::new(cx, cz));
+                for x in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        for y in generator.height.min_y() + 1..generator.height.max_y() {
+                            if chunk.get(x, y, z) == generator.water {
+                                assert!(!chunk.get(x, y - 1, z).is_air());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
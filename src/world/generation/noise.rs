@@ -0,0 +1,241 @@
+/// Seeded Perlin gradient noise ("improved noise", Perlin 2002).
+///
+/// Values are continuous everywhere, zero on integer lattice points and roughly
+/// within `[-1, 1]`.
+#[derive(Clone, Debug)]
+pub struct Perlin {
+    /// A permutation of `0..256`, repeated so lookups never need wrapping.
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    /// Creates a noise function whose permutation table is shuffled by `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+        // Fisher-Yates shuffle driven by a SplitMix64 sequence
+        let mut rng = SplitMix64::new(seed);
+        for i in (1..table.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        Perlin {
+            perm: std::array::from_fn(|i| table[i % 256]),
+        }
+    }
+
+    fn hash(&self, x: i32, y: i32, z: i32) -> u8 {
+        let x = self.perm[(x & 255) as usize] as usize;
+        let y = self.perm[x + (y & 255) as usize] as usize;
+        self.perm[y + (z & 255) as usize]
+    }
+
+    /// Samples 2D noise.
+    pub fn noise2(&self, x: f64, y: f64) -> f64 {
+        self.noise3(x, y, 0.0)
+    }
+
+    /// Samples 3D noise.
+    pub fn noise3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (xf, yf, zf) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            let hash = self.hash(xi + dx, yi + dy, zi + dz);
+            gradient(hash, xf - dx as f64, yf - dy as f64, zf - dz as f64)
+        };
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+}
+
+/// Fractal Brownian motion: several octaves of Perlin noise summed at increasing
+/// frequency and decreasing amplitude.
+#[derive(Clone, Debug)]
+pub struct FractalNoise {
+    perlin: Perlin,
+    /// Number of octaves summed.
+    pub octaves: u32,
+    /// Size (in blocks) of the features of the first octave.
+    pub scale: f64,
+    /// Amplitude multiplier applied per octave.
+    pub persistence: f64,
+    /// Frequency multiplier applied per octave.
+    pub lacunarity: f64,
+}
+
+impl FractalNoise {
+    /// Creates fractal noise from a seed and its octave parameters.
+    pub fn new(seed: u64, octaves: u32, scale: f64, persistence: f64, lacunarity: f64) -> Self {
+        FractalNoise {
+            perlin: Perlin::new(seed),
+            octaves: octaves.max(1),
+            scale,
+            persistence,
+            lacunarity,
+        }
+    }
+
+    /// Samples 2D fractal noise, normalized to roughly `[-1, 1]`.
+    pub fn sample2(&self, x: f64, z: f64) -> f64 {
+        self.sample(|perlin, frequency| perlin.noise2(x * frequency, z * frequency))
+    }
+
+    /// Samples 3D fractal noise, normalized to roughly `[-1, 1]`.
+    pub fn sample3(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.sample(|perlin, frequency| perlin.noise3(x * frequency, y * frequency, z * frequency))
+    }
+
+    fn sample<F>(&self, octave: F) -> f64
+    where
+        F: Fn(&Perlin, f64) -> f64,
+    {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut max_amplitude = 0.0;
+        let mut frequency = 1.0 / self.scale;
+
+        for _ in 0..self.octaves {
+            total += octave(&self.perlin, frequency) * amplitude;
+            max_amplitude += amplitude;
+            amplitude *= self.persistence;
+            frequency *= self.lacunarity;
+        }
+
+        total / max_amplitude
+    }
+}
+
+/// A small, fast PRNG (SplitMix64) used to derive deterministic values from seeds.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    /// Returns the next pseudo-random 64-bit value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the next pseudo-random value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        // Use the top 53 bits so every value is exactly representable
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a pseudo-random integer in `0..bound`.
+    pub fn next_below(&mut self, bound: i32) -> i32 {
+        (self.next_f64() * bound as f64) as i32
+    }
+
+    /// Creates a generator for a chunk, so per-chunk features are reproducible
+    /// regardless of the order chunks are generated in.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The world seed.
+    /// * `salt` - Distinguishes independent features generated for the same chunk.
+    /// * `x`, `z` - The chunk coordinates.
+    pub fn for_chunk(seed: u64, salt: u64, x: i32, z: i32) -> Self {
+        let mut mixer = SplitMix64::new(
+            seed ^ salt.wrapping_mul(0xD6E8_FEB8_6659_FD93)
+                ^ (x as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (z as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F),
+        );
+        SplitMix64::new(mixer.next_u64())
+    }
+}
+
+/// Perlin's quintic smoothstep, `6t^5 - 15t^4 + 10t^3`.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Dot product of the offset with one of the 12 cube-edge gradient directions.
+fn gradient(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let a = Perlin::new(42);
+        let b = Perlin::new(42);
+        for i in 0..100 {
+            let (x, y) = (i as f64 * 0.37, i as f64 * -0.91);
+            assert_eq!(a.noise2(x, y), b.noise2(x, y));
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_noise() {
+        let a = Perlin::new(1);
+        let b = Perlin::new(2);
+        let differs = (0..100).any(|i| {
+            let (x, y) = (i as f64 * 0.37 + 0.5, i as f64 * 0.13 + 0.5);
+            a.noise2(x, y) != b.noise2(x, y)
+        });
+        assert!(differs);
+    }
+
+    #[test]
+    fn noise_is_zero_on_lattice_points_and_bounded() {
+        let perlin = Perlin::new(7);
+        assert_eq!(perlin.noise3(3.0, -5.0, 8.0), 0.0);
+
+        for i in 0..1000 {
+            let v = perlin.noise3(i as f64 * 0.173, i as f64 * 0.311, i as f64 * -0.057);
+            assert!((-1.0..=1.0).contains(&v), "{} out of range", v);
+        }
+    }
+
+    #[test]
+    fn fractal_noise_is_continuous() {
+        let noise = FractalNoise::new(9, 5, 32.0, 0.5, 2.0);
+        for i in 0..200 {
+            let x = i as f64 * 1.7 - 150.0;
+            let delta = (noise.sample2(x, 12.3) - noise.sample2(x + 0.001, 12.3)).abs();
+            assert!(delta < 0.01);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::config::OreConfig;
+use crate::world::block::BlockId;
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos};
+use crate::world::generation::noise::SplitMix64;
+use crate::world::registry::BlockRegistry;
+use std::ops::Range;
+
+/// Salt distinguishing ore seeds from other per-chunk features.
+const ORE_SALT: u64 = 0x0BE5_0BE5;
+
+/// One kind of ore, resolved against the block registry.
+struct Ore {
+    block: BlockId,
+    min_height: i32,
+    max_height: i32,
+    vein_size: u32,
+    veins_per_chunk: f64,
+}
+
+/// Scatters ore veins through the stone of generated chunks.
+///
+/// Veins are grown from a generator seeded by the world seed, the ore and the
+/// chunk position, so a chunk always gets the same ores no matter when it's generated.
+pub struct OrePlacer {
+    seed: u64,
+    ores: Vec<Ore>,
+    stone: BlockId,
+}
+
+impl OrePlacer {
+    /// Creates a new `OrePlacer`.
+    ///
+    /// Ores naming blocks that aren't registered are skipped with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The world seed.
+    /// * `ores` - The ores to place, in order.
+    /// * `registry` - The block registry to look up ore blocks in.
+    pub fn new(seed: u64, ores: &[OreConfig], registry: &BlockRegistry) -> Self {
+        let ores = ores
+            .iter()
+            .filter_map(|ore| match registry.id(&ore.block) {
+                Some(block) => Some(Ore {
+                    block,
+                    min_height: ore.min_height,
+                    max_height: ore.max_height,
+                    vein_size: ore.vein_size,
+                    veins_per_chunk: ore.veins_per_chunk,
+                }),
+                None => {
+                    eprintln!("Unknown ore block `{}`, skipping it", ore.block);
+                    None
+                }
+            })
+            .collect();
+
+        OrePlacer {
+            seed,
+            ores,
+            stone: registry.expect_id("stone"),
+        }
+    }
+
+    /// Places ore veins into a freshly generated chunk, replacing only buried stone.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk to place ores in.
+    /// * `pos` - The position of the chunk.
+    /// * `heights` - The terrain height of each column, indexed `[x][z]`; surface
+    ///   blocks are never replaced.
+    pub fn place(
+        &self,
+        chunk: &mut Chunk,
+        pos: ChunkPos,
+        heights: &[[i32; CHUNK_SIZE]; CHUNK_SIZE],
+    ) {
+        for (index, ore) in self.ores.iter().enumerate() {
+            let mut rng = SplitMix64::for_chunk(self.seed, ORE_SALT + index as u64, pos.x, pos.z);
+
+            let height = chunk.height();
+            let min_height = ore.min_height.max(height.min_y());
+            let max_height = ore.max_height.min(height.max_y() - 1);
+            if min_height > max_height {
+                continue;
+            }
+
+            // The fractional part of the frequency is the chance of one extra vein
+            let mut veins = ore.veins_per_chunk.floor() as u32;
+            if rng.next_f64() < ore.veins_per_chunk.fract() {
+                veins += 1;
+            }
+
+            for _ in 0..veins {
+                let start = [
+                    rng.next_below(CHUNK_SIZE as i32),
+                    min_height + rng.next_below(max_height - min_height + 1),
+                    rng.next_below(CHUNK_SIZE as i32),
+                ];
+                let vertical = min_height..max_height + 1;
+                self.grow_vein(chunk, heights, &mut rng, start, vertical, ore);
+            }
+        }
+    }
+
+    /// Grows a vein from a starting block with a random walk, staying inside the
+    /// chunk and within `vertical`, the ore's height range clamped to the chunk.
+    fn grow_vein(
+        &self,
+        chunk: &mut Chunk,
+        heights: &[[i32; CHUNK_SIZE]; CHUNK_SIZE],
+        rng: &mut SplitMix64,
+        start: [i32; 3],
+        vertical: Range<i32>,
+        ore: &Ore,
+    ) {
+        let size = CHUNK_SIZE as i32;
+        let limits = [0..size, vertical, 0..size];
+        let mut block = start;
+
+        for _ in 0..ore.vein_size {
+            let [x, y, z] = block;
+            let (x, z) = (x as usize, z as usize);
+            if y < heights[x][z] && chunk.get(x, y, z) == self.stone {
+                chunk.set(x, y, z, ore.block);
+            }
+
+            // Step one block along a random axis, staying inside the limits
+            let axis = rng.next_below(3) as usize;
+            let step = if rng.next_f64() < 0.5 { -1 } else { 1 };
+            let moved = block[axis] + step;
+            if limits[axis].contains(&moved) {
+                block[axis] = moved;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::WorldHeight;
+
+    fn height() -> WorldHeight {
+        WorldHeight::new(0, 16)
+    }
+
+    fn filled_chunk(block: BlockId) -> Chunk {
+        let mut chunk = Chunk::new(height());
+        for x in 0..CHUNK_SIZE {
+            for y in height().range() {
+                for z in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, block);
+                }
+            }
+        }
+        chunk
+    }
+
+    /// Heights putting every column's surface above the top of the chunk.
+    const BURIED: [[i32; CHUNK_SIZE]; CHUNK_SIZE] = [[16; CHUNK_SIZE]; CHUNK_SIZE];
+
+    fn ore(block: &str, min_height: i32, max_height: i32) -> OreConfig {
+        OreConfig {
+            block: String::from(block),
+            min_height,
+            max_height,
+            vein_size: 8,
+            veins_per_chunk: 3.0,
+        }
+    }
+
+    #[test]
+    fn ores_stay_within_their_height_range() {
+        let registry = BlockRegistry::default();
+        let stone = registry.expect_id("stone");
+        let gold = registry.expect_id("gold_ore");
+        let placer = OrePlacer::new(3, &[ore("gold_ore", 2, 5)], &registry);
+
+        let mut found = 0;
+        for i in 0..16 {
+            let mut chunk = filled_chunk(stone);
+            placer.place(&mut chunk, ChunkPos::new(i, i * 3), &BURIED);
+            for x in 0..CHUNK_SIZE {
+                for y in height().range() {
+                    for z in 0..CHUNK_SIZE {
+                        if chunk.get(x, y, z) == gold {
+                            found += 1;
+                            assert!((2..=5).contains(&y));
+                        }
+                    }
+                }
+            }
+        }
+        assert!(found > 0);
+    }
+
+    #[test]
+    fn ores_only_replace_stone() {
+        let registry = BlockRegistry::default();
+        let dirt = registry.expect_id("dirt");
+        let placer = OrePlacer::new(3, &[ore("coal_ore", 0, 15)], &registry);
+
+        let mut chunk = filled_chunk(dirt);
+        placer.place(&mut chunk, ChunkPos::new(0, 0), &BURIED);
+        for x in 0..CHUNK_SIZE {
+            for y in height().range() {
+                for z in 0..CHUNK_SIZE {
+                    assert_eq!(chunk.get(x, y, z), dirt);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn placement_is_reproducible_per_chunk() {
+        let registry = BlockRegistry::default();
+        let stone = registry.expect_id("stone");
+        let ores = [ore("coal_ore", 0, 15), ore("iron_ore", 0, 8)];
+        let placer = OrePlacer::new(42, &ores, &registry);
+
+        let pos = ChunkPos::new(-4, 9);
+        let mut a = filled_chunk(stone);
+        placer.place(&mut a, pos, &BURIED);
+
+        // Placing ores elsewhere first must not change this chunk's veins
+        let mut other = filled_chunk(stone);
+        placer.place(&mut other, ChunkPos::new(1, 1), &BURIED);
+        let mut b = filled_chunk(stone);
+        placer.place(&mut b, pos, &BURIED);
+
+        for x in 0..CHUNK_SIZE {
+            for y in height().range() {
+                for z in 0..CHUNK_SIZE {
+                    assert_eq!(a.get(x, y, z), b.get(x, y, z));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ores_never_replace_the_surface() {
+        let registry = BlockRegistry::default();
+        let stone = registry.expect_id("stone");
+        let placer = OrePlacer::new(9, &[ore("coal_ore", 0, 15)], &registry);
+
+        let heights = [[4; CHUNK_SIZE]; CHUNK_SIZE];
+        let mut chunk = filled_chunk(stone);
+        placer.place(&mut chunk, ChunkPos::new(2, 2), &heights);
+        for x in 0..CHUNK_SIZE {
+            for y in 4..height().max_y() {
+                for z in 0..CHUNK_SIZE {
+                    assert_eq!(chunk.get(x, y, z), stone);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_ores_are_skipped() {
+        let registry = BlockRegistry::default();
+        let placer = OrePlacer::new(1, &[ore("mithril_ore", 0, 15)], &registry);
+        assert!(placer.ores.is_empty());
+    }
+}
@@ -4,22 +4,40 @@ extern crate glfw;
 
 use crate::config::Config;
 use crate::player::camera::Camera;
+use crate::player::input::PlayerInput;
+use crate::rendering::atlas::TextureAtlas;
 use crate::rendering::mesh::Mesh;
 use crate::rendering::shader::Shader;
-use crate::world::{block::Block, world::World};
+use crate::rendering::texture::AtlasTexture;
+use crate::world::cube_render::{OUTLINE_VERTEX_LAYOUT, outline_vertices};
+use crate::world::generation::TerrainGenerator;
+use crate::world::registry::BlockRegistry;
+use crate::world::storage::level::{LEVEL_FILE, LEVEL_FORMAT_VERSION, Level, PlayerState};
+use crate::world::storage::{WorldStorage, set_aside};
+use crate::world::world::World;
 use cgmath::{Deg, Matrix4, Point3, perspective};
 use glfw::{Context, CursorMode, Glfw, GlfwReceiver, PWindow, WindowEvent};
+use std::path::PathBuf;
 
 /// Represents the main application state, including window, camera, shader, and world.
 pub struct App {
+    // GL resources are declared before the window so they are dropped while its context is alive
+    pub shader: Shader,
+    pub atlas: AtlasTexture,
+    pub world: World,
+    pub outline: Mesh,
     pub glfw: Glfw,
     pub window: PWindow,
     pub events: GlfwReceiver<(f64, WindowEvent)>,
-    pub shader: Shader,
-    pub world: World,
     pub projection: Matrix4<f32>,
     pub view: Matrix4<f32>,
     pub camera: Camera,
+    /// Where the player was when the world was last saved, if it has been.
+    pub saved_player: Option<PlayerState>,
+    /// Seconds the world has been played for, across sessions.
+    pub game_time: f64,
+    seed: u64,
+    level_path: PathBuf,
 }
 
 impl App {
@@ -53,25 +71,33 @@ impl App {
         // Apply fullscreen if configured
         if config.window.fullscreen {
             glfw.with_primary_monitor(|_, m| {
-                if let Some(monitor) = m {
-                    if let Some(video_mode) = monitor.get_video_mode() {
-                        window.set_monitor(
-                            glfw::WindowMode::FullScreen(monitor),
-                            0,
-                            0,
-                            video_mode.width,
-                            video_mode.height,
-                            Some(video_mode.refresh_rate),
-                        );
-                    }
+                if let Some(monitor) = m
+                    && let Some(video_mode) = monitor.get_video_mode()
+                {
+                    window.set_monitor(
+                        glfw::WindowMode::FullScreen(monitor),
+                        0,
+                        0,
+                        video_mode.width,
+                        video_mode.height,
+                        Some(video_mode.refresh_rate),
+                    );
                 }
             });
         }
 
         window.make_current();
+
+        // Let the driver pace frames to the display instead of sleeping
+        glfw.set_swap_interval(if config.window.vsync {
+            glfw::SwapInterval::Sync(1)
+        } else {
+            glfw::SwapInterval::None
+        });
         window.set_key_polling(true);
         window.set_framebuffer_size_polling(true);
         window.set_cursor_pos_polling(true);
+        window.set_mouse_button_polling(true);
 
         if config.controls.cursor_locked {
             window.set_cursor_mode(CursorMode::Disabled);
@@ -83,9 +109,72 @@ impl App {
             "src/shaders/vertex_shader.glsl",
             "src/shaders/fragment_shader.glsl",
         );
-        let cube_vertices: [f32; 108] = Block::get_cube_vertices();
-        let mesh = Mesh::new(&cube_vertices);
-        let world = World::new(mesh);
+        // A saved world keeps the seed and height it was created with
+        let save_dir = PathBuf::from(&config.world.save_dir);
+        let level_path = save_dir.join(LEVEL_FILE);
+        // A save whose level can't be loaded is moved aside whole, so its chunks
+        // aren't mixed into a new world generated from another seed
+        let mut level = match Level::load(&level_path) {
+            Ok(level) => level,
+            Err(e) => {
+                eprintln!("Error loading level file {}: {}", level_path.display(), e);
+                match set_aside(&save_dir, "unreadable") {
+                    Ok(moved) => {
+                        eprintln!("Moved the world to {}", moved.display());
+                        eprintln!("Starting a new world instead");
+                    }
+                    Err(e) => {
+                        eprintln!("Error moving {} aside: {}", save_dir.display(), e);
+                        std::process::exit(1);
+                    }
+                }
+                None
+            }
+        };
+        let mut generation = config.generation.clone();
+        if let Some(level) = &level {
+            generation.seed = level.seed;
+            generation.min_y = level.min_y;
+            generation.max_y = level.max_y;
+        }
+
+        let mut registry = BlockRegistry::load("src/blocks.toml");
+        let atlas = TextureAtlas::load("src/textures");
+        registry.assign_tiles(&atlas);
+        let atlas = AtlasTexture::new(&atlas);
+        let generator = TerrainGenerator::new(&generation, &registry);
+        let storage = match WorldStorage::open(
+            &save_dir,
+            generator.height(),
+            level.as_ref().map(|level| level.blocks.as_slice()),
+            &registry,
+        ) {
+            Ok(storage) => storage,
+            Err(e) => {
+                eprintln!("Error opening saved world {}: {}", save_dir.display(), e);
+                std::process::exit(1);
+            }
+        };
+
+        // Opening the save renumbered it to the current blocks, so record them
+        // before anything else can go wrong
+        if let Some(level) = &mut level {
+            let blocks: Vec<String> = registry.names().map(String::from).collect();
+            if level.blocks != blocks {
+                level.blocks = blocks;
+                if let Err(e) = level.save(&level_path) {
+                    eprintln!("Error saving {}: {}", level_path.display(), e);
+                }
+            }
+        }
+        let mut world = World::new(
+            registry,
+            generator,
+            storage,
+            &config.world,
+            &config.rendering,
+        );
+        let outline = Mesh::new(&outline_vertices(), &OUTLINE_VERTEX_LAYOUT);
 
         // Get the current framebuffer size for projection matrix
         let (width, height) = window.get_framebuffer_size();
@@ -96,28 +185,97 @@ impl App {
             config.camera.far_plane,
         );
 
-        // Initialize camera at config position
-        let camera = Camera::new(Point3::new(
-            config.camera.position_x,
-            config.camera.position_y,
-            config.camera.position_z,
-        ));
-        let view = camera.get_view_matrix();
+        // Restore the saved camera, or start at the config position, lifted above
+        // the terrain if it would be buried
+        let saved_player = level.as_ref().map(|level| level.player);
+        let camera = match saved_player {
+            Some(player) => {
+                let mut camera = Camera::new(Point3::from(player.position));
+                camera.yaw = player.yaw;
+                camera.pitch = player.pitch;
+                camera.update_camera_vectors();
+                camera
+            }
+            None => {
+                let surface = world.surface_height(
+                    config.camera.position_x.floor() as i32,
+                    config.camera.position_z.floor() as i32,
+                );
+                Camera::new(Point3::new(
+                    config.camera.position_x,
+                    config
+                        .camera
+                        .position_y
+                        .max(surface as f32 + 1.0 + config.physics.player_height),
+                    config.camera.position_z,
+                ))
+            }
+        };
+        let view = camera.get_view_matrix(1.0);
+
+        // Generate the chunks around the spawn point before the first frame
+        world.update_loaded_chunks(camera.position);
+        world.finish_loading();
 
         App {
             glfw,
             window,
             events,
             shader,
+            atlas,
             world,
+            outline,
             projection,
             view,
             camera,
+            saved_player,
+            game_time: level.map_or(0.0, |level| level.game_time),
+            seed: generation.seed,
+            level_path,
+        }
+    }
+
+    /// Saves the world and the level file describing it.
+    ///
+    /// The level is only written once the world is, so it never describes chunks
+    /// that weren't saved. Errors are reported rather than returned, so a failed
+    /// save never stops the game.
+    ///
+    /// # Arguments
+    ///
+    /// * `player_input` - The player's input state, whose fly mode is saved.
+    pub fn save(&mut self, player_input: &PlayerInput) {
+        if let Err(e) = self.world.save() {
+            eprintln!("Error saving world: {}", e);
+            return;
+        }
+
+        let level = Level {
+            format_version: LEVEL_FORMAT_VERSION,
+            seed: self.seed,
+            min_y: self.world.height().min_y(),
+            max_y: self.world.height().max_y(),
+            game_time: self.game_time,
+            player: PlayerState {
+                position: self.camera.position.into(),
+                yaw: self.camera.yaw,
+                pitch: self.camera.pitch,
+                flying: player_input.fly_mode,
+            },
+            blocks: self.world.registry().names().map(String::from).collect(),
+        };
+        if let Err(e) = level.save(&self.level_path) {
+            eprintln!("Error saving {}: {}", self.level_path.display(), e);
         }
     }
 
     /// Updates the view matrix based on the current camera orientation.
-    pub fn update_view_matrix(&mut self) {
-        self.view = self.camera.get_view_matrix();
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - How far the frame lies between the last physics step and the
+    ///   next, from 0 to 1.
+    pub fn update_view_matrix(&mut self, alpha: f32) {
+        self.view = self.camera.get_view_matrix(alpha);
     }
 }
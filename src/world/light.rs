@@ -0,0 +1,623 @@
+use crate::world::block::{BlockId, Face};
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos, local_coord};
+use crate::world::registry::BlockRegistry;
+use crate::world::section::{SECTION_SIZE, SECTION_VOLUME};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The brightest light level, that of open sky and the brightest light sources.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The sky and block light reaching a block, each a level from 0 to `MAX_LIGHT`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Light(u8);
+
+impl Light {
+    /// No light at all.
+    pub const DARK: Light = Light(0);
+    /// Open sky, with no block light.
+    pub const SKY: Light = Light(MAX_LIGHT << 4);
+
+    /// Creates a light from its sky and block levels, which must not exceed `MAX_LIGHT`.
+    pub fn new(sky: u8, block: u8) -> Self {
+        Light((sky << 4) | block)
+    }
+
+    /// Returns the light reaching the block from the sky.
+    pub fn sky(self) -> u8 {
+        self.0 >> 4
+    }
+
+    /// Returns the light reaching the block from light-emitting blocks.
+    pub fn block(self) -> u8 {
+        self.0 & MAX_LIGHT
+    }
+
+    /// Returns the level of one channel.
+    pub fn get(self, channel: Channel) -> u8 {
+        match channel {
+            Channel::Sky => self.sky(),
+            Channel::Block => self.block(),
+        }
+    }
+
+    /// Returns a copy with one channel set to `level`.
+    pub fn with(self, channel: Channel, level: u8) -> Light {
+        match channel {
+            Channel::Sky => Light::new(level, self.block()),
+            Channel::Block => Light::new(self.sky(), level),
+        }
+    }
+}
+
+/// The two kinds of light, which spread independently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    /// Light from above the world; it falls straight down through air without fading.
+    Sky,
+    /// Light emitted by blocks such as lamps.
+    Block,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 2] = [Channel::Sky, Channel::Block];
+}
+
+/// The light of every block in a section, in `Section::index` order.
+///
+/// Sections lit evenly, such as open sky or buried rock, store a single value.
+#[derive(Clone, Debug)]
+pub enum LightSection {
+    Uniform(Light),
+    Mixed(Box<[Light; SECTION_VOLUME]>),
+}
+
+impl LightSection {
+    /// Returns the light at the given index.
+    pub fn get(&self, index: usize) -> Light {
+        match self {
+            LightSection::Uniform(light) => *light,
+            LightSection::Mixed(lights) => lights[index],
+        }
+    }
+
+    /// Sets the light at the given index.
+    pub fn set(&mut self, index: usize, light: Light) {
+        match self {
+            LightSection::Uniform(uniform) if *uniform == light => {}
+            LightSection::Uniform(uniform) => {
+                let mut lights = Box::new([*uniform; SECTION_VOLUME]);
+                lights[index] = light;
+                *self = LightSection::Mixed(lights);
+            }
+            LightSection::Mixed(lights) => lights[index] = light,
+        }
+    }
+
+    /// Goes back to storing a single value if every block has the same light.
+    pub fn compact(&mut self) {
+        if let LightSection::Mixed(lights) = self
+            && lights.iter().all(|&light| light == lights[0])
+        {
+            *self = LightSection::Uniform(lights[0]);
+        }
+    }
+}
+
+impl Default for LightSection {
+    fn default() -> Self {
+        LightSection::Uniform(Light::DARK)
+    }
+}
+
+/// Blocks and their light, as seen by light propagation.
+pub trait LightStorage {
+    /// Returns the block at `pos`, or `None` if light can't reach the position
+    /// because it's outside the world or in a chunk that isn't available.
+    fn block(&self, pos: [i32; 3]) -> Option<BlockId>;
+
+    /// Returns the light at `pos`; positions above the world get open sky.
+    fn light(&self, pos: [i32; 3]) -> Light;
+
+    /// Sets the light at `pos`; positions without a block are ignored.
+    fn set_light(&mut self, pos: [i32; 3], light: Light);
+}
+
+/// A single chunk, addressed in its local coordinates; light doesn't leave it.
+impl LightStorage for Chunk {
+    fn block(&self, [x, y, z]: [i32; 3]) -> Option<BlockId> {
+        let inside = |v: i32| (0..CHUNK_SIZE as i32).contains(&v);
+        (inside(x) && inside(z) && self.height().contains(y))
+            .then(|| self.get(x as usize, y, z as usize))
+    }
+
+    fn light(&self, [x, y, z]: [i32; 3]) -> Light {
+        let inside = |v: i32| (0..CHUNK_SIZE as i32).contains(&v);
+        if inside(x) && inside(z) {
+            Chunk::light(self, x as usize, y, z as usize)
+        } else {
+            Light::DARK
+        }
+    }
+
+    fn set_light(&mut self, [x, y, z]: [i32; 3], light: Light) {
+        if LightStorage::block(self, [x, y, z]).is_some() {
+            Chunk::set_light(self, x as usize, y, z as usize, light);
+        }
+    }
+}
+
+/// The loaded chunks of a world, addressed in world coordinates.
+pub struct LoadedChunks<'a> {
+    chunks: &'a mut HashMap<ChunkPos, Chunk>,
+    /// The `(x, z)` world coordinates of every column whose light changed.
+    pub changed: HashSet<[i32; 2]>,
+}
+
+impl<'a> LoadedChunks<'a> {
+    pub fn new(chunks: &'a mut HashMap<ChunkPos, Chunk>) -> Self {
+        LoadedChunks {
+            chunks,
+            changed: HashSet::new(),
+        }
+    }
+
+    fn chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
+        self.chunks.get(&ChunkPos::from_block(x, z))
+    }
+}
+
+impl LightStorage for LoadedChunks<'_> {
+    fn block(&self, [x, y, z]: [i32; 3]) -> Option<BlockId> {
+        let chunk = self.chunk(x, z)?;
+        chunk
+            .height()
+            .contains(y)
+            .then(|| chunk.get(local_coord(x), y, local_coord(z)))
+    }
+
+    fn light(&self, [x, y, z]: [i32; 3]) -> Light {
+        match self.chunk(x, z) {
+            Some(chunk) => chunk.light(local_coord(x), y, local_coord(z)),
+            None => Light::DARK,
+        }
+    }
+
+    fn set_light(&mut self, [x, y, z]: [i32; 3], light: Light) {
+        if let Some(chunk) = self.chunks.get_mut(&ChunkPos::from_block(x, z))
+            && chunk.height().contains(y)
+        {
+            chunk.set_light(local_coord(x), y, local_coord(z), light);
+            self.changed.insert([x, z]);
+        }
+    }
+}
+
+/// Computes the light of a chunk on its own, as if its neighbours were dark;
+/// `join_neighbours` then lets light cross its borders.
+///
+/// Columns above the highest block are filled with sky light directly, and only
+/// the blocks next to a change in surface height flood light sideways and down.
+///
+/// # Arguments
+///
+/// * `chunk` - The chunk to light, whose current light is discarded.
+/// * `registry` - The block registry providing opacity and light emission.
+pub fn light_chunk(chunk: &mut Chunk, registry: &BlockRegistry) {
+    let height = chunk.height();
+    let size = CHUNK_SIZE as i32;
+    let top = |chunk: &Chunk, x: i32, z: i32| chunk.top_block(x as usize, z as usize);
+    let highest = (0..size)
+        .flat_map(|x| (0..size).map(move |z| (x, z)))
+        .map(|(x, z)| top(chunk, x, z))
+        .max()
+        .unwrap_or(height.min_y() - 1);
+
+    // Sections above the highest block see the sky; the rest start dark
+    let open = (0..height.sections())
+        .map(|section| height.section_base(section))
+        .find(|&base| base > highest)
+        .unwrap_or(height.max_y());
+    for section in 0..height.sections() {
+        let base = height.section_base(section);
+        let light = if base >= open {
+            Light::SKY
+        } else {
+            Light::DARK
+        };
+        chunk.fill_section_light(base, light);
+    }
+
+    let mut sky = VecDeque::new();
+    for x in 0..size {
+        for z in 0..size {
+            let column_top = top(chunk, x, z);
+            for y in column_top + 1..open {
+                chunk.set_light(x as usize, y, z as usize, Light::SKY);
+            }
+
+            // Lit blocks beside a taller neighbour can light its side and what's under it
+            let beside = Face::ALL
+                .iter()
+                .map(|face| face.normal())
+                .filter(|&[nx, ny, nz]| {
+                    ny == 0 && (0..size).contains(&(x + nx)) && (0..size).contains(&(z + nz))
+                })
+                .map(|[nx, _, nz]| top(chunk, x + nx, z + nz))
+                .max()
+                .unwrap_or(column_top);
+            let highest_seed = beside.max(column_top + 1).min(height.max_y() - 1);
+            sky.extend((column_top + 1..=highest_seed).map(|y| [x, y, z]));
+        }
+    }
+    spread(chunk, registry, Channel::Sky, sky);
+
+    let mut block = VecDeque::new();
+    for base in chunk.non_empty_sections().collect::<Vec<_>>() {
+        for y in base..base + SECTION_SIZE as i32 {
+            for z in 0..size {
+                for x in 0..size {
+                    let emission = registry
+                        .get(chunk.get(x as usize, y, z as usize))
+                        .light_emission;
+                    if emission > 0 {
+                        let light = chunk.light(x as usize, y, z as usize);
+                        chunk.set_light(
+                            x as usize,
+                            y,
+                            z as usize,
+                            light.with(Channel::Block, emission),
+                        );
+                        block.push_back([x, y, z]);
+                    }
+                }
+            }
+        }
+    }
+    spread(chunk, registry, Channel::Block, block);
+
+    chunk.compact_light();
+}
+
+/// Lets light flow between a newly lit chunk and its loaded neighbours, in both
+/// directions.
+///
+/// # Arguments
+///
+/// * `chunks` - The loaded chunks, including the new one.
+/// * `registry` - The block registry providing opacity.
+/// * `pos` - The chunk that was just lit by `light_chunk`.
+pub fn join_neighbours(chunks: &mut LoadedChunks, registry: &BlockRegistry, pos: ChunkPos) {
+    let Some(height) = chunks.chunks.get(&pos).map(Chunk::height) else {
+        return;
+    };
+    let (origin_x, origin_z) = pos.origin();
+    let last = CHUNK_SIZE as i32 - 1;
+
+    let mut seeds = Vec::new();
+    for (side, neighbour) in pos.neighbours().into_iter().enumerate() {
+        if !chunks.chunks.contains_key(&neighbour) {
+            continue;
+        }
+        for i in 0..CHUNK_SIZE as i32 {
+            for y in height.range() {
+                // The border block of the new chunk and the one facing it
+                let (inside, outside) = match side {
+                    0 => ([origin_x, y, origin_z + i], [origin_x - 1, y, origin_z + i]),
+                    1 => (
+                        [origin_x + last, y, origin_z + i],
+                        [origin_x + last + 1, y, origin_z + i],
+                    ),
+                    2 => ([origin_x + i, y, origin_z], [origin_x + i, y, origin_z - 1]),
+                    _ => (
+                        [origin_x + i, y, origin_z + last],
+                        [origin_x + i, y, origin_z + last + 1],
+                    ),
+                };
+                for (from, to) in [(inside, outside), (outside, inside)] {
+                    let (from_light, to_light) = (chunks.light(from), chunks.light(to));
+                    if Channel::ALL
+                        .iter()
+                        .any(|&channel| from_light.get(channel) > to_light.get(channel) + 1)
+                    {
+                        seeds.push(from);
+                    }
+                }
+            }
+        }
+    }
+
+    for channel in Channel::ALL {
+        spread(chunks, registry, channel, seeds.iter().copied().collect());
+    }
+}
+
+/// Updates the light around a block that was just placed or removed.
+///
+/// Light that reached the block, or was emitted by it, is removed along with
+/// everything it lit, and the area is then relit from the light that remains and
+/// from the block itself.
+///
+/// # Arguments
+///
+/// * `storage` - The blocks and light, with the new block already in place.
+/// * `registry` - The block registry providing opacity and light emission.
+/// * `pos` - The position of the changed block.
+pub fn update_block<S: LightStorage>(storage: &mut S, registry: &BlockRegistry, pos: [i32; 3]) {
+    for channel in Channel::ALL {
+        let mut lit = VecDeque::new();
+        let old = storage.light(pos).get(channel);
+        set_level(storage, pos, channel, 0);
+        darken(
+            storage,
+            registry,
+            channel,
+            VecDeque::from([(pos, old)]),
+            &mut lit,
+        );
+
+        let level = source(storage, registry, channel, pos);
+        if level > 0 {
+            set_level(storage, pos, channel, level);
+            lit.push_back(pos);
+        }
+        lit.extend(
+            neighbours(pos)
+                .map(|(_, neighbour)| neighbour)
+                .filter(|&neighbour| storage.light(neighbour).get(channel) > 0),
+        );
+        spread(storage, registry, channel, lit);
+    }
+}
+
+/// Returns the six blocks sharing a face with `pos`, and the face leading to each.
+fn neighbours(pos: [i32; 3]) -> impl Iterator<Item = (Face, [i32; 3])> {
+    Face::ALL.into_iter().map(move |face| {
+        let [nx, ny, nz] = face.normal();
+        (face, [pos[0] + nx, pos[1] + ny, pos[2] + nz])
+    })
+}
+
+fn set_level<S: LightStorage>(storage: &mut S, pos: [i32; 3], channel: Channel, level: u8) {
+    let light = storage.light(pos);
+    storage.set_light(pos, light.with(channel, level));
+}
+
+/// Returns the light a block produces by itself: its emission, or open sky for
+/// non-opaque blocks at the top of the world.
+fn source<S: LightStorage>(
+    storage: &S,
+    registry: &BlockRegistry,
+    channel: Channel,
+    pos: [i32; 3],
+) -> u8 {
+    let Some(block) = storage.block(pos) else {
+        return 0;
+    };
+    match channel {
+        Channel::Block => registry.get(block).light_emission,
+        Channel::Sky => {
+            let above = [pos[0], pos[1] + 1, pos[2]];
+            let at_top = storage.block(above).is_none() && storage.light(above) == Light::SKY;
+            if at_top && !registry.is_opaque(block) {
+                MAX_LIGHT
+            } else {
+                0
+            }
+        }
+    }
+}
+
+/// Floods light outwards from the queued blocks, raising every reachable
+/// non-opaque block to one level less than its brightest neighbour.
+///
+/// Sky light at full strength falls through air without fading.
+fn spread<S: LightStorage>(
+    storage: &mut S,
+    registry: &BlockRegistry,
+    channel: Channel,
+    mut queue: VecDeque<[i32; 3]>,
+) {
+    while let Some(pos) = queue.pop_front() {
+        let level = storage.light(pos).get(channel);
+        if level == 0 {
+            continue;
+        }
+        for (face, neighbour) in neighbours(pos) {
+            let Some(block) = storage.block(neighbour) else {
+                continue;
+            };
+            if registry.is_opaque(block) {
+                continue;
+            }
+            let falling = channel == Channel::Sky && face == Face::NegY && level == MAX_LIGHT;
+            let new_level = if falling && block.is_air() {
+                MAX_LIGHT
+            } else {
+                level - 1
+            };
+            if new_level > storage.light(neighbour).get(channel) {
+                set_level(storage, neighbour, channel, new_level);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
+
+/// Removes the light that spread from the queued blocks, given with the level
+/// each had, queueing the lit blocks bordering the darkened area into `lit` so
+/// they can fill it back in.
+fn darken<S: LightStorage>(
+    storage: &mut S,
+    registry: &BlockRegistry,
+    channel: Channel,
+    mut queue: VecDeque<([i32; 3], u8)>,
+    lit: &mut VecDeque<[i32; 3]>,
+) {
+    while let Some((pos, level)) = queue.pop_front() {
+        for (face, neighbour) in neighbours(pos) {
+            let neighbour_level = storage.light(neighbour).get(channel);
+            if neighbour_level == 0 || storage.block(neighbour).is_none() {
+                continue;
+            }
+            let fell = channel == Channel::Sky
+                && face == Face::NegY
+                && level == MAX_LIGHT
+                && neighbour_level == MAX_LIGHT;
+            if neighbour_level < level || fell {
+                set_level(storage, neighbour, channel, 0);
+                queue.push_back((neighbour, neighbour_level));
+
+                // Light sources keep their own light
+                let own = source(storage, registry, channel, neighbour);
+                if own > 0 {
+                    set_level(storage, neighbour, channel, own);
+                    lit.push_back(neighbour);
+                }
+            } else {
+                lit.push_back(neighbour);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::WorldHeight;
+
+    fn registry() -> BlockRegistry {
+        BlockRegistry::default()
+    }
+
+    /// A chunk with a stone floor filling `y < 0`, open sky above.
+    fn floored_chunk() -> Chunk {
+        let stone = registry().expect_id("stone");
+        let mut chunk = Chunk::new(WorldHeight::new(-16, 32));
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in -16..0 {
+                    chunk.set(x, y, z, stone);
+                }
+            }
+        }
+        chunk
+    }
+
+    fn lit(mut chunk: Chunk) -> Chunk {
+        light_chunk(&mut chunk, &registry());
+        chunk
+    }
+
+    fn all_light(chunk: &Chunk) -> Vec<Light> {
+        let mut lights = Vec::new();
+        for y in chunk.height().range() {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    lights.push(chunk.light(x, y, z));
+                }
+            }
+        }
+        lights
+    }
+
+    /// Changes a block, updates the light incrementally and checks it matches
+    /// lighting the chunk from scratch.
+    fn assert_update_matches(chunk: &mut Chunk, pos: [i32; 3], block: BlockId) {
+        chunk.set(pos[0] as usize, pos[1], pos[2] as usize, block);
+        update_block(chunk, &registry(), pos);
+        let expected = lit(chunk.clone());
+        assert!(all_light(chunk) == all_light(&expected));
+    }
+
+    #[test]
+    fn light_packs_both_channels() {
+        let light = Light::new(12, 3);
+        assert_eq!((light.sky(), light.block()), (12, 3));
+        assert_eq!(light.with(Channel::Block, 9), Light::new(12, 9));
+        assert_eq!(Light::SKY.sky(), MAX_LIGHT);
+    }
+
+    #[test]
+    fn open_sky_is_lit_and_buried_blocks_are_dark() {
+        let chunk = lit(floored_chunk());
+        assert_eq!(chunk.light(3, 20, 3), Light::SKY);
+        assert_eq!(chunk.light(3, 0, 3), Light::SKY);
+        assert_eq!(chunk.light(3, -1, 3), Light::DARK);
+        // Above the world is open sky too
+        assert_eq!(chunk.light(3, 40, 3), Light::SKY);
+    }
+
+    #[test]
+    fn sky_light_fades_under_a_roof() {
+        let stone = registry().expect_id("stone");
+        let mut chunk = floored_chunk();
+        // A roof over x in 0..8 at y = 3
+        for x in 0..8 {
+            for z in 0..CHUNK_SIZE {
+                chunk.set(x, 3, z, stone);
+            }
+        }
+        let chunk = lit(chunk);
+
+        assert_eq!(chunk.light(8, 1, 5).sky(), MAX_LIGHT);
+        assert_eq!(chunk.light(7, 1, 5).sky(), MAX_LIGHT - 1);
+        assert_eq!(chunk.light(2, 1, 5).sky(), MAX_LIGHT - 6);
+    }
+
+    #[test]
+    fn block_light_fades_with_distance() {
+        let mut chunk = floored_chunk();
+        chunk.set(8, 4, 8, registry().expect_id("lamp"));
+        let chunk = lit(chunk);
+
+        assert_eq!(chunk.light(8, 4, 8).block(), MAX_LIGHT);
+        assert_eq!(chunk.light(9, 4, 8).block(), MAX_LIGHT - 1);
+        assert_eq!(chunk.light(11, 6, 8).block(), MAX_LIGHT - 5);
+        // Light doesn't pass into the stone floor
+        assert_eq!(chunk.light(8, -1, 8).block(), 0);
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_relight() {
+        let registry = registry();
+        let (stone, lamp) = (registry.expect_id("stone"), registry.expect_id("lamp"));
+        let mut chunk = lit(floored_chunk());
+
+        // Roof over part of the chunk, then open it up again
+        for x in 2..10 {
+            assert_update_matches(&mut chunk, [x, 4, 6], stone);
+        }
+        assert_update_matches(&mut chunk, [5, 2, 6], lamp);
+        assert_update_matches(&mut chunk, [6, 2, 6], stone);
+        assert_update_matches(&mut chunk, [5, 2, 6], BlockId::AIR);
+        assert_update_matches(&mut chunk, [5, 4, 6], BlockId::AIR);
+        // Dig into the floor
+        assert_update_matches(&mut chunk, [12, -1, 12], BlockId::AIR);
+        assert_update_matches(&mut chunk, [12, -2, 12], BlockId::AIR);
+    }
+
+    #[test]
+    fn light_crosses_into_loaded_neighbours() {
+        let registry = registry();
+        let mut chunks = HashMap::new();
+        let mut lamp_chunk = floored_chunk();
+        lamp_chunk.set(15, 2, 4, registry.expect_id("lamp"));
+        chunks.insert(ChunkPos::new(0, 0), lit(lamp_chunk));
+        chunks.insert(ChunkPos::new(1, 0), lit(floored_chunk()));
+
+        let mut loaded = LoadedChunks::new(&mut chunks);
+        join_neighbours(&mut loaded, &registry, ChunkPos::new(1, 0));
+        assert!(loaded.changed.contains(&[16, 4]));
+        assert_eq!(loaded.light([17, 2, 4]).block(), MAX_LIGHT - 2);
+
+        // Removing the lamp darkens both chunks again
+        chunks
+            .get_mut(&ChunkPos::new(0, 0))
+            .unwrap()
+            .set(15, 2, 4, BlockId::AIR);
+        let mut loaded = LoadedChunks::new(&mut chunks);
+        update_block(&mut loaded, &registry, [15, 2, 4]);
+        assert_eq!(loaded.light([17, 2, 4]).block(), 0);
+        assert_eq!(loaded.light([17, 2, 4]).sky(), MAX_LIGHT);
+    }
+}
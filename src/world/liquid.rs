@@ -0,0 +1,264 @@
+use crate::world::block::BlockId;
+use crate::world::registry::BlockRegistry;
+
+/// The level of a full liquid block: a source, or liquid falling from above.
+pub const FULL_LIQUID: u8 = 8;
+
+/// The state of a liquid block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Liquid {
+    /// The source block of the liquid, shared by all of its levels.
+    pub source: BlockId,
+    /// How full the block is, from 1 to `FULL_LIQUID`; sources are always full.
+    pub level: u8,
+    /// Whether this is the liquid's still source rather than a flowing block.
+    pub is_source: bool,
+}
+
+/// The horizontal offsets of the four blocks a liquid can spread sideways into.
+const SIDES: [[i32; 3]; 4] = [[-1, 0, 0], [1, 0, 0], [0, 0, -1], [0, 0, 1]];
+
+/// The offsets of the blocks liquid can reach a block from: above it and beside it.
+pub const INFLOWS: [[i32; 3]; 5] = [[0, 1, 0], SIDES[0], SIDES[1], SIDES[2], SIDES[3]];
+
+/// Returns the block a position should hold once liquid around it has flowed.
+///
+/// Sources never change, and only air and flowing liquid can be filled. A block
+/// under a liquid is filled to the brim by liquid falling into it. Otherwise it
+/// takes the level of its fullest sideways neighbour, one lower: sources and
+/// falling liquid spread `flow_distance` blocks, and flowing liquid only spreads
+/// sideways once it can't fall any further. A renewable liquid turns a block
+/// between two of its sources into a new source, as long as something holds it
+/// up. Flowing liquid that nothing feeds dries up into air.
+///
+/// # Arguments
+///
+/// * `registry` - The block registry describing the liquids.
+/// * `pos` - The world coordinates of the block to update.
+/// * `block` - Returns the block at world coordinates.
+///
+/// # Returns
+///
+/// The block that should be at `pos`, which may be the one already there.
+pub fn flow<F>(registry: &BlockRegistry, pos: [i32; 3], block: F) -> BlockId
+where
+    F: Fn([i32; 3]) -> BlockId,
+{
+    let offset = |[dx, dy, dz]: [i32; 3]| [pos[0] + dx, pos[1] + dy, pos[2] + dz];
+    let current = block(pos);
+    let here = registry.liquid(current);
+    if here.is_some_and(|liquid| liquid.is_source) || (!current.is_air() && here.is_none()) {
+        return current;
+    }
+    // Liquids don't flow into one another
+    let feeds = |liquid: &Liquid| here.is_none_or(|here| here.source == liquid.source);
+
+    // Returns whether liquid at `at` would rather fall than spread sideways
+    let can_fall = |at: [i32; 3], source: BlockId| {
+        let below = block([at[0], at[1] - 1, at[2]]);
+        below.is_air()
+            || registry
+                .liquid(below)
+                .is_some_and(|l| l.source == source && !l.is_source)
+    };
+
+    let mut best: Option<(BlockId, u8)> = None;
+    let mut sources = Vec::new();
+    for side in SIDES {
+        let at = offset(side);
+        let Some(neighbour) = registry.liquid(block(at)).filter(feeds) else {
+            continue;
+        };
+        if neighbour.is_source {
+            sources.push(neighbour.source);
+        } else if can_fall(at, neighbour.source) {
+            continue;
+        }
+        let level = if neighbour.level == FULL_LIQUID {
+            registry.get(neighbour.source).flow_distance
+        } else {
+            neighbour.level - 1
+        };
+        if best.is_none_or(|(_, best_level)| level > best_level) {
+            best = Some((neighbour.source, level));
+        }
+    }
+
+    if let Some((source, _)) = best
+        && registry.get(source).renewable
+        && sources.iter().filter(|&&s| s == source).count() >= 2
+    {
+        let below = block(offset([0, -1, 0]));
+        let held_up = registry.is_solid(below)
+            || registry
+                .liquid(below)
+                .is_some_and(|l| l.source == source && l.is_source);
+        if held_up {
+            return source;
+        }
+    }
+
+    if let Some(above) = registry.liquid(block(offset([0, 1, 0]))).filter(feeds) {
+        return registry.flowing(above.source, FULL_LIQUID);
+    }
+
+    match best {
+        Some((source, level)) if level > 0 => registry.flowing(source, level),
+        _ => BlockId::AIR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A small world of blocks, with stone below `y = 0` and air everywhere else.
+    struct Blocks {
+        blocks: HashMap<[i32; 3], BlockId>,
+        stone: BlockId,
+    }
+
+    impl Blocks {
+        fn new(registry: &BlockRegistry) -> Self {
+            Blocks {
+                blocks: HashMap::new(),
+                stone: registry.expect_id("stone"),
+            }
+        }
+
+        fn get(&self, pos: [i32; 3]) -> BlockId {
+            match self.blocks.get(&pos) {
+                Some(&block) => block,
+                None if pos[1] < 0 => self.stone,
+                None => BlockId::AIR,
+            }
+        }
+
+        /// Updates every block near liquid until nothing changes, like ticks would.
+        fn settle(&mut self, registry: &BlockRegistry) {
+            for _ in 0..64 {
+                let mut positions: Vec<[i32; 3]> = Vec::new();
+                for pos in self.blocks.keys() {
+                    for [dx, dy, dz] in [[0, 0, 0], [0, -1, 0], [0, 1, 0]].into_iter().chain(SIDES)
+                    {
+                        positions.push([pos[0] + dx, pos[1] + dy, pos[2] + dz]);
+                    }
+                }
+
+                let changes: Vec<_> = positions
+                    .into_iter()
+                    .filter(|pos| pos[1] >= 0)
+                    .map(|pos| (pos, flow(registry, pos, |p| self.get(p))))
+                    .filter(|&(pos, block)| self.get(pos) != block)
+                    .collect();
+                if changes.is_empty() {
+                    return;
+                }
+                for (pos, block) in changes {
+                    if block.is_air() {
+                        self.blocks.remove(&pos);
+                    } else {
+                        self.blocks.insert(pos, block);
+                    }
+                }
+            }
+            panic!("liquid never settled");
+        }
+
+        fn level(&self, registry: &BlockRegistry, pos: [i32; 3]) -> Option<u8> {
+            registry.liquid(self.get(pos)).map(|liquid| liquid.level)
+        }
+    }
+
+    #[test]
+    fn sources_spread_sideways_losing_a_level_per_block() {
+        let registry = BlockRegistry::default();
+        let water = registry.expect_id("water");
+        let mut blocks = Blocks::new(&registry);
+        blocks.blocks.insert([0, 0, 0], water);
+        blocks.settle(&registry);
+
+        let distance = registry.get(water).flow_distance;
+        assert_eq!(blocks.get([0, 0, 0]), water);
+        for step in 1..=distance as i32 {
+            let level = Some(distance + 1 - step as u8);
+            assert_eq!(blocks.level(&registry, [step, 0, 0]), level);
+            assert_eq!(blocks.level(&registry, [0, 0, -step]), level);
+        }
+        assert_eq!(blocks.get([distance as i32 + 1, 0, 0]), BlockId::AIR);
+        assert_eq!(blocks.get([0, 1, 0]), BlockId::AIR);
+    }
+
+    #[test]
+    fn liquid_falls_before_spreading() {
+        let registry = BlockRegistry::default();
+        let water = registry.expect_id("water");
+        let mut blocks = Blocks::new(&registry);
+        blocks.blocks.insert([0, 3, 0], water);
+        // A pillar holds the source up, so the water pours over its sides
+        for y in 0..3 {
+            blocks.blocks.insert([0, y, 0], blocks.stone);
+        }
+        blocks.settle(&registry);
+
+        // Beside the source the water is one level down, then falls straight to
+        // the ground as full columns and spreads from there
+        assert_eq!(blocks.level(&registry, [1, 3, 0]), Some(7));
+        assert_eq!(blocks.level(&registry, [1, 2, 0]), Some(FULL_LIQUID));
+        assert_eq!(blocks.level(&registry, [1, 0, 0]), Some(FULL_LIQUID));
+        assert_eq!(blocks.get([2, 3, 0]), BlockId::AIR);
+        assert_eq!(blocks.level(&registry, [2, 0, 0]), Some(7));
+    }
+
+    #[test]
+    fn renewable_liquids_fill_gaps_between_sources() {
+        let registry = BlockRegistry::default();
+        let water = registry.expect_id("water");
+        let mut blocks = Blocks::new(&registry);
+        blocks.blocks.insert([0, 0, 0], water);
+        blocks.blocks.insert([2, 0, 0], water);
+        blocks.settle(&registry);
+        assert_eq!(blocks.get([1, 0, 0]), water);
+
+        // Lava doesn't renew
+        let lava = registry.expect_id("lava");
+        let mut blocks = Blocks::new(&registry);
+        blocks.blocks.insert([0, 0, 0], lava);
+        blocks.blocks.insert([2, 0, 0], lava);
+        blocks.settle(&registry);
+        assert_ne!(blocks.get([1, 0, 0]), lava);
+        assert!(registry.liquid(blocks.get([1, 0, 0])).is_some());
+    }
+
+    #[test]
+    fn removing_the_source_dries_the_flow_up() {
+        let registry = BlockRegistry::default();
+        let water = registry.expect_id("water");
+        let mut blocks = Blocks::new(&registry);
+        blocks.blocks.insert([0, 0, 0], water);
+        blocks.settle(&registry);
+        assert!(blocks.blocks.len() > 1);
+
+        blocks.blocks.remove(&[0, 0, 0]);
+        blocks.settle(&registry);
+        assert!(blocks.blocks.is_empty());
+    }
+
+    #[test]
+    fn liquids_do_not_flow_into_solid_blocks_or_each_other() {
+        let registry = BlockRegistry::default();
+        let (water, lava) = (registry.expect_id("water"), registry.expect_id("lava"));
+        let mut blocks = Blocks::new(&registry);
+        blocks.blocks.insert([0, 0, 0], water);
+        blocks.blocks.insert([1, 0, 0], blocks.stone);
+        blocks.blocks.insert([-3, 0, 0], lava);
+        blocks.settle(&registry);
+
+        assert_eq!(blocks.get([1, 0, 0]), blocks.stone);
+        let between = registry.liquid(blocks.get([-2, 0, 0])).unwrap();
+        assert_eq!(between.source, lava);
+        let between = registry.liquid(blocks.get([-1, 0, 0])).unwrap();
+        assert_eq!(between.source, water);
+    }
+}
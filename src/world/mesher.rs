@@ -0,0 +1,989 @@
+use crate::rendering::mesh::{VertexAttribute, VertexLayout};
+use crate::world::block::{BlockId, Face};
+use crate::world::chunk::{CHUNK_SIZE, Chunk};
+use crate::world::light::{Light, MAX_LIGHT};
+use crate::world::liquid::FULL_LIQUID;
+use crate::world::registry::BlockRegistry;
+use crate::world::section::SECTION_SIZE;
+
+/// The attributes of a chunk vertex. Texture coordinates count blocks across the
+/// quad, so merged quads repeat their atlas tile once per block.
+pub const CHUNK_VERTEX_LAYOUT: VertexLayout = VertexLayout::new(&[
+    VertexAttribute::Position,
+    VertexAttribute::Normal,
+    VertexAttribute::Color,
+    VertexAttribute::Uv,
+    VertexAttribute::Light,
+    VertexAttribute::Tile,
+]);
+
+/// The number of floats per chunk vertex.
+pub const CHUNK_VERTEX_SIZE: usize = CHUNK_VERTEX_LAYOUT.vertex_size();
+
+/// The number of vertices emitted per face (two triangles).
+const VERTICES_PER_FACE: usize = 6;
+
+/// The occlusion of a face corner with no opaque blocks around it.
+const UNOCCLUDED: u8 = 3;
+
+/// The height of a block that fills its whole cell, in ninths of a block, so a
+/// full liquid stops just short of the top.
+const FULL_HEIGHT: u8 = FULL_LIQUID + 1;
+
+/// How a visible block face looks; adjacent faces that look the same can be merged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FaceLook {
+    block: BlockId,
+    /// The light of the block in front of the face.
+    light: Light,
+    /// The ambient occlusion of each corner, in `Face::corners` order, from `0`
+    /// (darkest) to `UNOCCLUDED`.
+    occlusion: [u8; 4],
+    /// How high the block reaches, in ninths of a block up to `FULL_HEIGHT`.
+    height: u8,
+}
+
+/// CPU-side vertex data for a chunk, ready to be uploaded into a `Mesh`.
+#[derive(Debug, Default)]
+pub struct ChunkMeshData {
+    /// Interleaved vertices of opaque faces, laid out as described by
+    /// `CHUNK_VERTEX_LAYOUT`.
+    pub vertices: Vec<f32>,
+    /// Interleaved vertices of translucent faces, drawn blended after opaque
+    /// ones. Each face's six vertices are kept together so they can be sorted.
+    pub translucent: Vec<f32>,
+}
+
+impl ChunkMeshData {
+    /// Returns the number of faces (quads) in the mesh, opaque and translucent.
+    pub fn face_count(&self) -> usize {
+        (self.vertices.len() + self.translucent.len()) / (CHUNK_VERTEX_SIZE * VERTICES_PER_FACE)
+    }
+
+    /// Returns `true` if the mesh contains no faces.
+    pub fn is_empty(&self) -> bool {
+        self.face_count() == 0
+    }
+
+    /// Appends a quad with the given corners, ordered counter-clockwise from outside.
+    ///
+    /// The quad is split along the diagonal between its brighter pair of opposite
+    /// corners, so occlusion fades evenly instead of along one triangle's edge.
+    fn push_quad(
+        &mut self,
+        mut corners: [[f32; 3]; 4],
+        face: Face,
+        registry: &BlockRegistry,
+        look: FaceLook,
+    ) {
+        // Lower the top edge of blocks that don't fill their cell
+        if look.height < FULL_HEIGHT && face != Face::NegY {
+            let top = corners
+                .iter()
+                .map(|corner| corner[1])
+                .fold(f32::MIN, f32::max);
+            let drop = (FULL_HEIGHT - look.height) as f32 / FULL_HEIGHT as f32;
+            for corner in corners.iter_mut().filter(|corner| corner[1] == top) {
+                corner[1] -= drop;
+            }
+        }
+
+        let color = registry.get(look.block).color;
+        let tile = registry.tile(look.block, face) as f32;
+        let vertices = if registry.is_translucent(look.block) {
+            &mut self.translucent
+        } else {
+            &mut self.vertices
+        };
+        let normal = face.normal().map(|n| n as f32);
+        // Corners run bottom-left, bottom-right, top-right, top-left
+        let extent = |to: usize| (0..3).map(|a| (corners[to][a] - corners[0][a]).abs()).sum();
+        let (width, height): (f32, f32) = (extent(1), extent(3));
+        let uvs = [[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]];
+        let [sky, block] =
+            [look.light.sky(), look.light.block()].map(|level| level as f32 / MAX_LIGHT as f32);
+        let occlusion = look.occlusion;
+        let indices = if occlusion[0] + occlusion[2] >= occlusion[1] + occlusion[3] {
+            [0, 1, 2, 2, 3, 0]
+        } else {
+            [1, 2, 3, 3, 0, 1]
+        };
+        for index in indices {
+            vertices.extend_from_slice(&corners[index]);
+            vertices.extend_from_slice(&normal);
+            vertices.extend_from_slice(&color);
+            vertices.extend_from_slice(&uvs[index]);
+            let ambient = occlusion[index] as f32 / UNOCCLUDED as f32;
+            vertices.extend_from_slice(&[sky, block, ambient]);
+            vertices.push(tile);
+        }
+    }
+}
+
+/// Reorders the faces of translucent vertex data from farthest to nearest, so
+/// blending them in order shows nearer faces over farther ones.
+///
+/// # Arguments
+///
+/// * `vertices` - Interleaved translucent vertices, six per face.
+/// * `eye` - The camera position, in the same coordinates as the vertices.
+pub fn sort_back_to_front(vertices: &mut [f32], eye: [f32; 3]) {
+    let face_size = CHUNK_VERTEX_SIZE * VERTICES_PER_FACE;
+    // The six vertices weigh opposite corners equally, so their mean is the centre
+    let distance = |face: &[f32]| -> f32 {
+        (0..3)
+            .map(|axis| {
+                let sum: f32 = face.chunks(CHUNK_VERTEX_SIZE).map(|v| v[axis]).sum();
+                (sum / VERTICES_PER_FACE as f32 - eye[axis]).powi(2)
+            })
+            .sum()
+    };
+
+    let mut faces: Vec<(f32, &[f32])> = vertices
+        .chunks(face_size)
+        .map(|face| (distance(face), face))
+        .collect();
+    faces.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let sorted: Vec<f32> = faces
+        .into_iter()
+        .flat_map(|(_, face)| face)
+        .copied()
+        .collect();
+    vertices.copy_from_slice(&sorted);
+}
+
+/// Builds the faces of a lone block spanning `[0, 1]` on each axis, for drawing
+/// blocks that aren't part of a chunk, such as falling blocks.
+///
+/// # Arguments
+///
+/// * `registry` - The block registry providing colors and textures.
+/// * `block` - The block to build.
+/// * `light` - The light shining on every face.
+///
+/// # Returns
+///
+/// The vertices of all six faces, in `CHUNK_VERTEX_LAYOUT`.
+pub fn block_vertices(registry: &BlockRegistry, block: BlockId, light: Light) -> Vec<f32> {
+    let look = FaceLook {
+        block,
+        light,
+        occlusion: [UNOCCLUDED; 4],
+        height: FULL_HEIGHT,
+    };
+    let mut mesh = ChunkMeshData::default();
+    for face in Face::ALL {
+        mesh.push_quad(face.corners(), face, registry, look);
+    }
+    mesh.vertices.extend(mesh.translucent);
+    mesh.vertices
+}
+
+/// How faces are turned into quads when building a chunk mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshingMode {
+    /// One quad per visible block face.
+    Naive,
+    /// Coplanar adjacent faces of the same block are merged into larger quads.
+    Greedy,
+}
+
+/// Builds the mesh for a chunk, emitting only the faces that aren't hidden by an
+/// opaque neighbour or a neighbouring block of the same kind. Faces of
+/// translucent blocks are kept apart from the rest, to be drawn blended, and
+/// liquids reach as high up their block as they are full.
+///
+/// Vertex positions are local to the chunk horizontally and world coordinates
+/// vertically, with block `(x, y, z)` spanning `[x, x + 1]` on each axis. Sections
+/// holding nothing but air are skipped. Each face is lit by the light of the
+/// block in front of it, and each of its corners is darkened by the opaque blocks
+/// touching that corner in front of the face (ambient occlusion).
+///
+/// # Arguments
+///
+/// * `chunk` - The chunk to mesh.
+/// * `registry` - The block registry providing colors, textures and opacity.
+/// * `mode` - Whether to merge adjacent faces into larger quads.
+/// * `outside_block` - Returns the block and light at chunk-local coordinates that
+///   fall outside the chunk (e.g. `x == -1`), so faces on chunk borders can be
+///   culled against and lit by neighbouring chunks.
+///
+/// # Returns
+///
+/// The vertex data for the chunk.
+pub fn build_chunk_mesh<F>(
+    chunk: &Chunk,
+    registry: &BlockRegistry,
+    mode: MeshingMode,
+    outside_block: F,
+) -> ChunkMeshData
+where
+    F: Fn(i32, i32, i32) -> (BlockId, Light),
+{
+    let block_at = |x: i32, y: i32, z: i32| -> (BlockId, Light) {
+        let inside = (0..CHUNK_SIZE as i32).contains(&x) && (0..CHUNK_SIZE as i32).contains(&z);
+        if inside {
+            let (x, z) = (x as usize, z as usize);
+            (chunk.get(x, y, z), chunk.light(x, y, z))
+        } else {
+            outside_block(x, y, z)
+        }
+    };
+
+    let occludes = |p: [i32; 3]| registry.is_opaque(block_at(p[0], p[1], p[2]).0);
+
+    // Returns how the `face` of the block at `p` looks, or `None` if it's hidden
+    let visible_face = |p: [i32; 3], face: Face| -> Option<FaceLook> {
+        let block_id = chunk.get(p[0] as usize, p[1], p[2] as usize);
+        if block_id.is_air() {
+            return None;
+        }
+        let normal = face.normal();
+        let axis = normal.iter().position(|&n| n != 0).unwrap();
+        let front = [0, 1, 2].map(|axis| p[axis] + normal[axis]);
+        let (neighbour, light) = block_at(front[0], front[1], front[2]);
+        // Transparent blocks of the same kind merge into one volume, like a body of water
+        if registry.is_opaque(neighbour) || neighbour == block_id {
+            return None;
+        }
+
+        // Liquids are as high as they are full, unless more of the same liquid
+        // lies on top, and only show where they rise above the liquid beside them
+        let liquid = registry.liquid(block_id);
+        let same_liquid = |other: BlockId| {
+            registry
+                .liquid(other)
+                .filter(|other| liquid.is_some_and(|liquid| liquid.source == other.source))
+        };
+        let height = match liquid {
+            Some(liquid) if same_liquid(block_at(p[0], p[1] + 1, p[2]).0).is_none() => liquid.level,
+            _ => FULL_HEIGHT,
+        };
+        if let (Some(liquid), Some(beside)) = (liquid, same_liquid(neighbour))
+            && (axis == 1 || beside.level >= liquid.level)
+        {
+            return None;
+        }
+
+        // Each corner is shaded by the two blocks beside it and the one diagonal
+        // to it in the layer in front of the face
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let occlusion = face.corners().map(|corner| {
+            let step = |c: f32| if c > 0.5 { 1 } else { -1 };
+            let (du, dv) = (step(corner[u]), step(corner[v]));
+            let offset = |a: i32, b: i32| {
+                let mut q = front;
+                q[u] += a;
+                q[v] += b;
+                occludes(q)
+            };
+            let (side_u, side_v, diagonal) = (offset(du, 0), offset(0, dv), offset(du, dv));
+            if side_u && side_v {
+                0
+            } else {
+                UNOCCLUDED - side_u as u8 - side_v as u8 - diagonal as u8
+            }
+        });
+
+        Some(FaceLook {
+            block: block_id,
+            light,
+            occlusion,
+            height,
+        })
+    };
+
+    let mut mesh = ChunkMeshData::default();
+    for base_y in chunk.non_empty_sections() {
+        match mode {
+            MeshingMode::Naive => mesh_naive(&mut mesh, registry, base_y, &visible_face),
+            MeshingMode::Greedy => mesh_greedy(&mut mesh, registry, base_y, &visible_face),
+        }
+    }
+    mesh
+}
+
+/// Emits one quad per visible block face of the section starting at `base_y`.
+fn mesh_naive<F>(mesh: &mut ChunkMeshData, registry: &BlockRegistry, base_y: i32, visible_face: &F)
+where
+    F: Fn([i32; 3], Face) -> Option<FaceLook>,
+{
+    for y in base_y..base_y + SECTION_SIZE as i32 {
+        for z in 0..CHUNK_SIZE as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                for face in Face::ALL {
+                    let Some(look) = visible_face([x, y, z], face) else {
+                        continue;
+                    };
+
+                    let corners = face
+                        .corners()
+                        .map(|[cx, cy, cz]| [cx + x as f32, cy + y as f32, cz + z as f32]);
+                    mesh.push_quad(corners, face, registry, look);
+                }
+            }
+        }
+    }
+}
+
+/// Sweeps each face direction of the section starting at `base_y` slice by slice,
+/// merging faces that look the same into the largest rectangles it can grow (first
+/// along `u`, then `v`).
+///
+/// Faces are only stretched along an axis their corner occlusion doesn't vary
+/// along, so merged quads shade exactly like the faces they replace.
+fn mesh_greedy<F>(mesh: &mut ChunkMeshData, registry: &BlockRegistry, base_y: i32, visible_face: &F)
+where
+    F: Fn([i32; 3], Face) -> Option<FaceLook>,
+{
+    let dims = [CHUNK_SIZE, SECTION_SIZE, CHUNK_SIZE];
+    let origin = [0, base_y, 0];
+
+    for face in Face::ALL {
+        // The axis the face points along, and the two axes spanning its plane
+        let axis = face.normal().iter().position(|&n| n != 0).unwrap();
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (size_u, size_v) = (dims[u], dims[v]);
+        let mut mask = vec![None; size_u * size_v];
+
+        for slice in 0..dims[axis] {
+            for j in 0..size_v {
+                for i in 0..size_u {
+                    let mut p = origin;
+                    p[axis] += slice as i32;
+                    p[u] += i as i32;
+                    p[v] += j as i32;
+                    mask[j * size_u + i] = visible_face(p, face);
+                }
+            }
+
+            for j in 0..size_v {
+                let mut i = 0;
+                while i < size_u {
+                    let cell = mask[j * size_u + i];
+                    let Some(look) = cell else {
+                        i += 1;
+                        continue;
+                    };
+
+                    let mut width = 1;
+                    if stretchable(face, u, look) {
+                        while i + width < size_u && mask[j * size_u + i + width] == cell {
+                            width += 1;
+                        }
+                    }
+
+                    let mut height = 1;
+                    if stretchable(face, v, look) {
+                        while j + height < size_v
+                            && mask[(j + height) * size_u + i..][..width]
+                                .iter()
+                                .all(|&other| other == cell)
+                        {
+                            height += 1;
+                        }
+                    }
+
+                    for row in j..j + height {
+                        mask[row * size_u + i..][..width].fill(None);
+                    }
+
+                    // Stretch the unit face over the merged rectangle
+                    let corners = face.corners().map(|corner| {
+                        let mut position = origin.map(|o| o as f32);
+                        position[axis] += slice as f32 + corner[axis];
+                        position[u] += i as f32 + corner[u] * width as f32;
+                        position[v] += j as f32 + corner[v] * height as f32;
+                        position
+                    });
+                    mesh.push_quad(corners, face, registry, look);
+
+                    i += width;
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` if a face can be stretched along `axis` and still shade and
+/// fit its blocks. Faces of blocks that don't fill their cell can't stretch
+/// upwards, since each is lowered to its own block's height.
+fn stretchable(face: Face, axis: usize, look: FaceLook) -> bool {
+    occlusion_constant_along(face, axis, look.occlusion)
+        && (axis != 1 || look.height == FULL_HEIGHT)
+}
+
+/// Returns `true` if corners of the face that differ only along `axis` have the
+/// same occlusion, so the face can be stretched along it.
+fn occlusion_constant_along(face: Face, axis: usize, occlusion: [u8; 4]) -> bool {
+    let corners = face.corners();
+    (0..4).all(|i| {
+        (0..4).all(|j| {
+            let aligned =
+                (0..3).all(|other| other == axis || corners[i][other] == corners[j][other]);
+            !aligned || occlusion[i] == occlusion[j]
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GenerationConfig;
+    use crate::rendering::atlas::TextureAtlas;
+    use crate::world::chunk::{ChunkPos, WorldHeight};
+    use crate::world::generation::TerrainGenerator;
+    use crate::world::light::light_chunk;
+
+    /// An empty chunk three sections tall, reaching below zero.
+    fn empty_chunk() -> Chunk {
+        Chunk::new(WorldHeight::new(-16, 32))
+    }
+
+    fn id(name: &str) -> BlockId {
+        BlockRegistry::default().expect_id(name)
+    }
+
+    fn mesh_with_mode(chunk: &Chunk, mode: MeshingMode) -> ChunkMeshData {
+        build_chunk_mesh(chunk, &BlockRegistry::default(), mode, |_, _, _| {
+            (BlockId::AIR, Light::SKY)
+        })
+    }
+
+    fn mesh_with_air_outside(chunk: &Chunk) -> ChunkMeshData {
+        mesh_with_mode(chunk, MeshingMode::Naive)
+    }
+
+    #[test]
+    fn empty_chunk_has_no_faces() {
+        let mesh = mesh_with_air_outside(&empty_chunk());
+        assert!(mesh.is_empty());
+        assert_eq!(mesh.face_count(), 0);
+    }
+
+    #[test]
+    fn single_block_has_six_faces() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("stone"));
+        assert_eq!(mesh_with_air_outside(&chunk).face_count(), 6);
+    }
+
+    #[test]
+    fn adjacent_blocks_hide_shared_faces() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("stone"));
+        chunk.set(5, 4, 4, id("dirt"));
+        assert_eq!(mesh_with_air_outside(&chunk).face_count(), 10);
+    }
+
+    #[test]
+    fn blocks_hide_faces_across_section_borders() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, -1, 4, id("stone"));
+        chunk.set(4, 0, 4, id("dirt"));
+        assert_eq!(mesh_with_air_outside(&chunk).face_count(), 10);
+        assert_same_surface(&chunk);
+    }
+
+    #[test]
+    fn solid_cube_only_emits_its_surface() {
+        let mut chunk = empty_chunk();
+        for x in 2..5 {
+            for y in 2..5 {
+                for z in 2..5 {
+                    chunk.set(x, y, z, id("stone"));
+                }
+            }
+        }
+        // 3x3 faces on each of the six sides; the buried centre block emits nothing
+        assert_eq!(mesh_with_air_outside(&chunk).face_count(), 6 * 9);
+    }
+
+    #[test]
+    fn full_layer_emits_top_bottom_and_border_faces() {
+        let mut chunk = empty_chunk();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.set(x, 0, z, id("stone"));
+            }
+        }
+        let expected = 2 * CHUNK_SIZE * CHUNK_SIZE + 4 * CHUNK_SIZE;
+        assert_eq!(mesh_with_air_outside(&chunk).face_count(), expected);
+    }
+
+    #[test]
+    fn faces_against_neighbouring_chunks_are_culled() {
+        let mut chunk = empty_chunk();
+        chunk.set(0, 4, 0, id("stone"));
+
+        // The neighbour at x = -1 is solid, everything else outside is air
+        let mesh = build_chunk_mesh(
+            &chunk,
+            &BlockRegistry::default(),
+            MeshingMode::Naive,
+            |x, y, z| {
+                if (x, y, z) == (-1, 4, 0) {
+                    (id("stone"), Light::DARK)
+                } else {
+                    (BlockId::AIR, Light::SKY)
+                }
+            },
+        );
+        assert_eq!(mesh.face_count(), 5);
+    }
+
+    #[test]
+    fn transparent_neighbours_do_not_hide_faces() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("stone"));
+        chunk.set(4, 5, 4, id("water"));
+
+        // The stone's top face stays visible under the water, but the water's
+        // bottom face is hidden by the stone
+        assert_eq!(mesh_with_air_outside(&chunk).face_count(), 6 + 5);
+    }
+
+    #[test]
+    fn translucent_faces_are_kept_apart() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("stone"));
+        chunk.set(4, 5, 4, id("water"));
+        let mesh = mesh_with_air_outside(&chunk);
+
+        let faces = |vertices: &[f32]| vertices.len() / (CHUNK_VERTEX_SIZE * VERTICES_PER_FACE);
+        assert_eq!(faces(&mesh.vertices), 6);
+        assert_eq!(faces(&mesh.translucent), 5);
+    }
+
+    #[test]
+    fn faces_between_matching_transparent_blocks_are_culled() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("water"));
+        chunk.set(5, 4, 4, id("water"));
+        chunk.set(4, 5, 4, id("glass"));
+
+        // The waters join into one 2x1x1 volume, but the glass and water still
+        // show through each other
+        let mesh = mesh_with_air_outside(&chunk);
+        assert_eq!(mesh.face_count(), 10 + 6);
+    }
+
+    #[test]
+    fn liquid_surfaces_follow_their_level() {
+        let registry = BlockRegistry::default();
+        let water = registry.expect_id("water");
+        let mut chunk = empty_chunk();
+        chunk.set(2, 4, 2, water);
+        chunk.set(3, 4, 2, registry.flowing(water, 4));
+        chunk.set(8, 4, 8, water);
+        chunk.set(8, 5, 8, water);
+        let mesh = mesh_with_air_outside(&chunk);
+        let vertices = || mesh.translucent.chunks(CHUNK_VERTEX_SIZE);
+
+        // Tops sit at the level of the liquid, as ninths of a block
+        let top = |x: f32| {
+            vertices()
+                .find(|v| v[4] == 1.0 && v[0] == x && v[2] < 4.0)
+                .map(|v| v[1])
+        };
+        assert_eq!(top(2.0), Some(4.0 + 8.0 / 9.0));
+        assert_eq!(top(4.0), Some(4.0 + 4.0 / 9.0));
+
+        // Between the two, only the fuller water's side shows
+        let sides_between = vertices().filter(|v| v[3] != 0.0 && v[0] == 3.0).count();
+        assert_eq!(sides_between, VERTICES_PER_FACE);
+
+        // Water under more water fills its block
+        assert!(vertices().any(|v| v[0] == 8.0 && v[1] == 5.0));
+        assert!(vertices().all(|v| v[0] < 8.0 || v[1] != 4.0 + 8.0 / 9.0));
+    }
+
+    #[test]
+    fn lone_block_vertices_fill_the_unit_cube() {
+        let registry = BlockRegistry::default();
+        let vertices = block_vertices(&registry, id("sand"), Light::SKY);
+        assert_eq!(vertices.len(), 6 * VERTICES_PER_FACE * CHUNK_VERTEX_SIZE);
+        for vertex in vertices.chunks(CHUNK_VERTEX_SIZE) {
+            assert!(vertex[..3].iter().all(|&v| v == 0.0 || v == 1.0));
+            assert_eq!(vertex[12], 1.0);
+        }
+    }
+
+    #[test]
+    fn translucent_faces_sort_farthest_first() {
+        let mut chunk = empty_chunk();
+        for x in 0..4 {
+            chunk.set(x * 2, 4, 0, id("glass"));
+        }
+        let mut mesh = mesh_with_air_outside(&chunk);
+        let eye = [-10.0, 4.5, 0.5];
+        sort_back_to_front(&mut mesh.translucent, eye);
+
+        let distances: Vec<f32> = mesh
+            .translucent
+            .chunks(CHUNK_VERTEX_SIZE * VERTICES_PER_FACE)
+            .map(|face| {
+                let centre: Vec<f32> = (0..3)
+                    .map(|axis| face.chunks(CHUNK_VERTEX_SIZE).map(|v| v[axis]).sum::<f32>() / 6.0)
+                    .collect();
+                (0..3).map(|axis| (centre[axis] - eye[axis]).powi(2)).sum()
+            })
+            .collect();
+        assert_eq!(distances.len(), 4 * 6);
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn vertices_span_the_block_cell() {
+        let mut chunk = empty_chunk();
+        chunk.set(2, 3, 4, id("stone"));
+        let mesh = mesh_with_air_outside(&chunk);
+
+        for vertex in mesh.vertices.chunks(CHUNK_VERTEX_SIZE) {
+            assert!((2.0..=3.0).contains(&vertex[0]));
+            assert!((3.0..=4.0).contains(&vertex[1]));
+            assert!((4.0..=5.0).contains(&vertex[2]));
+        }
+    }
+
+    /// A unit block face: outward normal, owning block, color, light and tile bits,
+    /// the bits of its plane and of its extent along `u` and `v` (less than a block
+    /// for liquids), and the occlusion bits of its corners indexed by their side
+    /// along `u` and `v`.
+    type UnitFace = ([i32; 3], [i32; 3], [u32; 7], [u32; 5], [[u32; 2]; 2]);
+
+    /// Expands every quad of a mesh, opaque and translucent, into the unit block
+    /// faces it covers.
+    fn covered_faces(mesh: &ChunkMeshData) -> Vec<UnitFace> {
+        let mut faces = Vec::new();
+        let quad_size = CHUNK_VERTEX_SIZE * VERTICES_PER_FACE;
+
+        for quad in mesh
+            .vertices
+            .chunks(quad_size)
+            .chain(mesh.translucent.chunks(quad_size))
+        {
+            let vertex = |index: usize| &quad[index * CHUNK_VERTEX_SIZE..][..CHUNK_VERTEX_SIZE];
+            let normal = [
+                vertex(0)[3] as i32,
+                vertex(0)[4] as i32,
+                vertex(0)[5] as i32,
+            ];
+            let color = [6, 7, 8, 9, 12, 13, 15].map(|i| vertex(0)[i].to_bits());
+
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for index in 0..VERTICES_PER_FACE {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(vertex(index)[axis]);
+                    max[axis] = max[axis].max(vertex(index)[axis]);
+                }
+            }
+
+            let axis = normal.iter().position(|&n| n != 0).unwrap();
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+            // Merged quads must shade each face they cover like its own corners
+            let mut occlusion = [[0; 2]; 2];
+            for index in 0..VERTICES_PER_FACE {
+                let side_u = (vertex(index)[u] == max[u]) as usize;
+                let side_v = (vertex(index)[v] == max[v]) as usize;
+                occlusion[side_u][side_v] = vertex(index)[14].to_bits();
+            }
+
+            for a in min[u].floor() as i32..max[u].ceil() as i32 {
+                for b in min[v].floor() as i32..max[v].ceil() as i32 {
+                    let mut block = [0; 3];
+                    // The face plane sits on the far side of blocks facing positive
+                    // axes, and below the top of liquids that aren't full
+                    block[axis] = if normal[axis] > 0 {
+                        max[axis].ceil() as i32 - 1
+                    } else {
+                        min[axis].floor() as i32
+                    };
+                    block[u] = a;
+                    block[v] = b;
+                    let extent = [
+                        min[axis],
+                        min[u].max(a as f32),
+                        max[u].min(a as f32 + 1.0),
+                        min[v].max(b as f32),
+                        max[v].min(b as f32 + 1.0),
+                    ]
+                    .map(f32::to_bits);
+                    faces.push((normal, block, color, extent, occlusion));
+                }
+            }
+        }
+
+        faces.sort_unstable();
+        faces
+    }
+
+    fn assert_same_surface(chunk: &Chunk) {
+        let naive = mesh_with_mode(chunk, MeshingMode::Naive);
+        let greedy = mesh_with_mode(chunk, MeshingMode::Greedy);
+
+        assert!(greedy.face_count() <= naive.face_count());
+        // Equal sorted lists also rule out overlapping greedy quads
+        assert_eq!(covered_faces(&naive), covered_faces(&greedy));
+    }
+
+    #[test]
+    fn greedy_merges_a_flat_layer_into_one_quad_per_side() {
+        let mut chunk = empty_chunk();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.set(x, 0, z, id("stone"));
+            }
+        }
+
+        let mesh = mesh_with_mode(&chunk, MeshingMode::Greedy);
+        assert_eq!(mesh.face_count(), 6);
+        assert_same_surface(&chunk);
+
+        // Texture coordinates span one unit per block, so textures tile
+        let uvs: Vec<_> = mesh
+            .vertices
+            .chunks(CHUNK_VERTEX_SIZE)
+            .map(|vertex| [vertex[10], vertex[11]])
+            .collect();
+        assert!(uvs.contains(&[CHUNK_SIZE as f32, CHUNK_SIZE as f32]));
+        assert!(uvs.contains(&[CHUNK_SIZE as f32, 1.0]));
+    }
+
+    #[test]
+    fn greedy_does_not_merge_different_blocks() {
+        let mut chunk = empty_chunk();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let block = if x < CHUNK_SIZE / 2 { "dirt" } else { "grass" };
+                chunk.set(x, 0, z, id(block));
+            }
+        }
+
+        // Two quads each for top and bottom, one for each side wall
+        let mesh = mesh_with_mode(&chunk, MeshingMode::Greedy);
+        assert_eq!(mesh.face_count(), 2 + 2 + 2 + 2 + 1 + 1);
+        assert_same_surface(&chunk);
+    }
+
+    #[test]
+    fn greedy_cannot_merge_a_checkerboard() {
+        let mut chunk = empty_chunk();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                if (x + z) % 2 == 0 {
+                    chunk.set(x, 0, z, id("stone"));
+                }
+            }
+        }
+
+        let naive = mesh_with_mode(&chunk, MeshingMode::Naive);
+        let greedy = mesh_with_mode(&chunk, MeshingMode::Greedy);
+        assert_eq!(naive.face_count(), greedy.face_count());
+        assert_same_surface(&chunk);
+    }
+
+    #[test]
+    fn greedy_meshes_water_and_glass_like_naive() {
+        let registry = BlockRegistry::default();
+        let (water, glass) = (registry.expect_id("water"), registry.expect_id("glass"));
+        let mut chunk = empty_chunk();
+
+        // A pool across a section border, sloping down at one end, with glass
+        // blocks in it and a column of water standing on it
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let surface = match x {
+                    0..9 => water,
+                    9..16 => registry.flowing(water, 16 - x as u8),
+                    _ => unreachable!(),
+                };
+                chunk.set(x, -2, z, water);
+                chunk.set(x, -1, z, surface);
+            }
+        }
+        for z in 4..8 {
+            chunk.set(3, -1, z, glass);
+            chunk.set(3, 0, z, glass);
+        }
+        for y in 0..3 {
+            chunk.set(6, y, 6, water);
+        }
+
+        let greedy = mesh_with_mode(&chunk, MeshingMode::Greedy);
+        assert!(!greedy.translucent.is_empty());
+        assert_same_surface(&chunk);
+    }
+
+    #[test]
+    fn faces_are_lit_by_the_block_in_front() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("stone"));
+        chunk.set_light(4, 5, 4, Light::new(9, 3));
+        let mesh = mesh_with_air_outside(&chunk);
+
+        for vertex in mesh.vertices.chunks(CHUNK_VERTEX_SIZE) {
+            let light = [vertex[12], vertex[13]];
+            if vertex[4] == 1.0 {
+                assert_eq!(light, [9.0 / 15.0, 3.0 / 15.0]);
+            } else {
+                assert_eq!(light, [0.0, 0.0]);
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_does_not_merge_differently_lit_faces() {
+        let mut chunk = empty_chunk();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.set(x, 0, z, id("stone"));
+            }
+        }
+        chunk.set(3, 4, 3, id("lamp"));
+        light_chunk(&mut chunk, &BlockRegistry::default());
+
+        let mesh = mesh_with_mode(&chunk, MeshingMode::Greedy);
+        assert!(mesh.face_count() > 6);
+        assert_same_surface(&chunk);
+    }
+
+    #[test]
+    fn faces_sample_their_own_atlas_tiles() {
+        let atlas = TextureAtlas::load("src/textures");
+        let mut registry = BlockRegistry::default();
+        registry.assign_tiles(&atlas);
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, registry.expect_id("grass"));
+        let mesh = build_chunk_mesh(&chunk, &registry, MeshingMode::Greedy, |_, _, _| {
+            (BlockId::AIR, Light::SKY)
+        });
+
+        let tile = |name: &str| atlas.tile(name).unwrap() as f32;
+        for vertex in mesh.vertices.chunks(CHUNK_VERTEX_SIZE) {
+            let expected = match vertex[4] {
+                1.0 => tile("grass_top"),
+                -1.0 => tile("dirt"),
+                _ => tile("grass_side"),
+            };
+            assert_eq!(vertex[15], expected);
+        }
+    }
+
+    /// Returns the occlusion of each corner of the quads facing up from `y`, by
+    /// corner position.
+    fn top_occlusion(mesh: &ChunkMeshData, y: f32) -> Vec<([f32; 3], f32)> {
+        mesh.vertices
+            .chunks(CHUNK_VERTEX_SIZE)
+            .filter(|vertex| vertex[4] == 1.0 && vertex[1] == y)
+            .map(|vertex| ([vertex[0], vertex[1], vertex[2]], vertex[14]))
+            .collect()
+    }
+
+    #[test]
+    fn lone_blocks_are_unoccluded() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 4, 4, id("stone"));
+        let mesh = mesh_with_air_outside(&chunk);
+        assert!(
+            mesh.vertices
+                .chunks(CHUNK_VERTEX_SIZE)
+                .all(|vertex| vertex[14] == 1.0)
+        );
+    }
+
+    #[test]
+    fn blocks_occlude_the_corners_they_touch() {
+        let mut chunk = empty_chunk();
+        for x in 0..8 {
+            for z in 0..8 {
+                chunk.set(x, 0, z, id("stone"));
+            }
+        }
+        // A wall along x = 4 and a single block diagonal to the corner (2, 1, 2)
+        for z in 0..8 {
+            chunk.set(4, 1, z, id("stone"));
+        }
+        chunk.set(1, 1, 1, id("stone"));
+
+        let occlusion = top_occlusion(&mesh_with_air_outside(&chunk), 1.0);
+        let at = |x: f32, z: f32| {
+            occlusion
+                .iter()
+                .filter(|(position, _)| position[0] == x && position[2] == z)
+                .map(|&(_, ambient)| ambient)
+                .collect::<Vec<_>>()
+        };
+        // Corners against the wall touch it beside and diagonally; the corner only
+        // diagonal to the single block touches it once
+        assert!(at(4.0, 6.0).iter().all(|&ambient| ambient == 1.0 / 3.0));
+        assert!(at(2.0, 2.0).iter().all(|&ambient| ambient == 2.0 / 3.0));
+        assert!(at(6.0, 6.0).iter().all(|&ambient| ambient == 1.0));
+    }
+
+    #[test]
+    fn corners_between_two_blocks_are_fully_occluded() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 0, 4, id("stone"));
+        chunk.set(3, 1, 4, id("stone"));
+        chunk.set(4, 1, 3, id("stone"));
+
+        let occlusion = top_occlusion(&mesh_with_air_outside(&chunk), 1.0);
+        let corner = occlusion
+            .iter()
+            .find(|(position, _)| position[0] == 4.0 && position[2] == 4.0)
+            .unwrap();
+        assert_eq!(corner.1, 0.0);
+    }
+
+    #[test]
+    fn quads_split_along_the_brighter_diagonal() {
+        let mut chunk = empty_chunk();
+        chunk.set(4, 0, 4, id("stone"));
+        chunk.set(3, 1, 3, id("stone"));
+        let mesh = mesh_with_air_outside(&chunk);
+
+        // The top face's only dark corner is (4, 4); it must not lie on the split
+        let top: Vec<_> = mesh
+            .vertices
+            .chunks(CHUNK_VERTEX_SIZE * VERTICES_PER_FACE)
+            .find(|quad| quad[4] == 1.0 && quad[1] == 1.0)
+            .unwrap()
+            .chunks(CHUNK_VERTEX_SIZE)
+            .map(|vertex| [vertex[0], vertex[2]])
+            .collect();
+        let dark = [4.0, 4.0];
+        assert_eq!(top.iter().filter(|&&corner| corner == dark).count(), 1);
+    }
+
+    #[test]
+    fn greedy_covers_generated_terrain() {
+        let config = GenerationConfig {
+            seed: 42,
+            ..GenerationConfig::default()
+        };
+        let registry = BlockRegistry::default();
+        let generator = TerrainGenerator::new(&config, &registry);
+        let mut chunk = generator.generate_chunk(ChunkPos::new(0, 0));
+        light_chunk(&mut chunk, &registry);
+        assert_same_surface(&chunk);
+    }
+
+    #[test]
+    fn greedy_covers_scattered_blocks() {
+        let mut chunk = empty_chunk();
+        let mut state: u32 = 12345;
+        for y in -16..16 {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    // Small LCG so the layout is random-looking but reproducible
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    chunk.set(x, y, z, BlockId(((state >> 16) % 4) as u16));
+                }
+            }
+        }
+        assert_same_surface(&chunk);
+    }
+}
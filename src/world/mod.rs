@@ -1,5 +1,18 @@
 pub mod block;
+pub mod chunk;
+pub mod collision;
 pub mod cube_render;
+pub mod entities;
 pub mod generation;
 pub mod init;
+pub mod light;
+pub mod liquid;
+pub mod mesher;
+pub mod raycast;
+pub mod registry;
+pub mod section;
+pub mod storage;
+pub mod ticks;
+pub mod workers;
+#[allow(clippy::module_inception)]
 pub mod world;
@@ -0,0 +1,177 @@
+use crate::world::block::Face;
+use cgmath::{InnerSpace, Point3, Vector3};
+
+/// The result of a successful voxel raycast.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit {
+    /// World coordinates of the block that was hit.
+    pub block: [i32; 3],
+    /// The face of the block the ray entered through.
+    pub face: Face,
+    /// Distance along the ray to the point of entry.
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// Returns the coordinates of the block in front of the hit face, where a new
+    /// block would be placed.
+    pub fn adjacent(&self) -> [i32; 3] {
+        let normal = self.face.normal();
+        [
+            self.block[0] + normal[0],
+            self.block[1] + normal[1],
+            self.block[2] + normal[2],
+        ]
+    }
+}
+
+/// Walks the voxel grid along a ray (Amanatides & Woo DDA) and returns the first
+/// solid block within `max_distance`.
+///
+/// The block containing `origin` itself is never reported.
+///
+/// # Arguments
+///
+/// * `origin` - The start of the ray.
+/// * `direction` - The direction of the ray; it doesn't need to be normalized.
+/// * `max_distance` - How far along the ray to search.
+/// * `is_solid` - Returns whether the block at the given world coordinates can be hit.
+///
+/// # Returns
+///
+/// The hit block and face, or `None` if nothing solid is within reach.
+pub fn raycast<F>(
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    max_distance: f32,
+    is_solid: F,
+) -> Option<RaycastHit>
+where
+    F: Fn(i32, i32, i32) -> bool,
+{
+    if direction.magnitude2() == 0.0 {
+        return None;
+    }
+    let direction = direction.normalize();
+
+    let origin = [origin.x, origin.y, origin.z];
+    let direction = [direction.x, direction.y, direction.z];
+    let mut block = origin.map(|v| v.floor() as i32);
+
+    // Per axis: which way we step, the distance to the first boundary, and the
+    // distance between consecutive boundaries
+    let mut step = [0; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            t_max[axis] = (block[axis] as f32 + 1.0 - origin[axis]) / direction[axis];
+            t_delta[axis] = 1.0 / direction[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            t_max[axis] = (origin[axis] - block[axis] as f32) / -direction[axis];
+            t_delta[axis] = -1.0 / direction[axis];
+        }
+    }
+
+    loop {
+        // Advance across whichever boundary is closest
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] { 0 } else { 2 }
+        } else if t_max[1] < t_max[2] {
+            1
+        } else {
+            2
+        };
+
+        let distance = t_max[axis];
+        if distance > max_distance {
+            return None;
+        }
+
+        block[axis] += step[axis];
+        if is_solid(block[0], block[1], block[2]) {
+            return Some(RaycastHit {
+                block,
+                face: Face::from_axis(axis, step[axis] < 0),
+                distance,
+            });
+        }
+
+        t_max[axis] += t_delta[axis];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_block_straight_ahead() {
+        let hit = raycast(
+            Point3::new(0.5, 0.5, 0.5),
+            Vector3::new(1.0, 0.0, 0.0),
+            10.0,
+            |x, y, z| (x, y, z) == (3, 0, 0),
+        )
+        .unwrap();
+
+        assert_eq!(hit.block, [3, 0, 0]);
+        assert_eq!(hit.face, Face::NegX);
+        assert_eq!(hit.adjacent(), [2, 0, 0]);
+        assert!((hit.distance - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reports_top_face_when_looking_down() {
+        let hit = raycast(
+            Point3::new(-4.5, 5.2, -7.5),
+            Vector3::new(0.0, -1.0, 0.0),
+            10.0,
+            |_, y, _| y < 0,
+        )
+        .unwrap();
+
+        assert_eq!(hit.block, [-5, -1, -8]);
+        assert_eq!(hit.face, Face::PosY);
+        assert_eq!(hit.adjacent(), [-5, 0, -8]);
+    }
+
+    #[test]
+    fn diagonal_ray_visits_blocks_in_order() {
+        // A wall at x = 2 is hit before the wall at x = 4
+        let hit = raycast(
+            Point3::new(0.5, 0.5, 0.5),
+            Vector3::new(1.0, 0.3, 0.2),
+            10.0,
+            |x, _, _| x == 2 || x == 4,
+        )
+        .unwrap();
+
+        assert_eq!(hit.block[0], 2);
+        assert_eq!(hit.face, Face::NegX);
+    }
+
+    #[test]
+    fn misses_beyond_max_distance() {
+        let hit = raycast(
+            Point3::new(0.5, 0.5, 0.5),
+            Vector3::new(0.0, 0.0, 1.0),
+            4.0,
+            |_, _, z| z == 6,
+        );
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn ignores_the_block_containing_the_origin() {
+        let hit = raycast(
+            Point3::new(0.5, 0.5, 0.5),
+            Vector3::new(0.0, 1.0, 0.0),
+            3.0,
+            |x, y, z| (x, y, z) == (0, 0, 0),
+        );
+        assert!(hit.is_none());
+    }
+}
@@ -0,0 +1,519 @@
+use crate::rendering::atlas::{BLANK_TILE, TextureAtlas};
+use crate::world::block::{BlockId, Face};
+use crate::world::liquid::{FULL_LIQUID, Liquid};
+use crate::world::ticks::RandomTick;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+/// The block definitions shipped with the game, used when the definitions file can't be loaded.
+const DEFAULT_BLOCKS: &str = include_str!("../blocks.toml");
+
+/// Describes the properties of a block type.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockDef {
+    /// Unique name of the block.
+    pub name: String,
+    /// RGBA color of the block, multiplied with its textures.
+    pub color: [f32; 4],
+    /// The atlas tiles drawn on the block's faces; untextured faces show just the color.
+    #[serde(default)]
+    pub textures: BlockTextures,
+    /// Whether the player collides with the block.
+    #[serde(default = "default_solid")]
+    pub solid: bool,
+    /// Whether faces behind the block stay visible.
+    #[serde(default)]
+    pub transparent: bool,
+    /// Whether the block is see-through, drawn blended over what's behind it
+    /// after all opaque blocks.
+    #[serde(default)]
+    pub translucent: bool,
+    /// Whether the block is a fluid. Each liquid is the still source of its
+    /// liquid; the registry adds a flowing block for every lower level.
+    #[serde(default)]
+    pub liquid: bool,
+    /// How many blocks a liquid spreads sideways from a source (1-7).
+    #[serde(default = "default_flow_distance")]
+    pub flow_distance: u8,
+    /// Ticks between a liquid's flow updates; higher values flow slower.
+    #[serde(default = "default_flow_delay")]
+    pub flow_delay: u32,
+    /// Whether a liquid fills gaps between two of its sources with a new source.
+    #[serde(default)]
+    pub renewable: bool,
+    /// Whether the block falls when there's nothing but air or liquid below it.
+    #[serde(default)]
+    pub falls: bool,
+    /// How hard the block is to break; negative values can't be broken.
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    /// Block light level emitted by the block (0-15).
+    #[serde(default)]
+    pub light_emission: u8,
+    /// What the block does when a random tick picks it.
+    #[serde(default)]
+    pub random_tick: RandomTick,
+}
+
+/// Names the texture atlas tiles drawn on each face of a block.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BlockTextures {
+    /// The tile of faces without a more specific one.
+    pub all: Option<String>,
+    /// The tile of the upward face.
+    pub top: Option<String>,
+    /// The tile of the four sideways faces.
+    pub side: Option<String>,
+    /// The tile of the downward face.
+    pub bottom: Option<String>,
+}
+
+impl BlockTextures {
+    /// Returns the name of the tile drawn on a face, if it has one.
+    pub fn name(&self, face: Face) -> Option<&str> {
+        let specific = match face {
+            Face::PosY => &self.top,
+            Face::NegY => &self.bottom,
+            _ => &self.side,
+        };
+        specific.as_ref().or(self.all.as_ref()).map(String::as_str)
+    }
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+fn default_hardness() -> f32 {
+    1.0
+}
+
+fn default_flow_distance() -> u8 {
+    7
+}
+
+fn default_flow_delay() -> u32 {
+    5
+}
+
+impl BlockDef {
+    fn air() -> Self {
+        BlockDef {
+            name: String::from("air"),
+            color: [0.0, 0.0, 0.0, 0.0],
+            textures: BlockTextures::default(),
+            solid: false,
+            transparent: true,
+            translucent: false,
+            liquid: false,
+            flow_distance: default_flow_distance(),
+            flow_delay: default_flow_delay(),
+            renewable: false,
+            falls: false,
+            hardness: 0.0,
+            light_emission: 0,
+            random_tick: RandomTick::None,
+        }
+    }
+
+    fn unknown() -> Self {
+        BlockDef {
+            name: String::from("unknown"),
+            color: [1.0, 0.0, 1.0, 1.0], // Magenta
+            textures: BlockTextures::default(),
+            solid: true,
+            transparent: false,
+            translucent: false,
+            liquid: false,
+            flow_distance: default_flow_distance(),
+            flow_delay: default_flow_delay(),
+            renewable: false,
+            falls: false,
+            hardness: 1.0,
+            light_emission: 0,
+            random_tick: RandomTick::None,
+        }
+    }
+}
+
+/// The layout of a block definitions file.
+#[derive(Deserialize)]
+struct BlockFile {
+    block: Vec<BlockDef>,
+}
+
+/// Holds every known block type and maps names to `BlockId`s.
+#[derive(Debug)]
+pub struct BlockRegistry {
+    defs: Vec<BlockDef>,
+    by_name: HashMap<String, BlockId>,
+    /// Returned for ids that aren't registered.
+    unknown: BlockDef,
+    /// The atlas tile of each face of each block, in `Face::ALL` order, once
+    /// assigned by `assign_tiles`.
+    tiles: Vec<[u32; 6]>,
+    /// The liquid state of each block, for liquids.
+    liquids: Vec<Option<Liquid>>,
+    /// The id of the level 1 flowing block of each liquid source; higher levels follow it.
+    flowing: HashMap<BlockId, u16>,
+}
+
+impl BlockRegistry {
+    /// Loads block definitions from the given TOML file, falling back to the
+    /// built-in definitions if it can't be read or parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the block definitions file.
+    ///
+    /// # Returns
+    ///
+    /// The loaded block registry.
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => match BlockRegistry::from_toml(&content) {
+                Ok(registry) => return registry,
+                Err(e) => {
+                    eprintln!("Error parsing block definitions {}: {}", path, e);
+                    eprintln!("Using built-in block definitions instead");
+                }
+            },
+            Err(e) => {
+                eprintln!("Error reading block definitions {}: {}", path, e);
+                eprintln!("Using built-in block definitions instead");
+            }
+        }
+
+        BlockRegistry::default()
+    }
+
+    /// Parses block definitions from a TOML string.
+    ///
+    /// Ids are assigned in definition order starting at 1, after air. The
+    /// flowing levels of each liquid follow, named `<liquid>_flowing_<level>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The TOML source, with one `[[block]]` table per block.
+    ///
+    /// # Returns
+    ///
+    /// The registry, or a description of why the definitions are invalid.
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        let file: BlockFile = toml::from_str(content).map_err(|e| e.to_string())?;
+
+        let mut registry = BlockRegistry {
+            defs: vec![BlockDef::air()],
+            by_name: HashMap::from([(String::from("air"), BlockId::AIR)]),
+            unknown: BlockDef::unknown(),
+            tiles: Vec::new(),
+            liquids: vec![None],
+            flowing: HashMap::new(),
+        };
+
+        for mut def in file.block {
+            def.flow_distance = def.flow_distance.clamp(1, FULL_LIQUID - 1);
+            let liquid = def.liquid;
+            let id = registry.register(def, None)?;
+            if liquid {
+                registry.liquids[id.0 as usize] = Some(Liquid {
+                    source: id,
+                    level: FULL_LIQUID,
+                    is_source: true,
+                });
+            }
+        }
+
+        let sources: Vec<BlockId> = registry
+            .liquids
+            .iter()
+            .flatten()
+            .map(|l| l.source)
+            .collect();
+        for source in sources {
+            let first = registry.defs.len() as u16;
+            registry.flowing.insert(source, first);
+            for level in 1..=FULL_LIQUID {
+                let mut def = registry.get(source).clone();
+                def.name = format!("{}_flowing_{}", def.name, level);
+                let liquid = Liquid {
+                    source,
+                    level,
+                    is_source: false,
+                };
+                registry.register(def, Some(liquid))?;
+            }
+        }
+
+        Ok(registry)
+    }
+
+    /// Adds a block definition under the next free id.
+    fn register(&mut self, def: BlockDef, liquid: Option<Liquid>) -> Result<BlockId, String> {
+        if self.by_name.contains_key(&def.name) {
+            return Err(format!("block `{}` is defined more than once", def.name));
+        }
+        let id = u16::try_from(self.defs.len())
+            .map_err(|_| String::from("too many block definitions"))?;
+        self.by_name.insert(def.name.clone(), BlockId(id));
+        self.defs.push(def);
+        self.liquids.push(liquid);
+        Ok(BlockId(id))
+    }
+
+    /// Returns the id of the block with the given name.
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Returns the id of the block with the given name, panicking if it isn't registered.
+    ///
+    /// Used for blocks the game can't work without, such as those world generation places.
+    pub fn expect_id(&self, name: &str) -> BlockId {
+        self.id(name)
+            .unwrap_or_else(|| panic!("block registry is missing `{}`", name))
+    }
+
+    /// Returns the names of all registered blocks in id order, starting with air.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.defs.iter().map(|def| def.name.as_str())
+    }
+
+    /// Returns the definition of a block; unregistered ids get a magenta placeholder.
+    pub fn get(&self, id: BlockId) -> &BlockDef {
+        self.defs.get(id.0 as usize).unwrap_or(&self.unknown)
+    }
+
+    /// Looks up the atlas tiles named by each block's textures, so `tile` can
+    /// return them.
+    ///
+    /// Textures missing from the atlas are reported and drawn with the blank tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `atlas` - The atlas the textures are drawn from.
+    pub fn assign_tiles(&mut self, atlas: &TextureAtlas) {
+        self.tiles = self
+            .defs
+            .iter()
+            .map(|def| {
+                Face::ALL.map(|face| {
+                    let Some(name) = def.textures.name(face) else {
+                        return BLANK_TILE;
+                    };
+                    atlas.tile(name).unwrap_or_else(|| {
+                        eprintln!("Block `{}` uses missing texture `{}`", def.name, name);
+                        BLANK_TILE
+                    })
+                })
+            })
+            .collect();
+    }
+
+    /// Returns the atlas tile drawn on a face of a block.
+    pub fn tile(&self, id: BlockId, face: Face) -> u32 {
+        let index = Face::ALL.iter().position(|&f| f == face).unwrap();
+        self.tiles
+            .get(id.0 as usize)
+            .map_or(BLANK_TILE, |tiles| tiles[index])
+    }
+
+    /// Returns whether the player collides with the block.
+    pub fn is_solid(&self, id: BlockId) -> bool {
+        self.get(id).solid
+    }
+
+    /// Returns whether the block completely hides the faces of blocks behind it.
+    pub fn is_opaque(&self, id: BlockId) -> bool {
+        !self.get(id).transparent
+    }
+
+    /// Returns the liquid state of a block, if it's a liquid.
+    pub fn liquid(&self, id: BlockId) -> Option<Liquid> {
+        self.liquids.get(id.0 as usize).copied().flatten()
+    }
+
+    /// Returns the flowing block of a liquid at the given level.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The liquid's source block.
+    /// * `level` - How full the block is, from 1 to `FULL_LIQUID`.
+    pub fn flowing(&self, source: BlockId, level: u8) -> BlockId {
+        let first = self.flowing[&source];
+        BlockId(first + level.clamp(1, FULL_LIQUID) as u16 - 1)
+    }
+
+    /// Returns whether the block is drawn in the blended translucent pass.
+    pub fn is_translucent(&self, id: BlockId) -> bool {
+        self.get(id).translucent
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        BlockRegistry::from_toml(DEFAULT_BLOCKS).expect("Built-in block definitions are invalid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_definitions_parse() {
+        let registry = BlockRegistry::default();
+        let stone = registry.expect_id("stone");
+
+        assert!(registry.is_solid(stone));
+        assert!(registry.is_opaque(stone));
+        assert_eq!(registry.get(stone).name, "stone");
+
+        let water = registry.expect_id("water");
+        assert!(!registry.is_solid(water));
+        assert!(registry.get(water).liquid);
+        assert!(registry.is_translucent(water));
+
+        let glass = registry.expect_id("glass");
+        assert!(registry.is_solid(glass));
+        assert!(!registry.is_opaque(glass));
+        assert!(registry.is_translucent(glass));
+        assert!(!registry.is_translucent(stone));
+    }
+
+    #[test]
+    fn air_is_reserved() {
+        let registry = BlockRegistry::from_toml("block = []").unwrap();
+        assert_eq!(registry.id("air"), Some(BlockId::AIR));
+        assert!(!registry.is_solid(BlockId::AIR));
+        assert!(!registry.is_opaque(BlockId::AIR));
+    }
+
+    #[test]
+    fn ids_follow_definition_order_and_defaults_apply() {
+        let registry = BlockRegistry::from_toml(
+            r#"
+            [[block]]
+            name = "a"
+            color = [1.0, 1.0, 1.0, 1.0]
+
+            [[block]]
+            name = "b"
+            color = [0.0, 0.0, 0.0, 1.0]
+            light_emission = 14
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(registry.id("a"), Some(BlockId(1)));
+        assert_eq!(registry.id("b"), Some(BlockId(2)));
+        assert!(registry.is_solid(BlockId(1)));
+        assert_eq!(registry.get(BlockId(1)).hardness, 1.0);
+        assert_eq!(registry.get(BlockId(2)).light_emission, 14);
+    }
+
+    #[test]
+    fn liquids_get_a_flowing_block_per_level() {
+        let registry = BlockRegistry::default();
+        let water = registry.expect_id("water");
+
+        assert_eq!(
+            registry.liquid(water),
+            Some(Liquid {
+                source: water,
+                level: FULL_LIQUID,
+                is_source: true,
+            })
+        );
+        for level in 1..=FULL_LIQUID {
+            let flowing = registry.flowing(water, level);
+            assert_eq!(
+                registry.get(flowing).name,
+                format!("water_flowing_{}", level)
+            );
+            assert_eq!(
+                registry.liquid(flowing),
+                Some(Liquid {
+                    source: water,
+                    level,
+                    is_source: false,
+                })
+            );
+            assert!(registry.get(flowing).liquid);
+            assert!(!registry.is_solid(flowing));
+        }
+        assert_eq!(registry.liquid(registry.expect_id("stone")), None);
+        assert_eq!(registry.liquid(BlockId(999)), None);
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let result = BlockRegistry::from_toml(
+            r#"
+            [[block]]
+            name = "air"
+            color = [1.0, 1.0, 1.0, 1.0]
+            "#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn faces_fall_back_to_the_all_texture() {
+        let tile = |name: &str| (String::from(name), vec![0; 16 * 16 * 4]);
+        let atlas = TextureAtlas::from_tiles(vec![tile("dirt"), tile("grass_top")]);
+        let mut registry = BlockRegistry::from_toml(
+            r#"
+            [[block]]
+            name = "grass"
+            color = [1.0, 1.0, 1.0, 1.0]
+            textures = { all = "dirt", top = "grass_top", side = "missing" }
+
+            [[block]]
+            name = "plain"
+            color = [1.0, 1.0, 1.0, 1.0]
+            "#,
+        )
+        .unwrap();
+        let (grass, plain) = (registry.expect_id("grass"), registry.expect_id("plain"));
+
+        // Tiles are blank until assigned
+        assert_eq!(registry.tile(grass, Face::PosY), BLANK_TILE);
+
+        registry.assign_tiles(&atlas);
+        assert_eq!(
+            registry.tile(grass, Face::PosY),
+            atlas.tile("grass_top").unwrap()
+        );
+        assert_eq!(
+            registry.tile(grass, Face::NegY),
+            atlas.tile("dirt").unwrap()
+        );
+        assert_eq!(registry.tile(grass, Face::NegX), BLANK_TILE);
+        assert_eq!(registry.tile(plain, Face::PosZ), BLANK_TILE);
+        assert_eq!(registry.tile(BlockId(999), Face::PosY), BLANK_TILE);
+    }
+
+    #[test]
+    fn built_in_textures_exist() {
+        let atlas = TextureAtlas::load("src/textures");
+        let registry = BlockRegistry::default();
+        for def in registry
+            .names()
+            .map(|name| registry.get(registry.expect_id(name)))
+        {
+            for face in Face::ALL {
+                if let Some(name) = def.textures.name(face) {
+                    assert!(atlas.tile(name).is_some(), "missing texture `{}`", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_ids_are_magenta_placeholders() {
+        let registry = BlockRegistry::default();
+        assert_eq!(registry.get(BlockId(999)).color, [1.0, 0.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::world::block::BlockId;
+
+/// The width, height and depth of a section in blocks.
+pub const SECTION_SIZE: usize = 16;
+
+/// The number of blocks stored in a single section.
+pub const SECTION_VOLUME: usize = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+
+/// The widest index a section needs: one per possible block id.
+const MAX_BITS: u32 = 16;
+
+/// A cube of blocks stored as indices into a palette of the block types it contains.
+///
+/// Indices are packed into `u64` words using the smallest power-of-two width the
+/// palette needs, so they never straddle word boundaries and can be located with
+/// shifts alone. A section holding a single block type,
+/// such as open sky or solid stone, stores no indices at all.
+///
+/// Palette entries aren't removed when the last block using them is replaced;
+/// they are dropped when the palette has to grow, or by `compact`.
+#[derive(Clone, Debug)]
+pub struct Section {
+    palette: Vec<BlockId>,
+    /// Bits per index; `0` means every block is `palette[0]`.
+    bits: u32,
+    words: Vec<u64>,
+    /// The number of blocks that aren't air, so empty sections are cheap to spot.
+    non_air: u16,
+}
+
+impl Section {
+    /// Creates a section filled with the given block.
+    pub fn filled(block: BlockId) -> Self {
+        Section {
+            palette: vec![block],
+            bits: 0,
+            words: Vec::new(),
+            non_air: if block.is_air() {
+                0
+            } else {
+                SECTION_VOLUME as u16
+            },
+        }
+    }
+
+    /// Creates a section holding the given blocks, using the smallest palette that fits.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - Every block of the section, in `Section::index` order.
+    pub fn from_blocks(blocks: &[BlockId; SECTION_VOLUME]) -> Self {
+        let mut palette: Vec<BlockId> = Vec::new();
+        let indices: Vec<usize> = blocks
+            .iter()
+            .map(
+                |block| match palette.iter().position(|entry| entry == block) {
+                    Some(index) => index,
+                    None => {
+                        palette.push(*block);
+                        palette.len() - 1
+                    }
+                },
+            )
+            .collect();
+
+        if palette.len() == 1 {
+            return Section::filled(palette[0]);
+        }
+
+        let bits = bits_for(palette.len());
+        let mut section = Section {
+            words: vec![0; SECTION_VOLUME.div_ceil(values_per_word(bits))],
+            palette,
+            bits,
+            non_air: blocks.iter().filter(|block| !block.is_air()).count() as u16,
+        };
+        for (index, palette_index) in indices.into_iter().enumerate() {
+            section.write_index(index, palette_index);
+        }
+        section
+    }
+
+    /// Returns the position of a block in the section's storage order.
+    pub fn index(x: usize, y: usize, z: usize) -> usize {
+        (y * SECTION_SIZE + z) * SECTION_SIZE + x
+    }
+
+    /// Returns the block at the given index.
+    pub fn get(&self, index: usize) -> BlockId {
+        if self.bits == 0 {
+            return self.palette[0];
+        }
+        self.palette[self.read_index(index)]
+    }
+
+    /// Sets the block at the given index, widening the indices if the palette is full.
+    pub fn set(&mut self, index: usize, block: BlockId) {
+        let previous = self.get(index);
+        if previous == block {
+            return;
+        }
+        if previous.is_air() {
+            self.non_air += 1;
+        } else if block.is_air() {
+            self.non_air -= 1;
+        }
+
+        let palette_index = match self.palette.iter().position(|entry| *entry == block) {
+            Some(palette_index) => palette_index,
+            None if self.palette.len() < 1 << self.bits => {
+                self.palette.push(block);
+                self.palette.len() - 1
+            }
+            None => {
+                // Rebuilding drops unused entries, so the palette only widens if it must
+                let mut blocks = self.blocks();
+                blocks[index] = block;
+                *self = Section::from_blocks(&blocks);
+                return;
+            }
+        };
+        self.write_index(index, palette_index);
+    }
+
+    /// Returns `true` if every block of the section is air.
+    pub fn is_empty(&self) -> bool {
+        self.non_air == 0
+    }
+
+    /// Replaces every block with the result of `f`, e.g. to renumber block ids.
+    pub fn map_blocks<F: Fn(BlockId) -> BlockId>(&mut self, f: F) {
+        for entry in &mut self.palette {
+            *entry = f(*entry);
+        }
+        self.non_air = self.count_non_air();
+    }
+
+    /// Drops unused palette entries and narrows the indices as far as possible.
+    pub fn compact(&mut self) {
+        if self.bits > 0 {
+            *self = Section::from_blocks(&self.blocks());
+        }
+    }
+
+    /// Returns every block of the section, in `Section::index` order.
+    pub fn blocks(&self) -> Box<[BlockId; SECTION_VOLUME]> {
+        let mut blocks = Box::new([self.palette[0]; SECTION_VOLUME]);
+        if self.bits > 0 {
+            for (index, block) in blocks.iter_mut().enumerate() {
+                *block = self.palette[self.read_index(index)];
+            }
+        }
+        blocks
+    }
+
+    /// Returns the number of bytes the section uses on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.palette.capacity() * size_of::<BlockId>() + self.words.capacity() * size_of::<u64>()
+    }
+
+    /// Appends the section to `out`: the palette length and ids as little-endian
+    /// `u16`s, then for sections with more than one block type the index width
+    /// and the packed index words as little-endian `u64`s.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.palette.len() as u16).to_le_bytes());
+        for entry in &self.palette {
+            out.extend_from_slice(&entry.0.to_le_bytes());
+        }
+        if self.bits > 0 {
+            out.push(self.bits as u8);
+            for word in &self.words {
+                out.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+
+    /// Reads a section written by `write` from the start of `bytes`, advancing past it.
+    ///
+    /// # Returns
+    ///
+    /// The section, or `None` if the data is truncated or malformed.
+    pub fn read(bytes: &mut &[u8]) -> Option<Self> {
+        let palette_len = u16::from_le_bytes(take(bytes)?) as usize;
+        let palette = (0..palette_len)
+            .map(|_| take(bytes).map(|id| BlockId(u16::from_le_bytes(id))))
+            .collect::<Option<Vec<_>>>()?;
+        match palette.len() {
+            0 => return None,
+            1 => return Some(Section::filled(palette[0])),
+            _ => {}
+        }
+
+        let [bits] = take(bytes)?;
+        let bits = bits as u32;
+        if !bits.is_power_of_two() || bits > MAX_BITS || palette.len() > 1 << bits {
+            return None;
+        }
+        let words = (0..SECTION_VOLUME.div_ceil(values_per_word(bits)))
+            .map(|_| take(bytes).map(u64::from_le_bytes))
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut section = Section {
+            palette,
+            bits,
+            words,
+            non_air: 0,
+        };
+        if !(0..SECTION_VOLUME).all(|index| section.read_index(index) < section.palette.len()) {
+            return None;
+        }
+        section.non_air = section.count_non_air();
+        Some(section)
+    }
+
+    fn count_non_air(&self) -> u16 {
+        match self.bits {
+            0 if self.palette[0].is_air() => 0,
+            0 => SECTION_VOLUME as u16,
+            _ => self.blocks().iter().filter(|block| !block.is_air()).count() as u16,
+        }
+    }
+
+    /// Returns the word holding an index and the index's offset in bits within it.
+    fn locate(&self, index: usize) -> (usize, u32) {
+        let bits_log = self.bits.trailing_zeros();
+        let per_word_log = u64::BITS.trailing_zeros() - bits_log;
+        let offset = (index & ((1 << per_word_log) - 1)) as u32;
+        (index >> per_word_log, offset << bits_log)
+    }
+
+    fn read_index(&self, index: usize) -> usize {
+        let (word, shift) = self.locate(index);
+        let mask = (1u64 << self.bits) - 1;
+        ((self.words[word] >> shift) & mask) as usize
+    }
+
+    fn write_index(&mut self, index: usize, palette_index: usize) {
+        if self.bits == 0 {
+            return;
+        }
+        let (word, shift) = self.locate(index);
+        let mask = ((1u64 << self.bits) - 1) << shift;
+        let word = &mut self.words[word];
+        *word = (*word & !mask) | ((palette_index as u64) << shift);
+    }
+}
+
+impl Default for Section {
+    fn default() -> Self {
+        Section::filled(BlockId::AIR)
+    }
+}
+
+/// Returns the index width needed for a palette of the given length.
+fn bits_for(palette_len: usize) -> u32 {
+    (usize::BITS - (palette_len - 1).leading_zeros())
+        .next_power_of_two()
+        .min(MAX_BITS)
+}
+
+fn values_per_word(bits: u32) -> usize {
+    (u64::BITS / bits) as usize
+}
+
+/// Splits the first `N` bytes off `bytes`, if there are that many.
+fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+    let (value, rest) = bytes.split_first_chunk::<N>()?;
+    *bytes = rest;
+    Some(*value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_block_sections_store_no_indices() {
+        let mut section = Section::filled(BlockId(3));
+        assert_eq!(section.get(100), BlockId(3));
+        assert_eq!(section.heap_size(), size_of::<BlockId>());
+
+        section.set(100, BlockId(3));
+        assert_eq!(section.bits, 0);
+        assert!(!section.is_empty());
+    }
+
+    #[test]
+    fn indices_widen_as_the_palette_grows() {
+        let mut section = Section::default();
+        for id in 0..100u16 {
+            section.set(id as usize * 13, BlockId(id));
+        }
+
+        assert_eq!(section.bits, 8);
+        for id in 0..100u16 {
+            assert_eq!(section.get(id as usize * 13), BlockId(id));
+        }
+        assert_eq!(section.get(1), BlockId::AIR);
+    }
+
+    #[test]
+    fn unused_entries_are_dropped_before_widening() {
+        let mut section = Section::default();
+        section.set(0, BlockId(1));
+        // Replaces the only block of type 1, so its entry can make room for type 2
+        section.set(0, BlockId(2));
+
+        assert_eq!(section.bits, 1);
+        assert_eq!(section.get(0), BlockId(2));
+        assert_eq!(section.get(1), BlockId::AIR);
+
+        section.set(0, BlockId::AIR);
+        assert!(section.is_empty());
+        section.compact();
+        assert_eq!(section.bits, 0);
+    }
+
+    #[test]
+    fn sections_round_trip_through_bytes() {
+        let mut section = Section::default();
+        for index in (0..SECTION_VOLUME).step_by(7) {
+            section.set(index, BlockId((index % 5) as u16 + 1));
+        }
+
+        let mut bytes = Vec::new();
+        section.write(&mut bytes);
+        Section::filled(BlockId(9)).write(&mut bytes);
+
+        let mut rest = bytes.as_slice();
+        let read = Section::read(&mut rest).unwrap();
+        assert!(read.blocks() == section.blocks());
+        assert_eq!(Section::read(&mut rest).unwrap().get(17), BlockId(9));
+        assert!(rest.is_empty());
+        assert!(Section::read(&mut &bytes[..100]).is_none());
+    }
+}
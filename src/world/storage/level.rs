@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// The name of the level file inside a save directory.
+pub const LEVEL_FILE: &str = "level.toml";
+
+/// The current version of the level format.
+pub const LEVEL_FORMAT_VERSION: u32 = 1;
+
+/// Where the player was and what they were doing when the world was saved.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerState {
+    /// Camera (eye) position.
+    pub position: [f32; 3],
+    /// Horizontal rotation in degrees.
+    pub yaw: f32,
+    /// Vertical rotation in degrees.
+    pub pitch: f32,
+    pub flying: bool,
+}
+
+/// Metadata describing a saved world, stored in `LEVEL_FILE` next to its regions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub format_version: u32,
+    /// Seed the world was generated with; overrides the configured seed.
+    pub seed: u64,
+    /// Lowest block height of the world; overrides the configured height.
+    pub min_y: i32,
+    /// Height above the world's highest block; overrides the configured height.
+    pub max_y: i32,
+    /// Seconds the world has been played for.
+    pub game_time: f64,
+    pub player: PlayerState,
+    /// Block names in id order when the world was saved, so chunks can be
+    /// renumbered if the block definitions change.
+    pub blocks: Vec<String>,
+}
+
+impl Level {
+    /// Loads the level file at the given path.
+    ///
+    /// # Returns
+    ///
+    /// The level, `None` if there is no level file, or an error if it can't be
+    /// read, parsed, or is of another format version.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let level = toml::from_str::<Level>(&content)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        if level.format_version != LEVEL_FORMAT_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported level format version {}", level.format_version),
+            ));
+        }
+        Ok(Some(level))
+    }
+
+    /// Writes the level file to the given path, replacing it atomically so a
+    /// crash mid-write doesn't lose the previous version.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, content)?;
+        fs::rename(&temp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::storage::temp_save_dir;
+
+    fn level() -> Level {
+        Level {
+            format_version: LEVEL_FORMAT_VERSION,
+            seed: 123,
+            min_y: -64,
+            max_y: 320,
+            game_time: 42.5,
+            player: PlayerState {
+                position: [1.5, 20.0, -3.25],
+                yaw: -90.0,
+                pitch: 12.0,
+                flying: true,
+            },
+            blocks: vec![String::from("air"), String::from("dirt")],
+        }
+    }
+
+    #[test]
+    fn level_round_trips_through_toml() {
+        let content = toml::to_string_pretty(&level()).unwrap();
+        assert_eq!(toml::from_str::<Level>(&content).unwrap(), level());
+    }
+
+    #[test]
+    fn unreadable_levels_are_errors() {
+        let dir = temp_save_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LEVEL_FILE);
+        assert!(Level::load(&path).unwrap().is_none());
+
+        level().save(&path).unwrap();
+        assert_eq!(Level::load(&path).unwrap(), Some(level()));
+
+        fs::write(&path, "seed = ").unwrap();
+        assert!(Level::load(&path).is_err());
+
+        // The world's height can't be guessed
+        let content = toml::to_string_pretty(&level()).unwrap();
+        let without_height: String = content
+            .lines()
+            .filter(|line| !line.starts_with("min_y"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        fs::write(&path, without_height).unwrap();
+        assert!(Level::load(&path).is_err());
+
+        let newer = Level {
+            format_version: LEVEL_FORMAT_VERSION + 1,
+            ..level()
+        };
+        newer.save(&path).unwrap();
+        assert!(Level::load(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,451 @@
+pub mod level;
+pub mod region;
+
+use crate::world::block::BlockId;
+use crate::world::chunk::{Chunk, ChunkPos, WorldHeight};
+use crate::world::generation::decoration::{PendingPlacements, Placement};
+use crate::world::registry::BlockRegistry;
+use crate::world::storage::region::{Region, RegionPos};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The number of bytes each pending placement takes on disk.
+const PLACEMENT_LEN: usize = 16;
+
+/// Reads and writes the blocks of a saved world: its chunks in region files under
+/// `region/` and the decorations still waiting for their chunks in `pending.bin`.
+/// The world's metadata is kept separately in a `Level`.
+///
+/// Regions are cached in memory once read; changes are written by `flush`.
+pub struct WorldStorage {
+    dir: PathBuf,
+    height: WorldHeight,
+    regions: HashMap<RegionPos, Region>,
+    /// Regions changed since they were last written.
+    dirty: HashSet<RegionPos>,
+}
+
+impl WorldStorage {
+    /// Opens the world saved in the given directory, creating the directory if needed.
+    ///
+    /// If the block definitions changed since the world was saved, every region
+    /// and the pending decorations are renumbered to the current ids and written
+    /// back before this returns, so the caller must record the current block
+    /// names straight away.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the world is saved in.
+    /// * `height` - The height of the world's chunks.
+    /// * `saved_blocks` - The block names in id order when the world was saved,
+    ///   or `None` for a new world.
+    /// * `registry` - The current block definitions, used to renumber saved blocks.
+    ///
+    /// # Returns
+    ///
+    /// The world storage, or the error that stopped the save from being created
+    /// or renumbered.
+    pub fn open(
+        dir: &Path,
+        height: WorldHeight,
+        saved_blocks: Option<&[String]>,
+        registry: &BlockRegistry,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir.join("region"))?;
+
+        let remap: Vec<BlockId> = saved_blocks
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| {
+                        registry.id(name).unwrap_or_else(|| {
+                            eprintln!("Saved block `{}` is no longer defined", name);
+                            BlockId(u16::MAX)
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|remap| {
+                // Ids that already match don't need renumbering
+                !remap
+                    .iter()
+                    .enumerate()
+                    .all(|(id, block)| block.0 as usize == id)
+            })
+            .unwrap_or_default();
+
+        let storage = WorldStorage {
+            dir: dir.to_path_buf(),
+            height,
+            regions: HashMap::new(),
+            dirty: HashSet::new(),
+        };
+        if !remap.is_empty() {
+            storage.renumber_blocks(&remap)?;
+        }
+        Ok(storage)
+    }
+
+    /// Rewrites every saved region and the pending decorations with their blocks
+    /// renumbered through `remap`, which maps saved ids to current ones.
+    ///
+    /// Regions are renumbered all at once rather than as they're visited, since
+    /// the block names saved next describe every region. A region that can't be
+    /// read, or holds a chunk that can't be decoded, is moved aside: its ids
+    /// can't be renumbered, and would mean other blocks once the names are saved.
+    fn renumber_blocks(&self, remap: &[BlockId]) -> io::Result<()> {
+        let renumber = |block: BlockId| remap.get(block.0 as usize).copied().unwrap_or(block);
+
+        for entry in fs::read_dir(self.dir.join("region"))? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "bin") {
+                continue;
+            }
+            let renumbered = Region::read(&path).and_then(|mut region| {
+                let any = region.map_chunks(self.height, |chunk| chunk.map_blocks(renumber))?;
+                Ok(any.then_some(region))
+            });
+            match renumbered {
+                Ok(Some(region)) => region.write(&path)?,
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Error renumbering region {}: {}", path.display(), e);
+                    let moved = set_aside(&path, "corrupt")?;
+                    eprintln!("Moved it to {}", moved.display());
+                }
+            }
+        }
+
+        let mut pending = PendingPlacements::default();
+        for placement in self.load_pending().iter() {
+            pending.push(Placement {
+                block: renumber(placement.block),
+                replaces: renumber(placement.replaces),
+                ..*placement
+            });
+        }
+        self.save_pending(&pending)
+    }
+
+    /// Returns the saved chunk at the given position, if there is one.
+    ///
+    /// Chunks that can't be read are reported and treated as never saved.
+    pub fn load_chunk(&mut self, pos: ChunkPos) -> Option<Chunk> {
+        let height = self.height;
+        match self
+            .region(RegionPos::from_chunk(pos))?
+            .load_chunk(pos, height)
+        {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                eprintln!("Error loading chunk ({}, {}): {}", pos.x, pos.z, e);
+                None
+            }
+        }
+    }
+
+    /// Stores a chunk, to be written to disk by the next `flush`.
+    ///
+    /// Chunks of a region whose file can't be read or moved aside aren't stored,
+    /// so the chunks already in that file aren't overwritten.
+    pub fn save_chunk(&mut self, pos: ChunkPos, chunk: &Chunk) {
+        let region_pos = RegionPos::from_chunk(pos);
+        let Some(region) = self.region(region_pos) else {
+            eprintln!(
+                "Not saving chunk ({}, {}): its region can't be read",
+                pos.x, pos.z
+            );
+            return;
+        };
+        region.store_chunk(pos, chunk);
+        self.dirty.insert(region_pos);
+    }
+
+    /// Writes every changed region to disk.
+    pub fn flush(&mut self) -> io::Result<()> {
+        for region_pos in std::mem::take(&mut self.dirty) {
+            let path = self.region_path(region_pos);
+            if let Err(e) = self.regions[&region_pos].write(&path) {
+                // Keep the region dirty so the next flush tries again
+                self.dirty.insert(region_pos);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the decorations that were waiting for their chunks when the world was saved.
+    pub fn load_pending(&self) -> PendingPlacements {
+        let mut pending = PendingPlacements::default();
+        let path = self.dir.join("pending.bin");
+        let bytes = match fs::read(&path) {
+            Ok(compressed) => {
+                let mut bytes = Vec::new();
+                match ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut bytes) {
+                    Ok(_) => bytes,
+                    Err(e) => {
+                        eprintln!("Error reading {}: {}", path.display(), e);
+                        return pending;
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => return pending,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                return pending;
+            }
+        };
+
+        for record in bytes.chunks_exact(PLACEMENT_LEN) {
+            let i32_at = |at: usize| i32::from_le_bytes(record[at..at + 4].try_into().unwrap());
+            let id_at = |at: usize| BlockId(u16::from_le_bytes([record[at], record[at + 1]]));
+            pending.push(Placement {
+                pos: [i32_at(0), i32_at(4), i32_at(8)],
+                block: id_at(12),
+                replaces: id_at(14),
+            });
+        }
+        pending
+    }
+
+    /// Writes the decorations still waiting for their chunks, replacing the file
+    /// atomically so a crash mid-write doesn't lose the previous version.
+    pub fn save_pending(&self, pending: &PendingPlacements) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        for placement in pending.iter() {
+            for coordinate in placement.pos {
+                encoder.write_all(&coordinate.to_le_bytes())?;
+            }
+            encoder.write_all(&placement.block.0.to_le_bytes())?;
+            encoder.write_all(&placement.replaces.0.to_le_bytes())?;
+        }
+        let path = self.dir.join("pending.bin");
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, encoder.finish()?)?;
+        fs::rename(&temp, path)
+    }
+
+    fn region_path(&self, pos: RegionPos) -> PathBuf {
+        self.dir.join("region").join(pos.file_name())
+    }
+
+    /// Returns the cached region, reading it from disk the first time.
+    ///
+    /// A region file that can't be read is moved aside, so the region starts
+    /// over in a new file rather than overwriting the chunks it might still hold.
+    /// If it can't be moved either, nothing is cached and `None` is returned.
+    fn region(&mut self, pos: RegionPos) -> Option<&mut Region> {
+        if !self.regions.contains_key(&pos) {
+            let path = self.region_path(pos);
+            let region = match Region::read(&path) {
+                Ok(region) => region,
+                Err(e) => {
+                    eprintln!("Error reading region {}: {}", path.display(), e);
+                    match set_aside(&path, "corrupt") {
+                        Ok(moved) => eprintln!("Moved it to {}", moved.display()),
+                        Err(e) => {
+                            eprintln!("Error moving {} aside: {}", path.display(), e);
+                            return None;
+                        }
+                    }
+                    Region::new()
+                }
+            };
+            self.regions.insert(pos, region);
+        }
+        self.regions.get_mut(&pos)
+    }
+}
+
+/// Renames a file or directory out of the way, keeping it for recovery.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to move.
+/// * `suffix` - Appended to its name, e.g. `corrupt` moves `r.0.0.bin` to
+///   `r.0.0.bin.corrupt`, or to `r.0.0.bin.corrupt-1` and so on if that's taken.
+///
+/// # Returns
+///
+/// Where it was moved to.
+pub fn set_aside(path: &Path, suffix: &str) -> io::Result<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut target = path.with_file_name(format!("{}.{}", name, suffix));
+    let mut attempt = 1;
+    while target.exists() {
+        target = path.with_file_name(format!("{}.{}-{}", name, suffix, attempt));
+        attempt += 1;
+    }
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Returns an empty directory for a test to save a world into.
+#[cfg(test)]
+pub fn temp_save_dir() -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "cubix-test-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn height() -> WorldHeight {
+        WorldHeight::new(-16, 32)
+    }
+
+    #[test]
+    fn chunks_persist_across_sessions() {
+        let dir = temp_save_dir();
+        let registry = BlockRegistry::default();
+        let pos = ChunkPos::new(-40, 7);
+        let mut chunk = Chunk::new(height());
+        chunk.set(3, 4, 5, BlockId(2));
+
+        let mut storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        assert!(storage.load_chunk(pos).is_none());
+        storage.save_chunk(pos, &chunk);
+        storage.flush().unwrap();
+
+        let mut storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        let loaded = storage.load_chunk(pos).unwrap();
+        assert_eq!(loaded.get(3, 4, 5), BlockId(2));
+        assert!(storage.load_chunk(ChunkPos::new(-40, 8)).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_blocks_are_renumbered() {
+        let dir = temp_save_dir();
+        let registry = BlockRegistry::default();
+        let pos = ChunkPos::new(0, 0);
+        let mut chunk = Chunk::new(height());
+        chunk.set(0, 0, 0, BlockId(1));
+
+        let mut storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        storage.save_chunk(pos, &chunk);
+        storage.flush().unwrap();
+
+        // Saved with a block list where id 1 was stone
+        let saved = [String::from("air"), String::from("stone")];
+        let mut storage = WorldStorage::open(&dir, height(), Some(&saved), &registry).unwrap();
+        let loaded = storage.load_chunk(pos).unwrap();
+        assert_eq!(loaded.get(0, 0, 0), registry.expect_id("stone"));
+        assert_eq!(loaded.get(1, 0, 0), BlockId::AIR);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn regions_not_visited_are_renumbered_too() {
+        let dir = temp_save_dir();
+        let registry = BlockRegistry::default();
+        let (visited, unvisited) = (ChunkPos::new(0, 0), ChunkPos::new(100, -70));
+        let mut chunk = Chunk::new(height());
+        chunk.set(0, 0, 0, BlockId(1));
+
+        let mut storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        storage.save_chunk(visited, &chunk);
+        storage.save_chunk(unvisited, &chunk);
+        storage.flush().unwrap();
+
+        // A session with a block list where id 1 was stone, that only visits
+        // one region and then saves the current block list
+        let saved = [String::from("air"), String::from("stone")];
+        let mut storage = WorldStorage::open(&dir, height(), Some(&saved), &registry).unwrap();
+        storage.load_chunk(visited).unwrap();
+        storage.flush().unwrap();
+
+        let current: Vec<String> = registry.names().map(String::from).collect();
+        let mut storage = WorldStorage::open(&dir, height(), Some(&current), &registry).unwrap();
+        let stone = registry.expect_id("stone");
+        assert_eq!(storage.load_chunk(visited).unwrap().get(0, 0, 0), stone);
+        assert_eq!(storage.load_chunk(unvisited).unwrap().get(0, 0, 0), stone);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_regions_are_kept_aside() {
+        let dir = temp_save_dir();
+        let registry = BlockRegistry::default();
+        let pos = ChunkPos::new(3, 3);
+        let mut storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        let path = storage.region_path(RegionPos::from_chunk(pos));
+        fs::write(&path, b"damaged").unwrap();
+
+        assert!(storage.load_chunk(pos).is_none());
+        storage.save_chunk(pos, &Chunk::new(height()));
+        storage.flush().unwrap();
+
+        // The damaged file is still there to recover chunks from
+        let corrupt = path.with_file_name("r.0.0.bin.corrupt");
+        assert_eq!(fs::read(&corrupt).unwrap(), b"damaged");
+        let mut storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        assert!(storage.load_chunk(pos).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn regions_with_undecodable_chunks_are_kept_aside() {
+        let dir = temp_save_dir();
+        let registry = BlockRegistry::default();
+        let (good, bad) = (ChunkPos::new(0, 0), ChunkPos::new(1, 0));
+        let storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        let path = storage.region_path(RegionPos::from_chunk(good));
+
+        // One chunk saved with fewer sections than the world has
+        let mut region = Region::new();
+        region.store_chunk(good, &Chunk::new(height()));
+        region.store_chunk(bad, &Chunk::new(WorldHeight::new(0, 16)));
+        region.write(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        let saved = [String::from("air"), String::from("stone")];
+        let mut storage = WorldStorage::open(&dir, height(), Some(&saved), &registry).unwrap();
+        assert!(storage.load_chunk(good).is_none());
+        let corrupt = path.with_file_name("r.0.0.bin.corrupt");
+        assert_eq!(fs::read(&corrupt).unwrap(), bytes);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pending_placements_persist() {
+        let dir = temp_save_dir();
+        let registry = BlockRegistry::default();
+        let placement = Placement {
+            pos: [-17, 9, 40],
+            block: BlockId(4),
+            replaces: BlockId(12),
+        };
+
+        let storage = WorldStorage::open(&dir, height(), None, &registry).unwrap();
+        let mut pending = PendingPlacements::default();
+        pending.push(placement);
+        storage.save_pending(&pending).unwrap();
+
+        let mut loaded = WorldStorage::open(&dir, height(), None, &registry)
+            .unwrap()
+            .load_pending();
+        assert_eq!(loaded.take(placement.chunk()), vec![placement]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
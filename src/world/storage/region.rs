@@ -0,0 +1,276 @@
+use crate::world::chunk::{Chunk, ChunkPos, WorldHeight};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::Path;
+
+/// The width and depth of a region in chunks.
+pub const REGION_SIZE: i32 = 32;
+
+/// The number of chunks a region can hold.
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+
+/// Identifies region files.
+const MAGIC: [u8; 4] = *b"CBXR";
+
+/// The current version of the region format.
+pub const REGION_FORMAT_VERSION: u32 = 1;
+
+/// Magic, version, then an `(offset, length)` pair of `u32`s per chunk.
+const HEADER_LEN: usize = 8 + REGION_CHUNKS * 8;
+
+/// The position of a region in region coordinates (one unit per `REGION_SIZE` chunks).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RegionPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl RegionPos {
+    /// Returns the region containing the given chunk.
+    pub fn from_chunk(pos: ChunkPos) -> Self {
+        RegionPos {
+            x: pos.x.div_euclid(REGION_SIZE),
+            z: pos.z.div_euclid(REGION_SIZE),
+        }
+    }
+
+    /// Returns the name of the region's file, e.g. `r.-1.0.bin`.
+    pub fn file_name(&self) -> String {
+        format!("r.{}.{}.bin", self.x, self.z)
+    }
+}
+
+/// Returns the slot of a chunk within its region.
+fn slot(pos: ChunkPos) -> usize {
+    (pos.z.rem_euclid(REGION_SIZE) * REGION_SIZE + pos.x.rem_euclid(REGION_SIZE)) as usize
+}
+
+/// Returns a chunk position stored in the given slot.
+///
+/// Slots map back to positions within the first region, which is all `slot` needs.
+fn slot_pos(index: usize) -> ChunkPos {
+    ChunkPos::new(index as i32 % REGION_SIZE, index as i32 / REGION_SIZE)
+}
+
+/// A group of `REGION_SIZE` × `REGION_SIZE` chunks stored in one file.
+///
+/// The file starts with a header holding the format version and the offset and
+/// length of every chunk; chunks follow as zlib-compressed `Chunk::to_bytes` data. Absent
+/// chunks have an offset of zero.
+pub struct Region {
+    /// Compressed data of each chunk, indexed by slot.
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl Region {
+    /// Creates an empty region.
+    pub fn new() -> Self {
+        Region {
+            chunks: vec![None; REGION_CHUNKS],
+        }
+    }
+
+    /// Reads a region file, returning an empty region if the file doesn't exist.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => Region::from_bytes(&bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Region::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the region file, replacing it atomically so a crash mid-write
+    /// doesn't lose the previous version.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, self.to_bytes())?;
+        fs::rename(&temp, path)
+    }
+
+    /// Parses a region from the contents of a region file.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+        let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        if bytes.len() < HEADER_LEN || bytes[0..4] != MAGIC {
+            return Err(invalid("not a region file"));
+        }
+        let version = read_u32(4);
+        if version != REGION_FORMAT_VERSION {
+            return Err(invalid(&format!(
+                "unsupported region format version {}",
+                version
+            )));
+        }
+
+        let mut region = Region::new();
+        for (index, chunk) in region.chunks.iter_mut().enumerate() {
+            let offset = read_u32(8 + index * 8) as usize;
+            let length = read_u32(12 + index * 8) as usize;
+            if offset == 0 {
+                continue;
+            }
+            let data = bytes
+                .get(offset..offset + length)
+                .ok_or_else(|| invalid("chunk data past the end of the file"))?;
+            *chunk = Some(data.to_vec());
+        }
+        Ok(region)
+    }
+
+    /// Serializes the region into the contents of a region file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&REGION_FORMAT_VERSION.to_le_bytes());
+
+        let mut body = Vec::new();
+        for chunk in &self.chunks {
+            let (offset, length) = match chunk {
+                Some(data) => {
+                    let offset = HEADER_LEN + body.len();
+                    body.extend_from_slice(data);
+                    (offset as u32, data.len() as u32)
+                }
+                None => (0, 0),
+            };
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&length.to_le_bytes());
+        }
+
+        header.extend_from_slice(&body);
+        header
+    }
+
+    /// Returns the chunk stored at the given position, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the chunk.
+    /// * `height` - The height of the world the chunk was saved in.
+    pub fn load_chunk(&self, pos: ChunkPos, height: WorldHeight) -> io::Result<Option<Chunk>> {
+        let Some(data) = &self.chunks[slot(pos)] else {
+            return Ok(None);
+        };
+
+        let mut bytes = Vec::new();
+        ZlibDecoder::new(data.as_slice()).read_to_end(&mut bytes)?;
+        Chunk::from_bytes(&bytes, height)
+            .map(Some)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "malformed chunk data"))
+    }
+
+    /// Decodes every stored chunk, applies `f` to it and stores it again.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the region holds any chunks, or the error of the first chunk
+    /// that can't be decoded. The region is left partly mapped in that case.
+    pub fn map_chunks<F: FnMut(&mut Chunk)>(
+        &mut self,
+        height: WorldHeight,
+        mut f: F,
+    ) -> io::Result<bool> {
+        let mut any = false;
+        for index in 0..REGION_CHUNKS {
+            let pos = slot_pos(index);
+            if let Some(mut chunk) = self.load_chunk(pos, height)? {
+                f(&mut chunk);
+                self.store_chunk(pos, &chunk);
+                any = true;
+            }
+        }
+        Ok(any)
+    }
+
+    /// Stores a chunk at the given position, replacing any previous version.
+    pub fn store_chunk(&mut self, pos: ChunkPos, chunk: &Chunk) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        encoder
+            .write_all(&chunk.to_bytes())
+            .and_then(|_| encoder.flush())
+            .expect("Writing to memory can't fail");
+        self.chunks[slot(pos)] = Some(encoder.finish().expect("Writing to memory can't fail"));
+    }
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::BlockId;
+    use crate::world::chunk::CHUNK_SIZE;
+
+    fn height() -> WorldHeight {
+        WorldHeight::new(0, 16)
+    }
+
+    fn sample_chunk(seed: u16) -> Chunk {
+        let mut chunk = Chunk::new(height());
+        for i in 0..16 {
+            chunk.set(i, i as i32, 15 - i, BlockId(seed + i as u16));
+        }
+        chunk
+    }
+
+    fn same_blocks(a: &Chunk, b: &Chunk) -> bool {
+        a.height().range().all(|y| {
+            (0..CHUNK_SIZE).all(|z| (0..CHUNK_SIZE).all(|x| a.get(x, y, z) == b.get(x, y, z)))
+        })
+    }
+
+    #[test]
+    fn chunks_round_trip_through_a_region_file() {
+        let mut region = Region::new();
+        let positions = [
+            ChunkPos::new(0, 0),
+            ChunkPos::new(-1, 5),
+            ChunkPos::new(31, -32),
+        ];
+        for (i, &pos) in positions.iter().enumerate() {
+            region.store_chunk(pos, &sample_chunk(i as u16 * 20));
+        }
+
+        let read = Region::from_bytes(&region.to_bytes()).unwrap();
+        for (i, &pos) in positions.iter().enumerate() {
+            let chunk = read.load_chunk(pos, height()).unwrap().unwrap();
+            assert!(same_blocks(&chunk, &sample_chunk(i as u16 * 20)));
+        }
+        assert!(
+            read.load_chunk(ChunkPos::new(1, 1), height())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn regions_group_chunks() {
+        assert_eq!(
+            RegionPos::from_chunk(ChunkPos::new(0, 31)),
+            RegionPos { x: 0, z: 0 }
+        );
+        assert_eq!(
+            RegionPos::from_chunk(ChunkPos::new(-1, 32)),
+            RegionPos { x: -1, z: 1 }
+        );
+        assert_ne!(slot(ChunkPos::new(-1, 0)), slot(ChunkPos::new(0, 0)));
+        assert_eq!(slot(ChunkPos::new(-1, 0)), slot(ChunkPos::new(31, 0)));
+    }
+
+    #[test]
+    fn rejects_other_files_and_versions() {
+        assert!(Region::from_bytes(b"not a region").is_err());
+
+        let mut bytes = Region::new().to_bytes();
+        bytes[4..8].copy_from_slice(&(REGION_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(Region::from_bytes(&bytes).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use crate::world::block::{BlockId, Face};
+use crate::world::generation::noise::SplitMix64;
+use crate::world::light::Light;
+use crate::world::registry::BlockRegistry;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+/// The light a grass block needs above it to spread.
+const GRASS_SPREAD_LIGHT: u8 = 9;
+
+/// The light dirt needs above it for grass to spread onto it.
+const GRASS_GROW_LIGHT: u8 = 4;
+
+/// How many blocks through leaves a log holds leaves up from.
+const LEAF_SUPPORT_DISTANCE: u32 = 4;
+
+/// What a block does when a random tick picks it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RandomTick {
+    /// Nothing happens.
+    #[default]
+    None,
+    /// Spreads onto nearby lit dirt, and turns back into dirt once covered.
+    SpreadGrass,
+    /// Decays into air once no log is close enough to hold it up.
+    Decay,
+}
+
+/// A block update waiting for its tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScheduledTick {
+    /// The world coordinates of the block to update.
+    pub pos: [i32; 3],
+    /// The block that asked for the update, which decides what the update does.
+    /// The block at `pos` may have changed since.
+    pub block: BlockId,
+}
+
+/// Block updates queued for later ticks, handed out in the order they're due.
+///
+/// A block waits for at most one update at a time, so scheduling the same
+/// update again before it runs does nothing.
+#[derive(Debug, Default)]
+pub struct TickScheduler {
+    /// Queued updates by the tick they are due at.
+    due: BTreeMap<u64, Vec<ScheduledTick>>,
+    /// Every update in `due`, so none is queued twice.
+    queued: HashSet<ScheduledTick>,
+}
+
+impl TickScheduler {
+    /// Creates an empty scheduler.
+    pub fn new() -> Self {
+        TickScheduler::default()
+    }
+
+    /// Queues an update for a tick.
+    ///
+    /// # Arguments
+    ///
+    /// * `tick` - The update.
+    /// * `due` - The tick it should run on.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the update was queued, `false` if it was already waiting.
+    pub fn schedule(&mut self, tick: ScheduledTick, due: u64) -> bool {
+        if !self.queued.insert(tick) {
+            return false;
+        }
+        self.due.entry(due).or_default().push(tick);
+        true
+    }
+
+    /// Returns whether an update is waiting to run.
+    pub fn is_scheduled(&self, tick: &ScheduledTick) -> bool {
+        self.queued.contains(tick)
+    }
+
+    /// Removes and returns every update due at or before a tick, earliest first
+    /// and in the order they were queued within a tick.
+    pub fn take_due(&mut self, now: u64) -> Vec<ScheduledTick> {
+        let mut ready = Vec::new();
+        while self
+            .due
+            .first_key_value()
+            .is_some_and(|(&due, _)| due <= now)
+        {
+            let (_, ticks) = self.due.pop_first().unwrap();
+            ready.extend(ticks);
+        }
+        for tick in &ready {
+            self.queued.remove(tick);
+        }
+        ready
+    }
+}
+
+/// The blocks and light that block updates look at.
+pub trait BlockAccess {
+    /// Returns the block at world coordinates.
+    fn block(&self, pos: [i32; 3]) -> BlockId;
+
+    /// Returns the light at world coordinates.
+    fn light(&self, pos: [i32; 3]) -> Light;
+}
+
+/// Works out what a random tick does to a block.
+///
+/// # Arguments
+///
+/// * `world` - The blocks around the ticked one.
+/// * `registry` - The block registry describing what each block does.
+/// * `pos` - The world coordinates of the ticked block.
+/// * `random` - Picks where spreading blocks spread to.
+///
+/// # Returns
+///
+/// The position and new block of the change the tick makes, if any.
+pub fn random_tick<W: BlockAccess>(
+    world: &W,
+    registry: &BlockRegistry,
+    pos: [i32; 3],
+    random: &mut SplitMix64,
+) -> Option<([i32; 3], BlockId)> {
+    let block = world.block(pos);
+    match registry.get(block).random_tick {
+        RandomTick::None => None,
+        RandomTick::SpreadGrass => spread_grass(world, registry, pos, block, random),
+        RandomTick::Decay => decay(world, registry, pos, block),
+    }
+}
+
+/// Returns the brightest of a light's sky and block light.
+fn brightness(light: Light) -> u8 {
+    light.sky().max(light.block())
+}
+
+/// Turns covered grass back into dirt, or spreads lit grass onto a random dirt
+/// block up to one block away sideways, three below and one above.
+fn spread_grass<W: BlockAccess>(
+    world: &W,
+    registry: &BlockRegistry,
+    pos: [i32; 3],
+    grass: BlockId,
+    random: &mut SplitMix64,
+) -> Option<([i32; 3], BlockId)> {
+    let dirt = registry.id("dirt")?;
+    let above = |[x, y, z]: [i32; 3]| [x, y + 1, z];
+    if registry.is_opaque(world.block(above(pos))) {
+        return Some((pos, dirt));
+    }
+    if brightness(world.light(above(pos))) < GRASS_SPREAD_LIGHT {
+        return None;
+    }
+
+    let target = [
+        pos[0] + random.next_below(3) - 1,
+        pos[1] + random.next_below(5) - 3,
+        pos[2] + random.next_below(3) - 1,
+    ];
+    let grows = world.block(target) == dirt
+        && !registry.is_opaque(world.block(above(target)))
+        && brightness(world.light(above(target))) >= GRASS_GROW_LIGHT;
+    grows.then_some((target, grass))
+}
+
+/// Turns leaves into air unless a log is within `LEAF_SUPPORT_DISTANCE` blocks,
+/// counting only paths through the same kind of leaves.
+fn decay<W: BlockAccess>(
+    world: &W,
+    registry: &BlockRegistry,
+    pos: [i32; 3],
+    leaves: BlockId,
+) -> Option<([i32; 3], BlockId)> {
+    let log = registry.id("log")?;
+    let mut visited = HashSet::from([pos]);
+    let mut queue = VecDeque::from([(pos, 0)]);
+    while let Some((at, distance)) = queue.pop_front() {
+        if distance == LEAF_SUPPORT_DISTANCE {
+            continue;
+        }
+        for face in Face::ALL {
+            let normal = face.normal();
+            let next = [0, 1, 2].map(|axis| at[axis] + normal[axis]);
+            let block = world.block(next);
+            if block == log {
+                return None;
+            }
+            if block == leaves && visited.insert(next) {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+    Some((pos, BlockId::AIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A small world of blocks lit by the sky, with air everywhere else.
+    #[derive(Default)]
+    struct Blocks {
+        blocks: HashMap<[i32; 3], BlockId>,
+        dark: HashSet<[i32; 3]>,
+    }
+
+    impl BlockAccess for Blocks {
+        fn block(&self, pos: [i32; 3]) -> BlockId {
+            self.blocks.get(&pos).copied().unwrap_or(BlockId::AIR)
+        }
+
+        fn light(&self, pos: [i32; 3]) -> Light {
+            if self.dark.contains(&pos) {
+                Light::DARK
+            } else {
+                Light::SKY
+            }
+        }
+    }
+
+    fn tick(at: [i32; 3]) -> ScheduledTick {
+        ScheduledTick {
+            pos: at,
+            block: BlockId::AIR,
+        }
+    }
+
+    #[test]
+    fn scheduled_ticks_run_once_in_due_order() {
+        let mut scheduler = TickScheduler::new();
+        assert!(scheduler.schedule(tick([0, 0, 0]), 5));
+        assert!(scheduler.schedule(tick([1, 0, 0]), 2));
+        assert!(scheduler.schedule(tick([2, 0, 0]), 2));
+        assert!(!scheduler.schedule(tick([0, 0, 0]), 1));
+
+        assert!(scheduler.take_due(1).is_empty());
+        assert_eq!(
+            scheduler.take_due(3),
+            vec![tick([1, 0, 0]), tick([2, 0, 0])]
+        );
+        assert!(!scheduler.is_scheduled(&tick([1, 0, 0])));
+        assert!(scheduler.is_scheduled(&tick([0, 0, 0])));
+        assert_eq!(scheduler.take_due(10), vec![tick([0, 0, 0])]);
+        assert!(scheduler.take_due(u64::MAX).is_empty());
+
+        // Once it has run, an update can be queued again
+        assert!(scheduler.schedule(tick([0, 0, 0]), 11));
+    }
+
+    #[test]
+    fn grass_spreads_to_lit_dirt_and_dies_when_covered() {
+        let registry = BlockRegistry::default();
+        let (grass, dirt, stone) = (
+            registry.expect_id("grass"),
+            registry.expect_id("dirt"),
+            registry.expect_id("stone"),
+        );
+        let mut blocks = Blocks::default();
+        blocks.blocks.insert([0, 0, 0], grass);
+        for x in 1..=2 {
+            blocks.blocks.insert([x, 0, 0], dirt);
+        }
+        let mut random = SplitMix64::new(7);
+        while blocks.block([1, 0, 0]) != grass {
+            if let Some((at, block)) = random_tick(&blocks, &registry, [0, 0, 0], &mut random) {
+                blocks.blocks.insert(at, block);
+            }
+        }
+        // Grass never jumps more than one block sideways
+        assert_eq!(blocks.block([2, 0, 0]), dirt);
+
+        // Dark dirt stays bare
+        blocks.dark.insert([2, 1, 0]);
+        for _ in 0..200 {
+            let change = random_tick(&blocks, &registry, [1, 0, 0], &mut random);
+            assert!(change.is_none_or(|(at, _)| at != [2, 0, 0]));
+        }
+
+        blocks.blocks.insert([1, 1, 0], stone);
+        let change = random_tick(&blocks, &registry, [1, 0, 0], &mut random);
+        assert_eq!(change, Some(([1, 0, 0], dirt)));
+    }
+
+    #[test]
+    fn leaves_decay_away_from_logs() {
+        let registry = BlockRegistry::default();
+        let (log, leaves) = (registry.expect_id("log"), registry.expect_id("leaves"));
+        let mut blocks = Blocks::default();
+        blocks.blocks.insert([0, 0, 0], log);
+        for x in 1..=5 {
+            blocks.blocks.insert([x, 0, 0], leaves);
+        }
+        let mut random = SplitMix64::new(0);
+        let mut tick = |blocks: &Blocks, x| random_tick(blocks, &registry, [x, 0, 0], &mut random);
+
+        for x in 1..=4 {
+            assert_eq!(tick(&blocks, x), None, "leaves {} blocks from the log", x);
+        }
+        assert_eq!(tick(&blocks, 5), Some(([5, 0, 0], BlockId::AIR)));
+
+        // Cutting the path cuts the support
+        blocks.blocks.remove(&[2, 0, 0]);
+        assert_eq!(tick(&blocks, 3), Some(([3, 0, 0], BlockId::AIR)));
+        assert_eq!(tick(&blocks, 1), None);
+    }
+}
@@ -0,0 +1,439 @@
+use crate::world::block::BlockId;
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos};
+use crate::world::generation::TerrainGenerator;
+use crate::world::generation::decoration::{FEATURE_REACH, Placement};
+use crate::world::light::Light;
+use crate::world::mesher::{ChunkMeshData, MeshingMode, build_chunk_mesh};
+use crate::world::registry::BlockRegistry;
+use crate::world::storage::WorldStorage;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Work handed to the chunk workers.
+pub enum Job {
+    /// Load the chunk at the given position from disk, or generate it if it was never saved.
+    Generate(ChunkPos),
+    /// Build the mesh of a chunk from a copy of it and its neighbours.
+    Mesh {
+        pos: ChunkPos,
+        /// Identifies the request, so results of outdated requests can be dropped.
+        revision: u64,
+        blocks: Box<Neighbourhood>,
+    },
+}
+
+impl Job {
+    /// Returns the chunk the job works on.
+    pub fn pos(&self) -> ChunkPos {
+        match self {
+            Job::Generate(pos) | Job::Mesh { pos, .. } => *pos,
+        }
+    }
+}
+
+/// Work finished by the chunk workers.
+pub enum JobResult {
+    Generated(Box<GeneratedChunk>),
+    Meshed {
+        pos: ChunkPos,
+        revision: u64,
+        data: ChunkMeshData,
+    },
+}
+
+/// A freshly generated or loaded chunk and the decorations that involve it.
+pub struct GeneratedChunk {
+    pub pos: ChunkPos,
+    pub chunk: Chunk,
+    /// Whether the chunk was loaded from disk; saved chunks already hold their
+    /// decorations, so both lists below are empty.
+    pub saved: bool,
+    /// Decorations starting in the chunk, which may spill into its neighbours.
+    pub decorations: Vec<Placement>,
+    /// Decorations starting in each neighbouring chunk that land in this one.
+    pub neighbour_decorations: Vec<(ChunkPos, Vec<Placement>)>,
+}
+
+/// A copy of a chunk and the chunks sharing a side with it, used to mesh the chunk
+/// without access to the world.
+pub struct Neighbourhood {
+    pub center: Chunk,
+    /// The neighbours in the order of `ChunkPos::surrounding`; unloaded neighbours are `None`.
+    pub neighbours: [Option<Chunk>; 8],
+}
+
+impl Neighbourhood {
+    /// Returns the block at coordinates local to the center chunk, which may reach
+    /// into the surrounding chunks.
+    ///
+    /// Positions outside the world height or in unloaded chunks are air.
+    pub fn get(&self, x: i32, y: i32, z: i32) -> BlockId {
+        match self.locate(x, z) {
+            Some((chunk, x, z)) => chunk.get(x, y, z),
+            None => BlockId::AIR,
+        }
+    }
+
+    /// Returns the light at coordinates local to the center chunk, which may reach
+    /// into the surrounding chunks.
+    ///
+    /// Positions in unloaded chunks are dark.
+    pub fn light(&self, x: i32, y: i32, z: i32) -> Light {
+        match self.locate(x, z) {
+            Some((chunk, x, z)) => chunk.light(x, y, z),
+            None => Light::DARK,
+        }
+    }
+
+    /// Returns the chunk holding a column and the column's coordinates within it.
+    fn locate(&self, x: i32, z: i32) -> Option<(&Chunk, usize, usize)> {
+        let size = CHUNK_SIZE as i32;
+        let offset = ChunkPos::new(x.div_euclid(size), z.div_euclid(size));
+        let chunk = if offset == ChunkPos::new(0, 0) {
+            &self.center
+        } else {
+            let index = ChunkPos::new(0, 0)
+                .surrounding()
+                .iter()
+                .position(|&pos| pos == offset)?;
+            self.neighbours[index].as_ref()?
+        };
+        Some((
+            chunk,
+            x.rem_euclid(size) as usize,
+            z.rem_euclid(size) as usize,
+        ))
+    }
+}
+
+/// Jobs waiting for a worker, shared between the workers and the main thread.
+struct Queue {
+    jobs: Vec<Job>,
+    /// The chunk the camera is in; jobs closest to it are picked first.
+    center: ChunkPos,
+    shutdown: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Signalled when jobs are added or the workers should shut down.
+    available: Condvar,
+}
+
+/// A pool of background threads that generate and mesh chunks.
+///
+/// Results are collected on the main thread, which owns the chunks and uploads
+/// meshes to the GPU.
+pub struct ChunkWorkers {
+    shared: Arc<Shared>,
+    results: Receiver<JobResult>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl ChunkWorkers {
+    /// Starts the worker threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - The number of worker threads; `0` picks one per spare CPU core.
+    /// * `registry` - The block types, used for meshing.
+    /// * `generator` - Generates chunks and their decorations.
+    /// * `storage` - The saved world, checked before generating a chunk.
+    /// * `meshing_mode` - How chunk meshes are built.
+    ///
+    /// # Returns
+    ///
+    /// The running worker pool.
+    pub fn new(
+        threads: usize,
+        registry: Arc<BlockRegistry>,
+        generator: Arc<TerrainGenerator>,
+        storage: Arc<Mutex<WorldStorage>>,
+        meshing_mode: MeshingMode,
+    ) -> Self {
+        let threads = if threads == 0 {
+            // Leave a core for the render loop
+            thread::available_parallelism()
+                .map(|cores| cores.get().saturating_sub(1))
+                .unwrap_or(1)
+                .max(1)
+        } else {
+            threads
+        };
+
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                jobs: Vec::new(),
+                center: ChunkPos::new(0, 0),
+                shutdown: false,
+            }),
+            available: Condvar::new(),
+        });
+        let (sender, results) = mpsc::channel();
+
+        let threads = (0..threads)
+            .map(|index| {
+                let worker = Worker {
+                    shared: Arc::clone(&shared),
+                    results: sender.clone(),
+                    registry: Arc::clone(&registry),
+                    generator: Arc::clone(&generator),
+                    storage: Arc::clone(&storage),
+                    meshing_mode,
+                };
+                thread::Builder::new()
+                    .name(format!("chunk-worker-{}", index))
+                    .spawn(move || worker.run())
+                    .expect("Failed to spawn chunk worker")
+            })
+            .collect();
+
+        ChunkWorkers {
+            shared,
+            results,
+            threads,
+        }
+    }
+
+    /// Queues a job for the workers.
+    pub fn submit(&self, job: Job) {
+        self.shared.queue.lock().unwrap().jobs.push(job);
+        self.shared.available.notify_one();
+    }
+
+    /// Sets the chunk the camera is in, so the jobs closest to it are done first.
+    pub fn set_center(&self, center: ChunkPos) {
+        self.shared.queue.lock().unwrap().center = center;
+    }
+
+    /// Drops queued jobs that no longer need doing; jobs already running still finish.
+    pub fn retain<F>(&self, keep: F)
+    where
+        F: Fn(&Job) -> bool,
+    {
+        self.shared.queue.lock().unwrap().jobs.retain(keep);
+    }
+
+    /// Returns a finished job, if any, without waiting.
+    pub fn try_recv(&self) -> Option<JobResult> {
+        self.results.try_recv().ok()
+    }
+
+    /// Waits for the next finished job.
+    pub fn recv(&self) -> JobResult {
+        self.results.recv().expect("All chunk workers have stopped")
+    }
+}
+
+impl Drop for ChunkWorkers {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.available.notify_all();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The state owned by each worker thread.
+struct Worker {
+    shared: Arc<Shared>,
+    results: Sender<JobResult>,
+    registry: Arc<BlockRegistry>,
+    generator: Arc<TerrainGenerator>,
+    storage: Arc<Mutex<WorldStorage>>,
+    meshing_mode: MeshingMode,
+}
+
+impl Worker {
+    /// Runs jobs, nearest to the camera first, until the pool shuts down.
+    fn run(self) {
+        loop {
+            let job = {
+                let mut queue = self.shared.queue.lock().unwrap();
+                loop {
+                    if queue.shutdown {
+                        return;
+                    }
+                    if let Some(index) = nearest_job(&queue.jobs, queue.center) {
+                        break queue.jobs.swap_remove(index);
+                    }
+                    queue = self.shared.available.wait(queue).unwrap();
+                }
+            };
+
+            // The main thread is gone if sending fails, so there's nothing left to do
+            if self.results.send(self.execute(job)).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn execute(&self, job: Job) -> JobResult {
+        match job {
+            Job::Generate(pos) => {
+                let saved = self.storage.lock().unwrap().load_chunk(pos);
+                if let Some(chunk) = saved {
+                    return JobResult::Generated(Box::new(GeneratedChunk {
+                        pos,
+                        chunk,
+                        saved: true,
+                        decorations: Vec::new(),
+                        neighbour_decorations: Vec::new(),
+                    }));
+                }
+
+                let mut neighbour_decorations = Vec::new();
+                for x in -FEATURE_REACH..=FEATURE_REACH {
+                    for z in -FEATURE_REACH..=FEATURE_REACH {
+                        let neighbour = ChunkPos::new(pos.x + x, pos.z + z);
+                        if neighbour == pos {
+                            continue;
+                        }
+                        let mut placements = self.generator.decorations(neighbour);
+                        placements.retain(|placement| placement.chunk() == pos);
+                        neighbour_decorations.push((neighbour, placements));
+                    }
+                }
+
+                JobResult::Generated(Box::new(GeneratedChunk {
+                    pos,
+                    chunk: self.generator.generate_chunk(pos),
+                    saved: false,
+                    decorations: self.generator.decorations(pos),
+                    neighbour_decorations,
+                }))
+            }
+            Job::Mesh {
+                pos,
+                revision,
+                blocks,
+            } => JobResult::Meshed {
+                pos,
+                revision,
+                data: build_chunk_mesh(
+                    &blocks.center,
+                    &self.registry,
+                    self.meshing_mode,
+                    |x, y, z| (blocks.get(x, y, z), blocks.light(x, y, z)),
+                ),
+            },
+        }
+    }
+}
+
+/// Returns the index of the job closest to `center`, if there are any jobs.
+fn nearest_job(jobs: &[Job], center: ChunkPos) -> Option<usize> {
+    jobs.iter()
+        .enumerate()
+        .min_by_key(|(_, job)| {
+            let pos = job.pos();
+            let (dx, dz) = (pos.x - center.x, pos.z - center.z);
+            dx * dx + dz * dz
+        })
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::WorldHeight;
+    use crate::world::storage::temp_save_dir;
+
+    #[test]
+    fn nearest_job_is_picked_first() {
+        let jobs =
+            [(5, 5), (-1, 2), (3, 0), (1, 1)].map(|(x, z)| Job::Generate(ChunkPos::new(x, z)));
+        assert_eq!(nearest_job(&jobs, ChunkPos::new(0, 0)), Some(3));
+        assert_eq!(nearest_job(&jobs, ChunkPos::new(4, 4)), Some(0));
+        assert_eq!(nearest_job(&[], ChunkPos::new(0, 0)), None);
+    }
+
+    #[test]
+    fn neighbourhood_reads_across_borders() {
+        let block = |id| {
+            let mut chunk = Chunk::new(WorldHeight::new(0, 16));
+            chunk.set(0, 1, 0, BlockId(id));
+            chunk.set(CHUNK_SIZE - 1, 1, CHUNK_SIZE - 1, BlockId(id));
+            chunk
+        };
+        let blocks = Neighbourhood {
+            center: block(1),
+            neighbours: [
+                Some(block(2)),
+                Some(block(3)),
+                Some(block(4)),
+                None,
+                Some(block(5)),
+                None,
+                None,
+                None,
+            ],
+        };
+
+        let last = CHUNK_SIZE as i32 - 1;
+        assert_eq!(blocks.get(0, 1, 0), BlockId(1));
+        assert_eq!(blocks.get(-1, 1, last), BlockId(2));
+        assert_eq!(blocks.get(last + 1, 1, 0), BlockId(3));
+        assert_eq!(blocks.get(last, 1, -1), BlockId(4));
+        assert_eq!(blocks.get(-1, 1, -1), BlockId(5));
+        // Unloaded neighbours and positions outside the world height are air
+        assert_eq!(blocks.get(0, 1, last + 1), BlockId::AIR);
+        assert_eq!(blocks.get(0, -1, 0), BlockId::AIR);
+    }
+
+    #[test]
+    fn workers_generate_and_mesh_chunks() {
+        let registry = Arc::new(BlockRegistry::default());
+        let config = crate::config::GenerationConfig {
+            seed: 9,
+            ..Default::default()
+        };
+        let generator = Arc::new(TerrainGenerator::new(&config, &registry));
+        let dir = temp_save_dir();
+        let storage = Arc::new(Mutex::new(
+            WorldStorage::open(&dir, generator.height(), None, &registry).unwrap(),
+        ));
+        let workers = ChunkWorkers::new(
+            2,
+            registry,
+            Arc::clone(&generator),
+            Arc::clone(&storage),
+            MeshingMode::Greedy,
+        );
+
+        let pos = ChunkPos::new(2, -1);
+        workers.submit(Job::Generate(pos));
+        let JobResult::Generated(generated) = workers.recv() else {
+            panic!("expected a generated chunk");
+        };
+        assert_eq!(generated.pos, pos);
+        assert!(!generated.saved);
+        assert_eq!(generated.decorations, generator.decorations(pos));
+
+        // Once saved, the chunk is loaded instead of generated again
+        storage.lock().unwrap().save_chunk(pos, &generated.chunk);
+        workers.submit(Job::Generate(pos));
+        let JobResult::Generated(loaded) = workers.recv() else {
+            panic!("expected a loaded chunk");
+        };
+        assert!(loaded.saved && loaded.decorations.is_empty());
+
+        workers.submit(Job::Mesh {
+            pos,
+            revision: 7,
+            blocks: Box::new(Neighbourhood {
+                center: generated.chunk,
+                neighbours: [None, None, None, None, None, None, None, None],
+            }),
+        });
+        let JobResult::Meshed { revision, data, .. } = workers.recv() else {
+            panic!("expected a chunk mesh");
+        };
+        assert_eq!(revision, 7);
+        assert!(!data.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
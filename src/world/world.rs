@@ -1,150 +1,1072 @@
 extern crate gl;
 
+use crate::config::{RenderingConfig, WorldConfig};
 use crate::rendering::mesh::Mesh;
 use crate::rendering::shader::Shader;
-use crate::world::cube_render::draw_chunk;
-use crate::world::generation::generate_chunk;
-use cgmath::Point3;
+use crate::world::block::{BlockId, Face};
+use crate::world::chunk::{CHUNK_SIZE, Chunk, ChunkPos, WorldHeight, local_coord};
+use crate::world::collision::{self, Aabb, SweptMove};
+use crate::world::cube_render::{
+    TranslucentMesh, draw_chunk, draw_entity, draw_translucent_chunks,
+};
+use crate::world::entities::{DroppedItem, FallingBlock, ITEM_SIZE, Landing, falls_through};
+use crate::world::generation::TerrainGenerator;
+use crate::world::generation::biome::Biome;
+use crate::world::generation::decoration::PendingPlacements;
+use crate::world::generation::noise::SplitMix64;
+use crate::world::light::{self, Light, LoadedChunks};
+use crate::world::liquid::{self, INFLOWS};
+use crate::world::mesher::{CHUNK_VERTEX_LAYOUT, ChunkMeshData, MeshingMode, block_vertices};
+use crate::world::raycast::{RaycastHit, raycast};
+use crate::world::registry::BlockRegistry;
+use crate::world::section::SECTION_SIZE;
+use crate::world::storage::WorldStorage;
+use crate::world::ticks::{self, BlockAccess, ScheduledTick, TickScheduler};
+use crate::world::workers::{ChunkWorkers, GeneratedChunk, Job, JobResult, Neighbourhood};
+use cgmath::{Point3, Vector3};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// How many times per second the world ticks.
+pub const TICKS_PER_SECOND: u32 = 20;
+
+/// Ticks between a falling block losing its support and starting to fall.
+const FALL_DELAY: u64 = 2;
 
 /// Represents the game world, including blocks and rendering.
+///
+/// Chunks are generated and meshed by background workers; the world collects
+/// their results each frame and uploads the finished meshes. Chunks are saved
+/// when they are unloaded and by `save`, and loaded from the save before being
+/// generated.
 pub struct World {
-    pub chunk_data: [[[u32; 16]; 16]; 16],
-    pub mesh: Mesh,
+    registry: Arc<BlockRegistry>,
+    generator: Arc<TerrainGenerator>,
+    storage: Arc<Mutex<WorldStorage>>,
+    workers: ChunkWorkers,
+    chunks: HashMap<ChunkPos, Chunk>,
+    /// Loaded chunks that changed since they were last saved.
+    unsaved: HashSet<ChunkPos>,
+    /// Chunks queued for generation but not yet received.
+    generating: HashSet<ChunkPos>,
+    /// Baked meshes of loaded chunks' opaque faces; chunks without any have no entry.
+    meshes: HashMap<ChunkPos, Mesh>,
+    /// Baked meshes of loaded chunks' translucent faces, drawn after all opaque ones.
+    translucent_meshes: HashMap<ChunkPos, TranslucentMesh>,
+    /// Chunks whose mesh is out of date and hasn't been requested yet.
+    dirty: HashSet<ChunkPos>,
+    /// The latest mesh request of each chunk; older results are dropped.
+    mesh_revisions: HashMap<ChunkPos, u64>,
+    next_revision: u64,
+    /// Finished meshes waiting to be uploaded.
+    ready_meshes: HashMap<ChunkPos, ChunkMeshData>,
+    /// Decorations waiting for the chunks they spill into to be generated.
+    pending: PendingPlacements,
+    /// The chunk the world was last loaded around.
+    center: ChunkPos,
+    render_distance: i32,
+    uploads_per_frame: usize,
+    /// The number of ticks the world has advanced by this session.
+    ticks: u64,
+    /// Block updates waiting for a later tick.
+    scheduled: TickScheduler,
+    /// Blocks picked in each non-empty section of a loaded chunk every tick.
+    random_tick_speed: u32,
+    /// Picks the blocks random ticks land on.
+    random: SplitMix64,
+    /// Blocks on their way down; like items, they aren't saved.
+    falling_blocks: Vec<FallingBlock>,
+    /// Items lying around or falling.
+    items: Vec<DroppedItem>,
+    /// Meshes of falling blocks and items, by block and the light they're drawn in.
+    /// Only those drawn in the last frame are kept.
+    entity_meshes: HashMap<(BlockId, Light), Mesh>,
 }
 
 impl World {
-    /// Creates a new `World` instance with the given mesh.
+    /// Creates a new, empty `World` instance.
+    ///
+    /// Chunks are loaded or generated lazily by `update_loaded_chunks`, on worker
+    /// threads started here.
     ///
     /// # Arguments
     ///
-    /// * `mesh` - The mesh to use for rendering the world.
+    /// * `registry` - The block types the world is made of.
+    /// * `generator` - Generates chunks that haven't been saved.
+    /// * `storage` - The save the world is loaded from and saved to.
+    /// * `config` - The world configuration.
+    /// * `rendering` - The rendering configuration.
     ///
     /// # Returns
     ///
     /// A new `World` instance.
-    pub fn new(mesh: Mesh) -> Self {
+    pub fn new(
+        registry: BlockRegistry,
+        generator: TerrainGenerator,
+        storage: WorldStorage,
+        config: &WorldConfig,
+        rendering: &RenderingConfig,
+    ) -> Self {
+        let registry = Arc::new(registry);
+        let generator = Arc::new(generator);
+        let pending = storage.load_pending();
+        let storage = Arc::new(Mutex::new(storage));
+        let meshing_mode = if rendering.greedy_meshing {
+            MeshingMode::Greedy
+        } else {
+            MeshingMode::Naive
+        };
+
         World {
-            chunk_data: generate_chunk(),
-            mesh,
+            workers: ChunkWorkers::new(
+                config.worker_threads,
+                Arc::clone(&registry),
+                Arc::clone(&generator),
+                Arc::clone(&storage),
+                meshing_mode,
+            ),
+            registry,
+            generator,
+            storage,
+            chunks: HashMap::new(),
+            unsaved: HashSet::new(),
+            generating: HashSet::new(),
+            meshes: HashMap::new(),
+            translucent_meshes: HashMap::new(),
+            dirty: HashSet::new(),
+            mesh_revisions: HashMap::new(),
+            next_revision: 0,
+            ready_meshes: HashMap::new(),
+            pending,
+            center: ChunkPos::new(0, 0),
+            render_distance: config.render_distance.max(0),
+            uploads_per_frame: config.mesh_uploads_per_frame.max(1),
+            ticks: 0,
+            scheduled: TickScheduler::new(),
+            random_tick_speed: config.random_tick_speed,
+            random: SplitMix64::new(0),
+            falling_blocks: Vec::new(),
+            items: Vec::new(),
+            entity_meshes: HashMap::new(),
         }
     }
 
-    /// Resolves collision and returns a safe position
-    pub fn resolve_collision(
-        &self,
-        current_pos: Point3<f32>,
-        target_pos: Point3<f32>,
-        radius: f32,
-        height: f32,
-    ) -> Point3<f32> {
-        // Convert camera position (eyes) to feet position for collision
-        let feet_current = Point3::new(current_pos.x, current_pos.y - height, current_pos.z);
-        let feet_target = Point3::new(target_pos.x, target_pos.y - height, target_pos.z);
+    /// Advances the world by one tick: runs the block updates that are due,
+    /// moves falling blocks and items, then runs random ticks.
+    ///
+    /// Updates whose block isn't surrounded by loaded chunks are dropped, so
+    /// liquid at the edge of the loaded area stops until it's disturbed again.
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        for scheduled in self.scheduled.take_due(self.ticks) {
+            let [x, y, z] = scheduled.pos;
+            let loaded = [[0, 0], [-1, 0], [1, 0], [0, -1], [0, 1]]
+                .iter()
+                .all(|[dx, dz]| {
+                    self.chunks
+                        .contains_key(&ChunkPos::from_block(x + dx, z + dz))
+                });
+            if !loaded {
+                continue;
+            }
+
+            if self.registry.liquid(scheduled.block).is_some() {
+                let block = liquid::flow(&self.registry, scheduled.pos, |pos| self.block(pos));
+                if block != self.get_block(x, y, z) {
+                    self.set_block(x, y, z, block);
+                }
+            } else if self.registry.get(scheduled.block).falls
+                && self.get_block(x, y, z) == scheduled.block
+                && falls_through(&self.registry, self.get_block(x, y - 1, z))
+            {
+                self.set_block(x, y, z, BlockId::AIR);
+                self.falling_blocks
+                    .push(FallingBlock::new(scheduled.block, scheduled.pos));
+            }
+        }
+        self.tick_entities();
+        self.random_ticks();
+    }
+
+    /// Moves falling blocks and items, turning landed blocks back into blocks.
+    ///
+    /// Anything in an unloaded chunk waits for it to load, and anything that
+    /// falls out of the bottom of the world is gone.
+    fn tick_entities(&mut self) {
+        let registry = Arc::clone(&self.registry);
+        let min_y = self.height().min_y() as f32;
+        let loaded = |chunks: &HashMap<ChunkPos, Chunk>, position: Point3<f32>| {
+            chunks.contains_key(&ChunkPos::from_block(
+                position.x.floor() as i32,
+                position.z.floor() as i32,
+            ))
+        };
 
-        // If no collision at target position, return it
-        if !self.check_collision(&feet_target, radius, height) {
-            return target_pos;
+        // Lower blocks land first, so blocks falling onto them land on top
+        let mut falling_blocks = std::mem::take(&mut self.falling_blocks);
+        falling_blocks.sort_by(|a, b| a.position.y.total_cmp(&b.position.y));
+        for mut falling in falling_blocks {
+            if !loaded(&self.chunks, falling.position) {
+                self.falling_blocks.push(falling);
+                continue;
+            }
+            match falling.tick(&registry, |pos| self.block(pos)) {
+                // Something may have been placed where the block lands
+                Some(Landing::Block([x, y, z]))
+                    if falls_through(&registry, self.get_block(x, y, z)) =>
+                {
+                    self.set_block(x, y, z, falling.block);
+                }
+                Some(Landing::Block(pos)) => self.items.push(DroppedItem::new(falling.block, pos)),
+                Some(Landing::Item(item)) => self.items.push(item),
+                None if falling.position.y >= min_y => self.falling_blocks.push(falling),
+                None => {}
+            }
         }
 
-        // Try horizontal movement separately to enable sliding
-        let mut new_feet_pos = feet_current;
+        for mut item in std::mem::take(&mut self.items) {
+            let waiting = !loaded(&self.chunks, item.position);
+            if waiting || (item.tick(&registry, |pos| self.block(pos)) && item.position.y >= min_y)
+            {
+                self.items.push(item);
+            }
+        }
+    }
 
-        // Try x-axis movement
-        let x_pos = Point3::new(feet_target.x, feet_current.y, feet_current.z);
-        if !self.check_collision(&x_pos, radius, height) {
-            new_feet_pos.x = feet_target.x;
+    /// Ticks `random_tick_speed` random blocks in every non-empty section of
+    /// each loaded chunk whose neighbours are all loaded, so blocks near a border
+    /// never act on the air an unloaded chunk reads as.
+    fn random_ticks(&mut self) {
+        let mut positions: Vec<ChunkPos> = self
+            .chunks
+            .keys()
+            .filter(|pos| {
+                pos.surrounding()
+                    .iter()
+                    .all(|neighbour| self.chunks.contains_key(neighbour))
+            })
+            .copied()
+            .collect();
+        // Sorted so the same world ticks the same way
+        positions.sort_by_key(|pos| (pos.x, pos.z));
+
+        // Random ticks read the world while drawing numbers, so they draw from a
+        // generator of their own for this tick
+        let mut random = SplitMix64::new(self.random.next_u64());
+        let size = CHUNK_SIZE as i32;
+        for pos in positions {
+            let sections: Vec<i32> = self.chunks[&pos].non_empty_sections().collect();
+            let (origin_x, origin_z) = pos.origin();
+            for base_y in sections {
+                for _ in 0..self.random_tick_speed {
+                    let at = [
+                        origin_x + random.next_below(size),
+                        base_y + random.next_below(SECTION_SIZE as i32),
+                        origin_z + random.next_below(size),
+                    ];
+                    let change = ticks::random_tick(self, &self.registry, at, &mut random);
+                    if let Some(([x, y, z], block)) = change {
+                        self.set_block(x, y, z, block);
+                    }
+                }
+            }
         }
+    }
+
+    /// Queues updates for a changed block and the blocks around it: a flow update
+    /// for each one that liquid could flow into or out of, and a fall check for
+    /// each falling block.
+    fn schedule_updates(&mut self, pos: [i32; 3]) {
+        let around = Face::ALL.map(|face| face.normal());
+        for offset in std::iter::once([0, 0, 0]).chain(around) {
+            let at = [0, 1, 2].map(|axis| pos[axis] + offset[axis]);
+            let block = self.get_block(at[0], at[1], at[2]);
+            if self.registry.get(block).falls {
+                let scheduled = ScheduledTick { pos: at, block };
+                self.scheduled.schedule(scheduled, self.ticks + FALL_DELAY);
+            }
 
-        // Try z-axis movement
-        let z_pos = Point3::new(new_feet_pos.x, feet_current.y, feet_target.z);
-        if !self.check_collision(&z_pos, radius, height) {
-            new_feet_pos.z = feet_target.z;
+            // The update waits for the fastest liquid that could be involved
+            let fastest = std::iter::once([0, 0, 0])
+                .chain(INFLOWS)
+                .filter_map(|[dx, dy, dz]| {
+                    let block = self.get_block(at[0] + dx, at[1] + dy, at[2] + dz);
+                    self.registry.liquid(block)
+                })
+                .map(|liquid| (self.registry.get(liquid.source).flow_delay, liquid.source))
+                .min();
+            if let Some((delay, source)) = fastest {
+                let scheduled = ScheduledTick {
+                    pos: at,
+                    block: source,
+                };
+                self.scheduled
+                    .schedule(scheduled, self.ticks + delay.max(1) as u64);
+            }
         }
+    }
 
-        // Try vertical movement (for jumping/falling)
-        let y_pos = Point3::new(new_feet_pos.x, feet_target.y, new_feet_pos.z);
-        if !self.check_collision(&y_pos, radius, height) {
-            new_feet_pos.y = feet_target.y;
+    /// Queues loading of missing chunks around the given position, saves and
+    /// unloads distant ones and collects the chunks and meshes the workers have finished.
+    ///
+    /// Chunks are kept until they are more than one chunk outside the render
+    /// distance, so walking back and forth across a border doesn't thrash.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The world position to load chunks around (usually the camera).
+    pub fn update_loaded_chunks(&mut self, center: Point3<f32>) {
+        let center_chunk = ChunkPos::from_block(center.x.floor() as i32, center.z.floor() as i32);
+        let radius = self.render_distance;
+        let in_range = |pos: &ChunkPos| pos.distance(center_chunk) <= radius + 1;
+
+        self.center = center_chunk;
+        self.workers.set_center(center_chunk);
+        self.workers.retain(|job| in_range(&job.pos()));
+
+        let mut storage = self.storage.lock().unwrap();
+        self.chunks.retain(|pos, chunk| {
+            if in_range(pos) {
+                return true;
+            }
+            if self.unsaved.remove(pos) {
+                storage.save_chunk(*pos, chunk);
+            }
+            false
+        });
+        drop(storage);
+        self.generating.retain(in_range);
+        self.meshes.retain(|pos, _| in_range(pos));
+        self.translucent_meshes.retain(|pos, _| in_range(pos));
+        self.ready_meshes.retain(|pos, _| in_range(pos));
+        self.mesh_revisions.retain(|pos, _| in_range(pos));
+
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                let pos = ChunkPos::new(center_chunk.x + x, center_chunk.z + z);
+                if self.chunks.contains_key(&pos) || !self.generating.insert(pos) {
+                    continue;
+                }
+                self.workers.submit(Job::Generate(pos));
+            }
         }
 
-        // Convert back to camera/eye position
-        Point3::new(new_feet_pos.x, new_feet_pos.y + height, new_feet_pos.z)
+        while let Some(result) = self.workers.try_recv() {
+            self.handle_result(result);
+        }
     }
 
-    pub fn check_collision(&self, feet_position: &Point3<f32>, radius: f32, height: f32) -> bool {
-        // Convert feet position to block coordinates
-        let block_x = feet_position.x.floor() as usize;
-        let block_y = feet_position.y.floor() as usize;
-        let block_z = feet_position.z.floor() as usize;
+    /// Saves every changed chunk, the decorations still waiting for their chunks,
+    /// and writes them to disk.
+    pub fn save(&mut self) -> io::Result<()> {
+        let mut storage = self.storage.lock().unwrap();
+        for pos in std::mem::take(&mut self.unsaved) {
+            storage.save_chunk(pos, &self.chunks[&pos]);
+        }
+        storage.save_pending(&self.pending)?;
+        storage.flush()
+    }
 
-        // Check blocks from feet position upward
-        let radius_check = radius.ceil() as usize + 1;
+    /// Blocks until every chunk queued for generation has been received.
+    ///
+    /// Used at startup so the player doesn't spawn into an empty world.
+    pub fn finish_loading(&mut self) {
+        while !self.generating.is_empty() {
+            let result = self.workers.recv();
+            self.handle_result(result);
+        }
+    }
 
-        for x in block_x.saturating_sub(radius_check)..=(block_x + radius_check).min(15) {
-            for y in block_y..=(block_y + height as usize).min(15) {
-                for z in block_z.saturating_sub(radius_check)..=(block_z + radius_check).min(15) {
-                    // Skip air blocks
-                    if self.chunk_data[x][y][z] == 0 {
-                        continue;
-                    }
+    /// Stores a finished job, dropping it if it's no longer wanted.
+    fn handle_result(&mut self, result: JobResult) {
+        match result {
+            JobResult::Generated(generated) => {
+                // Chunks that went out of range while generating are dropped
+                if self.generating.remove(&generated.pos) {
+                    self.insert_chunk(*generated);
+                }
+            }
+            JobResult::Meshed {
+                pos,
+                revision,
+                data,
+            } => {
+                if self.mesh_revisions.get(&pos) == Some(&revision) {
+                    self.ready_meshes.insert(pos, data);
+                }
+            }
+        }
+    }
+
+    /// Adds a generated or loaded chunk to the world and decorates it.
+    ///
+    /// A decoration is written once both the chunk it starts in and the chunk it
+    /// lands in exist: directly if the target is loaded, otherwise through the
+    /// pending queue when the target is generated. Loaded neighbours' decorations
+    /// are reapplied, since they were written into an earlier copy of this chunk
+    /// if it has been unloaded without being saved since. Saved chunks already
+    /// hold their own and their neighbours' decorations, so only pending ones are
+    /// applied to them.
+    ///
+    /// The chunk is lit once its own blocks are in place, and light then flows
+    /// across its borders; decorations written into loaded neighbours update the
+    /// light around each block.
+    ///
+    /// # Arguments
+    ///
+    /// * `generated` - The chunk and decorations produced by a worker.
+    fn insert_chunk(&mut self, generated: GeneratedChunk) {
+        let GeneratedChunk {
+            pos,
+            mut chunk,
+            saved,
+            decorations,
+            neighbour_decorations,
+        } = generated;
+
+        let pending = self.pending.take(pos);
+        if !saved || !pending.is_empty() {
+            self.unsaved.insert(pos);
+        }
+        for placement in pending {
+            placement.apply(&mut chunk);
+        }
+        for (neighbour, placements) in neighbour_decorations {
+            if self.chunks.contains_key(&neighbour) {
+                for placement in placements {
+                    placement.apply(&mut chunk);
+                }
+            }
+        }
+        let (own, spilling): (Vec<_>, Vec<_>) = decorations
+            .into_iter()
+            .partition(|placement| placement.chunk() == pos);
+        for placement in own {
+            placement.apply(&mut chunk);
+        }
+
+        light::light_chunk(&mut chunk, &self.registry);
+        self.chunks.insert(pos, chunk);
+        let mut loaded = LoadedChunks::new(&mut self.chunks);
+        light::join_neighbours(&mut loaded, &self.registry, pos);
+        let changed = loaded.changed;
+        self.mark_light_dirty(changed);
 
-                    // Check collision with this block
-                    if self.check_block_collision(feet_position, x, y, z, radius, height) {
-                        return true;
+        // Neighbours may have faces that the new chunk now hides or shades
+        self.dirty.insert(pos);
+        self.dirty.extend(pos.surrounding());
+
+        for placement in spilling {
+            let [x, _, z] = placement.pos;
+            match self.chunks.get_mut(&placement.chunk()) {
+                Some(target) => {
+                    if placement.apply(target) {
+                        self.update_light(placement.pos);
                     }
+                    self.unsaved.insert(placement.chunk());
+                    self.mark_dirty(x, z, local_coord(x), local_coord(z));
                 }
+                None => self.pending.push(placement),
             }
         }
+    }
 
-        false
+    /// Updates the light around a block that was just placed or removed, and
+    /// marks the chunks whose light changed for remeshing.
+    fn update_light(&mut self, pos: [i32; 3]) {
+        let mut loaded = LoadedChunks::new(&mut self.chunks);
+        light::update_block(&mut loaded, &self.registry, pos);
+        let changed = loaded.changed;
+        self.mark_light_dirty(changed);
     }
 
-    fn check_block_collision(
-        &self,
-        feet_position: &Point3<f32>,
-        block_x: usize,
-        block_y: usize,
-        block_z: usize,
-        radius: f32,
-        height: f32,
-    ) -> bool {
-        // Block min/max coordinates
-        let block_min_x = block_x as f32;
-        let block_max_x = block_x as f32 + 1.0;
-        let block_min_y = block_y as f32;
-        let block_max_y = block_y as f32 + 1.0;
-        let block_min_z = block_z as f32;
-        let block_max_z = block_z as f32 + 1.0;
-
-        // Player capsule min/max (from feet position)
-        let player_min_y = feet_position.y;
-        let player_max_y = feet_position.y + height;
-
-        // Check vertical overlap first (faster rejection)
-        if player_max_y <= block_min_y || player_min_y >= block_max_y {
+    /// Marks the chunks holding the given columns as dirty, along with the
+    /// neighbours whose border faces they light.
+    fn mark_light_dirty(&mut self, columns: HashSet<[i32; 2]>) {
+        for [x, z] in columns {
+            self.mark_dirty(x, z, local_coord(x), local_coord(z));
+        }
+    }
+
+    /// Queues meshing of the chunks that changed since the last call and uploads
+    /// finished meshes, nearest to the camera first, up to the per-frame budget.
+    pub fn rebuild_dirty_meshes(&mut self) {
+        for pos in std::mem::take(&mut self.dirty) {
+            let Some(chunk) = self.chunks.get(&pos) else {
+                continue;
+            };
+
+            let blocks = Box::new(Neighbourhood {
+                center: chunk.clone(),
+                neighbours: pos
+                    .surrounding()
+                    .map(|neighbour| self.chunks.get(&neighbour).cloned()),
+            });
+            self.next_revision += 1;
+            self.mesh_revisions.insert(pos, self.next_revision);
+            self.workers.submit(Job::Mesh {
+                pos,
+                revision: self.next_revision,
+                blocks,
+            });
+        }
+
+        let mut ready: Vec<ChunkPos> = self.ready_meshes.keys().copied().collect();
+        ready.sort_by_key(|pos| pos.distance(self.center));
+        for pos in ready.into_iter().take(self.uploads_per_frame) {
+            let data = self.ready_meshes.remove(&pos).unwrap();
+            if data.vertices.is_empty() {
+                self.meshes.remove(&pos);
+            } else {
+                let mesh = Mesh::new(&data.vertices, &CHUNK_VERTEX_LAYOUT);
+                self.meshes.insert(pos, mesh);
+            }
+            if data.translucent.is_empty() {
+                self.translucent_meshes.remove(&pos);
+            } else {
+                let mesh = TranslucentMesh::new(data.translucent);
+                self.translucent_meshes.insert(pos, mesh);
+            }
+        }
+    }
+
+    /// Returns the height of the generated terrain surface in the given column.
+    pub fn surface_height(&self, x: i32, z: i32) -> i32 {
+        self.generator.height_at(x, z)
+    }
+
+    /// Returns the biome of the given column.
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        self.generator.biome_at(x, z)
+    }
+
+    /// Returns the vertical extent of the world.
+    pub fn height(&self) -> WorldHeight {
+        self.generator.height()
+    }
+
+    /// Returns the block types the world is made of.
+    pub fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+
+    /// Returns the block at the given world block coordinates.
+    ///
+    /// Positions in unloaded chunks or outside the world height are air.
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> BlockId {
+        match self.chunks.get(&ChunkPos::from_block(x, z)) {
+            Some(chunk) => chunk.get(local_coord(x), y, local_coord(z)),
+            None => BlockId::AIR,
+        }
+    }
+
+    /// Sets the block at the given world block coordinates, and lets liquid
+    /// around it flow and unsupported blocks around it fall on later ticks.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the block was written, `false` if the position is outside the
+    /// world height or its chunk isn't loaded.
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, id: BlockId) -> bool {
+        if !self.height().contains(y) {
             return false;
         }
+        match self.chunks.get_mut(&ChunkPos::from_block(x, z)) {
+            Some(chunk) => {
+                let (local_x, local_z) = (local_coord(x), local_coord(z));
+                chunk.set(local_x, y, local_z, id);
+                self.unsaved.insert(ChunkPos::from_block(x, z));
+                self.mark_dirty(x, z, local_x, local_z);
+                self.update_light([x, y, z]);
+                self.schedule_updates([x, y, z]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns whether the block containing a point is a liquid.
+    pub fn is_liquid_at(&self, position: Point3<f32>) -> bool {
+        let [x, y, z] = [position.x, position.y, position.z].map(|v| v.floor() as i32);
+        self.registry.get(self.get_block(x, y, z)).liquid
+    }
 
-        // For horizontal collision, treat player as a cylinder
-        let closest_x = feet_position.x.max(block_min_x).min(block_max_x);
-        let closest_z = feet_position.z.max(block_min_z).min(block_max_z);
+    /// Returns the first block along a ray that can be targeted (anything but air
+    /// and liquids), if any is within `max_distance`.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The start of the ray (usually the camera position).
+    /// * `direction` - The direction of the ray.
+    /// * `max_distance` - How far along the ray to search.
+    pub fn raycast(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<RaycastHit> {
+        raycast(origin, direction, max_distance, |x, y, z| {
+            let block = self.get_block(x, y, z);
+            !block.is_air() && !self.registry.get(block).liquid
+        })
+    }
 
-        // Distance from the closest point to player center axis
-        let dx = closest_x - feet_position.x;
-        let dz = closest_z - feet_position.z;
-        let distance_squared = dx * dx + dz * dz;
+    /// Marks the chunk containing a changed block as dirty, along with any
+    /// neighbouring chunk whose border faces the block touches or shades.
+    fn mark_dirty(&mut self, x: i32, z: i32, local_x: usize, local_z: usize) {
+        // How far into a neighbouring chunk the block's influence reaches on each axis
+        let reach = |local: usize| match local {
+            0 => -1..=0,
+            local if local == CHUNK_SIZE - 1 => 0..=1,
+            _ => 0..=0,
+        };
+        for dx in reach(local_x) {
+            for dz in reach(local_z) {
+                self.dirty.insert(ChunkPos::from_block(x + dx, z + dz));
+            }
+        }
+    }
 
-        // Collision if distance is less than radius
-        distance_squared < (radius * radius)
+    /// Sweeps a box through the solid blocks of the world as far as it can move,
+    /// climbing ledges up to `step_height`. Blocks in unloaded chunks don't stop it.
+    ///
+    /// # Arguments
+    ///
+    /// * `aabb` - The box to move.
+    /// * `movement` - How far it wants to move.
+    /// * `step_height` - The tallest ledge it climbs without jumping.
+    ///
+    /// # Returns
+    ///
+    /// How far the box moved and along which axes it was stopped.
+    pub fn move_box(&self, aabb: Aabb, movement: Vector3<f32>, step_height: f32) -> SweptMove {
+        collision::sweep(aabb, movement, step_height, |[x, y, z]| {
+            self.registry.is_solid(self.get_block(x, y, z))
+        })
     }
 
-    /// Draws the world using the given shader.
+    /// Draws the world using the given shader: opaque faces first, then falling
+    /// blocks and items, then translucent faces blended over them.
     ///
     /// # Arguments
     ///
     /// * `shader` - The shader to use for rendering.
+    /// * `eye` - The camera position, which translucent faces are sorted towards.
     /// * `time` - The current time (used for animations).
-    pub fn draw(&self, shader: &Shader, time: f32) {
-        draw_chunk(&self.chunk_data, &self.mesh, shader, time);
+    /// * `show_edges` - Whether to draw the debug wireframe around every opaque block.
+    pub fn draw(&mut self, shader: &Shader, eye: Point3<f32>, time: f32, show_edges: bool) {
+        for (pos, mesh) in &self.meshes {
+            draw_chunk(mesh, *pos, shader, time, show_edges);
+        }
+
+        let falling = self
+            .falling_blocks
+            .iter()
+            .map(|f| (f.block, f.position, 1.0));
+        let items = self.items.iter().map(|item| {
+            let half = ITEM_SIZE / 2.0;
+            let corner = item.position - Vector3::new(half, 0.0, half);
+            (item.block, corner, ITEM_SIZE)
+        });
+        let mut drawn = HashSet::new();
+        for (block, corner, size) in falling.chain(items) {
+            let cell = [corner.x, corner.y, corner.z].map(|v| v.floor() as i32);
+            let light = self.light(cell);
+            drawn.insert((block, light));
+            let mesh = self.entity_meshes.entry((block, light)).or_insert_with(|| {
+                Mesh::new(
+                    &block_vertices(&self.registry, block, light),
+                    &CHUNK_VERTEX_LAYOUT,
+                )
+            });
+            draw_entity(mesh, corner, size, shader);
+        }
+        // Entities move through changing light, so stale meshes would pile up
+        self.entity_meshes.retain(|key, _| drawn.contains(key));
+
+        let translucent = self
+            .translucent_meshes
+            .iter_mut()
+            .map(|(pos, mesh)| (*pos, mesh))
+            .collect();
+        draw_translucent_chunks(translucent, eye, shader);
+    }
+}
+
+impl BlockAccess for World {
+    fn block(&self, [x, y, z]: [i32; 3]) -> BlockId {
+        self.get_block(x, y, z)
+    }
+
+    fn light(&self, [x, y, z]: [i32; 3]) -> Light {
+        match self.chunks.get(&ChunkPos::from_block(x, z)) {
+            Some(chunk) => chunk.light(local_coord(x), y, local_coord(z)),
+            None => Light::DARK,
+        }
+    }
+}
+
+/// Creates a world for tests with the built-in blocks, generated from `seed` and
+/// saved in `dir`, with nothing loaded yet.
+#[cfg(test)]
+pub fn test_world_in(seed: u64, dir: &std::path::Path) -> World {
+    let registry = BlockRegistry::default();
+    let config = crate::config::GenerationConfig {
+        seed,
+        ..Default::default()
+    };
+    let generator = TerrainGenerator::new(&config, &registry);
+    let storage = WorldStorage::open(dir, generator.height(), None, &registry).unwrap();
+    World::new(
+        registry,
+        generator,
+        storage,
+        &WorldConfig {
+            render_distance: 2,
+            ..WorldConfig::default()
+        },
+        &RenderingConfig::default(),
+    )
+}
+
+/// A world for tests saved in a temporary directory, which is removed along with
+/// the world.
+#[cfg(test)]
+pub struct TestWorld {
+    /// Only `None` while being dropped.
+    world: Option<World>,
+    dir: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl TestWorld {
+    /// Creates a world generated from `seed`, with nothing loaded yet.
+    pub fn new(seed: u64) -> Self {
+        let dir = crate::world::storage::temp_save_dir();
+        TestWorld {
+            world: Some(test_world_in(seed, &dir)),
+            dir,
+        }
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestWorld {
+    type Target = World;
+
+    fn deref(&self) -> &World {
+        self.world.as_ref().unwrap()
+    }
+}
+
+#[cfg(test)]
+impl std::ops::DerefMut for TestWorld {
+    fn deref_mut(&mut self) -> &mut World {
+        self.world.as_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestWorld {
+    fn drop(&mut self) {
+        // Stop the workers saving into the directory before removing it
+        drop(self.world.take());
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::liquid::FULL_LIQUID;
+    use crate::world::storage::temp_save_dir;
+
+    /// Loads the chunks around `center` and waits for them to be generated.
+    fn load_around(world: &mut World, center: Point3<f32>) {
+        world.update_loaded_chunks(center);
+        world.finish_loading();
+    }
+
+    fn chunk_center(pos: ChunkPos) -> Point3<f32> {
+        let (origin_x, origin_z) = pos.origin();
+        Point3::new(origin_x as f32 + 8.0, 10.0, origin_z as f32 + 8.0)
+    }
+
+    /// Returns every block of the chunks within one chunk of `center`.
+    fn blocks_around(world: &World, center: ChunkPos) -> Vec<BlockId> {
+        let (origin_x, origin_z) = center.origin();
+        let mut blocks = Vec::new();
+        for x in origin_x - 16..origin_x + 32 {
+            for z in origin_z - 16..origin_z + 32 {
+                for y in world.height().range() {
+                    blocks.push(world.get_block(x, y, z));
+                }
+            }
+        }
+        blocks
+    }
+
+    /// Returns the light of every block of the chunks within one chunk of `center`.
+    fn light_around(world: &World, center: ChunkPos) -> Vec<Light> {
+        let (origin_x, origin_z) = center.origin();
+        let mut lights = Vec::new();
+        for x in origin_x - 16..origin_x + 32 {
+            for z in origin_z - 16..origin_z + 32 {
+                let chunk = &world.chunks[&ChunkPos::from_block(x, z)];
+                for y in world.height().range() {
+                    lights.push(chunk.light(local_coord(x), y, local_coord(z)));
+                }
+            }
+        }
+        lights
+    }
+
+    #[test]
+    fn blocks_are_addressed_across_negative_chunk_borders() {
+        let mut world = TestWorld::new(2);
+        load_around(&mut world, chunk_center(ChunkPos::new(-1, -1)));
+        let stone = world.registry().expect_id("stone");
+        let y = world.height().max_y() - 1;
+        let coords = [-17, -16, -1, 0, 15, 16];
+
+        // Each coordinate lands in its own chunk and cell, and nowhere else
+        for x in coords {
+            for z in coords {
+                assert!(world.set_block(x, y, z, stone));
+                let chunk = &world.chunks[&ChunkPos::from_block(x, z)];
+                assert_eq!(chunk.get(local_coord(x), y, local_coord(z)), stone);
+                for other_x in coords {
+                    for other_z in coords {
+                        let expected = if (other_x, other_z) == (x, z) {
+                            stone
+                        } else {
+                            BlockId::AIR
+                        };
+                        assert_eq!(world.get_block(other_x, y, other_z), expected);
+                    }
+                }
+                assert!(world.set_block(x, y, z, BlockId::AIR));
+            }
+        }
+
+        assert_eq!(ChunkPos::from_block(-1, -16), ChunkPos::new(-1, -1));
+        assert_eq!(ChunkPos::from_block(-17, 16), ChunkPos::new(-2, 1));
+        assert_eq!(coords.map(local_coord), [15, 0, 15, 0, 15, 0]);
+    }
+
+    #[test]
+    fn distant_chunks_are_unloaded() {
+        let mut world = TestWorld::new(2);
+        let start = ChunkPos::new(-1, -1);
+        load_around(&mut world, chunk_center(start));
+        let stone = world.registry().expect_id("stone");
+        let y = world.height().max_y() - 1;
+        assert!(world.set_block(-17, y, -1, stone));
+
+        let far = ChunkPos::new(8, -1);
+        load_around(&mut world, chunk_center(far));
+        assert!(
+            world
+                .chunks
+                .keys()
+                .all(|pos| pos.distance(far) <= world.render_distance + 1)
+        );
+        assert!(!world.chunks.contains_key(&start));
+        assert!(world.get_block(-17, y, -1).is_air());
+        assert!(!world.set_block(-17, y, -1, stone));
+    }
+
+    #[test]
+    fn decorations_do_not_depend_on_load_order() {
+        let seed = 4;
+        let offset = |pos: ChunkPos, x: i32| ChunkPos::new(pos.x + x, pos.z);
+
+        // Find a chunk with a decoration spilling into its western neighbour
+        let generator = Arc::clone(&TestWorld::new(seed).generator);
+        let center = (0..64)
+            .map(|i| ChunkPos::new(i * 7, i * -5))
+            .find(|&pos| {
+                generator
+                    .decorations(pos)
+                    .iter()
+                    .any(|placement| placement.chunk() == offset(pos, -1))
+            })
+            .expect("no decoration crosses a chunk border");
+
+        let mut direct = TestWorld::new(seed);
+        load_around(&mut direct, chunk_center(center));
+
+        // Approach from the east, so chunks are generated in a different order
+        let mut from_east = TestWorld::new(seed);
+        for x in (0..=6).rev() {
+            load_around(&mut from_east, chunk_center(offset(center, x)));
+        }
+
+        // Step away far enough to unload the western neighbour but not the chunk
+        // its decoration starts in, then come back and generate it again
+        let mut revisited = TestWorld::new(seed);
+        load_around(&mut revisited, chunk_center(center));
+        load_around(&mut revisited, chunk_center(offset(center, 3)));
+        assert!(!revisited.chunks.contains_key(&offset(center, -1)));
+        assert!(revisited.chunks.contains_key(&center));
+        load_around(&mut revisited, chunk_center(center));
+
+        let expected = blocks_around(&direct, center);
+        assert!(expected == blocks_around(&from_east, center));
+        assert!(expected == blocks_around(&revisited, center));
+        // Light flows across borders whichever side is loaded first
+        assert!(light_around(&direct, center) == light_around(&from_east, center));
+    }
+
+    #[test]
+    fn decorations_are_generated() {
+        let mut world = TestWorld::new(4);
+        load_around(&mut world, chunk_center(ChunkPos::new(0, 0)));
+
+        let log = world.registry().expect_id("log");
+        let leaves = world.registry().expect_id("leaves");
+        let mut found = HashSet::new();
+        for x in -32..48 {
+            for z in -32..48 {
+                for y in world.height().range() {
+                    found.insert(world.get_block(x, y, z));
+                }
+            }
+        }
+        assert!(found.contains(&log) && found.contains(&leaves));
+    }
+
+    #[test]
+    fn placed_liquid_flows_on_later_ticks() {
+        let mut world = TestWorld::new(3);
+        load_around(&mut world, chunk_center(ChunkPos::new(0, 0)));
+        let (stone, water) = (
+            world.registry.expect_id("stone"),
+            world.registry.expect_id("water"),
+        );
+        let y = world.height().max_y() - 4;
+        world.set_block(3, y, 3, stone);
+        world.set_block(4, y, 3, stone);
+        world.set_block(3, y + 1, 3, water);
+
+        let registry = Arc::clone(&world.registry);
+        let flowing = |level| registry.flowing(water, level);
+        assert!(world.get_block(4, y + 1, 3).is_air());
+        for _ in 0..registry.get(water).flow_delay * 3 {
+            world.tick();
+        }
+
+        // The water spreads across the stone and pours off its edges
+        assert_eq!(world.get_block(4, y + 1, 3), flowing(7));
+        assert_eq!(world.get_block(2, y + 1, 3), flowing(7));
+        assert_eq!(world.get_block(2, y, 3), flowing(FULL_LIQUID));
+        assert_eq!(world.get_block(3, y + 2, 3), BlockId::AIR);
+    }
+
+    #[test]
+    fn unsupported_sand_falls_and_lands() {
+        let mut world = TestWorld::new(3);
+        load_around(&mut world, chunk_center(ChunkPos::new(0, 0)));
+        let (stone, sand) = (
+            world.registry.expect_id("stone"),
+            world.registry.expect_id("sand"),
+        );
+        let y = world.height().max_y() - 4;
+        world.set_block(3, y - 20, 3, stone);
+        world.set_block(3, y, 3, sand);
+        world.set_block(3, y + 1, 3, sand);
+
+        world.tick();
+        assert_eq!(world.get_block(3, y, 3), sand);
+        for _ in 0..200 {
+            world.tick();
+        }
+
+        // Both blocks fall onto the stone, the top one landing on the other
+        assert!(world.falling_blocks.is_empty());
+        assert!(world.items.is_empty());
+        assert!(world.get_block(3, y, 3).is_air());
+        assert_eq!(world.get_block(3, y - 19, 3), sand);
+        assert_eq!(world.get_block(3, y - 18, 3), sand);
+    }
+
+    #[test]
+    fn random_ticks_kill_covered_grass_but_keep_trees() {
+        let mut world = TestWorld::new(5);
+        let center = ChunkPos::new(0, 0);
+        load_around(&mut world, chunk_center(center));
+        let registry = Arc::clone(&world.registry);
+        let (grass, dirt, stone, leaves) = (
+            registry.expect_id("grass"),
+            registry.expect_id("dirt"),
+            registry.expect_id("stone"),
+            registry.expect_id("leaves"),
+        );
+        let leaf_count = |world: &World| {
+            blocks_around(world, center)
+                .iter()
+                .filter(|&&b| b == leaves)
+                .count()
+        };
+        let leaves_before = leaf_count(&world);
+        assert!(leaves_before > 0);
+
+        // Cover every grass block of the centre chunk
+        let mut covered = Vec::new();
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let top = (world.height().min_y()..world.height().max_y())
+                    .rev()
+                    .find(|&y| !world.get_block(x, y, z).is_air())
+                    .unwrap();
+                if world.get_block(x, top, z) == grass {
+                    world.set_block(x, top + 1, z, stone);
+                    covered.push([x, top, z]);
+                }
+            }
+        }
+        assert!(!covered.is_empty());
+
+        let dead = |world: &World| {
+            covered
+                .iter()
+                .any(|&[x, y, z]| world.get_block(x, y, z) == dirt)
+        };
+        let mut ticks = 0;
+        while !dead(&world) {
+            world.tick();
+            ticks += 1;
+            assert!(ticks < 10_000, "covered grass never died");
+        }
+        assert_eq!(leaf_count(&world), leaves_before);
+    }
+
+    #[test]
+    fn changes_survive_unloading_and_restarting() {
+        let dir = temp_save_dir();
+        let origin = chunk_center(ChunkPos::new(0, 0));
+        let mut world = test_world_in(7, &dir);
+        let top = world.height().max_y() - 1;
+        load_around(&mut world, origin);
+        let stone = world.registry().expect_id("stone");
+        assert!(world.set_block(3, top, 5, stone));
+
+        // Walk far enough away to unload the chunk, then come back
+        load_around(&mut world, chunk_center(ChunkPos::new(10, 0)));
+        assert!(!world.chunks.contains_key(&ChunkPos::new(0, 0)));
+        load_around(&mut world, origin);
+        assert_eq!(world.get_block(3, top, 5), stone);
+        assert!(world.set_block(4, top, 5, stone));
+
+        let expected = blocks_around(&world, ChunkPos::new(0, 0));
+        world.save().unwrap();
+        drop(world);
+
+        let mut world = test_world_in(7, &dir);
+        load_around(&mut world, origin);
+        assert!(expected == blocks_around(&world, ChunkPos::new(0, 0)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }